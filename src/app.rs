@@ -0,0 +1,187 @@
+// 标准库导入
+use std::sync::Arc;  // 共享的组件
+
+// 外部库导入
+use actix_web::body::MessageBody;  // 响应体类型
+use actix_web::cookie::Key;  // 会话Cookie签名密钥
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};  // 应用工厂的类型
+use actix_web::middleware::from_fn;  // 用于函数式中间件
+use actix_web::{web, App};  // Web应用
+use derive_more::{Display, Error};  // 用于自动派生Display和Error trait
+
+// 内部模块导入
+use crate::auth::{session_key, session_middleware};  // 会话中间件
+use crate::config::routes;  // 所有路由
+use crate::counters::CounterStore;  // 命名计数器
+use crate::errors::{render_api_errors, AuthError, RepositoryError};  // 错误渲染中间件和初始化错误
+use crate::events::EventHub;  // 事件总线
+use crate::health::{EventHubCheck, Health, RepositoryCheck, TlsCertificateCheck};  // 健康检查
+use crate::jwt::{bearer_auth, JwtKeys};  // JWT认证
+use crate::logging::access_log;  // 访问日志中间件
+use crate::login_guard::LoginGuard;  // 登录防暴力破解
+use crate::metrics::{track_requests, Metrics};  // 服务器指标
+use crate::models::AppState;  // 应用状态
+use crate::rate_limit::{rate_limit, RateLimiter};  // 限流
+use crate::repository::{self, UserRepository};  // 用户存储
+use crate::request_id::request_id;  // 请求ID中间件
+use crate::settings::{AuthSettings, Settings};  // 服务器配置
+use crate::tls::CertReloader;  // 当前使用的TLS证书
+use crate::vhost::{virtual_hosts, VirtualHosts};  // 虚拟主机
+use crate::ws::Rooms;  // WebSocket房间
+
+/// 应用组件初始化错误
+#[derive(Debug, Display, Error)]
+pub enum StartupError {
+    #[display(fmt = "计数器快照读取失败: {_0}")]
+    /// 快照文件存在但无法读取或解析
+    Counters(std::io::Error),
+
+    #[display(fmt = "用户存储初始化失败: {_0}")]
+    /// 无法打开用户存储
+    Storage(RepositoryError),
+
+    #[display(fmt = "JWT配置错误: {_0}")]
+    /// JWT密钥无法加载
+    Jwt(AuthError),
+}
+
+/// 完整应用共享的组件
+///
+/// 启动时按配置创建一次，`build_app`为每个工作线程构建应用时只克隆其中的引用，
+/// 所有监听器的所有工作线程共享同一份状态；集成测试同样通过它构建应用并检查状态
+#[derive(Clone)]
+pub struct AppServices {
+    pub app_name: String,                            // 应用名称
+    pub auth: AuthSettings,                          // 会话配置
+    pub cookie_key: Key,                             // 会话Cookie签名密钥
+    pub counters: web::Data<CounterStore>,           // 命名计数器
+    pub users: web::Data<dyn UserRepository>,        // 用户存储
+    pub jwt_keys: web::Data<JwtKeys>,                // JWT密钥
+    pub event_hub: web::Data<EventHub>,              // 事件总线
+    pub rooms: web::Data<Rooms>,                     // WebSocket房间
+    pub metrics: web::Data<Metrics>,                 // 服务器指标
+    pub health: web::Data<Health>,                   // 健康检查
+    pub rate_limiter: web::Data<RateLimiter>,        // 限流器
+    pub login_guard: web::Data<LoginGuard>,          // 登录防暴力破解
+    pub virtual_hosts: web::Data<VirtualHosts>,      // 虚拟主机表
+}
+
+impl AppServices {
+    /// 按配置创建所有组件
+    ///
+    /// 不启动任何后台任务（计数器快照、限流器和登录记录的清理、证书热更新），
+    /// 由调用方按需启动
+    ///
+    /// # 参数
+    /// * `settings` - 服务器配置
+    /// * `certificates` - 当前使用的TLS证书，提供时注册证书有效期检查；测试中没有证书时传入None
+    ///
+    /// # 返回值
+    /// * 成功时返回所有组件
+    /// * 计数器快照、用户存储或JWT密钥无法加载时返回错误
+    pub fn from_settings(
+        settings: &Settings,
+        certificates: Option<Arc<CertReloader>>,
+    ) -> Result<Self, StartupError> {
+        // 配置了快照文件时从中恢复之前的数值
+        let counters = CounterStore::from_settings(&settings.counters).map_err(StartupError::Counters)?;
+        let users = repository::from_settings(&settings.storage).map_err(StartupError::Storage)?;
+        // RS256模式下从PEM文件读取
+        let jwt_keys = JwtKeys::from_settings(&settings.jwt).map_err(StartupError::Jwt)?;
+        let event_hub = Arc::new(EventHub::from_settings(&settings.events));
+
+        // 注册就绪检查
+        let mut health = Health::new();
+        health.register(RepositoryCheck(users.clone()));
+        if let Some(reloader) = certificates {
+            health.register(TlsCertificateCheck {
+                reloader,
+                warn_days: settings.health.tls_expiry_warn_days,
+            });
+        }
+        health.register(EventHubCheck(event_hub.clone()));
+
+        Ok(AppServices {
+            app_name: settings.app_name.clone(),
+            auth: settings.auth.clone(),
+            cookie_key: session_key(&settings.auth),
+            counters: web::Data::new(counters),
+            users: web::Data::from(users),
+            jwt_keys: web::Data::new(jwt_keys),
+            event_hub: web::Data::from(event_hub),
+            rooms: web::Data::new(Rooms::from_settings(&settings.websocket)),
+            metrics: web::Data::new(Metrics::new()),
+            health: web::Data::new(health),
+            rate_limiter: web::Data::new(RateLimiter::from_settings(&settings.rate_limit)),
+            login_guard: web::Data::new(LoginGuard::from_settings(&settings.login_guard)),
+            virtual_hosts: web::Data::new(VirtualHosts::from_settings(&settings.virtual_hosts)),
+        })
+    }
+}
+
+/// 构建完整的应用
+///
+/// 配置所有中间件、共享状态和路由，`HttpServer`的每个工作线程调用一次，
+/// 集成测试用`actix_web::test::init_service`初始化；
+/// 返回的应用只持有组件的引用计数，不借用`services`
+///
+/// # 参数
+/// * `services` - 共享的组件
+///
+/// # 返回值
+/// * 返回配置好的应用
+pub fn build_app(
+    services: &AppServices,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody + use<>>,
+        Error = actix_web::Error,
+        InitError = (),
+    > + use<>,
+> {
+    App::new()
+        // 添加限流中间件，位于认证之内，按API Key、登录用户或IP识别客户端
+        .wrap(from_fn(rate_limit))
+        // 添加Bearer令牌认证中间件，校验通过的Claims放入请求扩展
+        .wrap(from_fn(bearer_auth))
+        // 添加会话中间件，处理签名的会话Cookie
+        .wrap(session_middleware(&services.auth, services.cookie_key.clone()))
+        // 添加虚拟主机中间件，按Host选择虚拟主机，位于错误渲染之内以便421同样渲染为信封
+        .wrap(from_fn(virtual_hosts))
+        // 添加错误渲染中间件，所有错误统一渲染为带请求ID的JSON信封
+        .wrap(from_fn(render_api_errors))
+        // 添加指标中间件，按路由记录请求数和耗时
+        .wrap(from_fn(track_requests))
+        // 添加访问日志中间件，每个请求输出一行JSON日志
+        .wrap(from_fn(access_log))
+        // 添加请求ID中间件，必须位于错误渲染和访问日志中间件之外
+        .wrap(from_fn(request_id))
+        // 添加应用状态数据
+        .app_data(web::Data::new(AppState {
+            app_name: services.app_name.clone(),  // 设置应用名称
+        }))
+        // 添加命名计数器
+        .app_data(services.counters.clone())
+        // 添加用户存储
+        .app_data(services.users.clone())
+        // 添加JWT密钥
+        .app_data(services.jwt_keys.clone())
+        // 添加事件总线
+        .app_data(services.event_hub.clone())
+        // 添加WebSocket房间
+        .app_data(services.rooms.clone())
+        // 添加服务器指标
+        .app_data(services.metrics.clone())
+        // 添加健康检查
+        .app_data(services.health.clone())
+        // 添加限流器
+        .app_data(services.rate_limiter.clone())
+        // 添加登录防暴力破解
+        .app_data(services.login_guard.clone())
+        // 添加虚拟主机表
+        .app_data(services.virtual_hosts.clone())
+        // 注册所有路由
+        .configure(routes)
+}
@@ -0,0 +1,166 @@
+// 标准库导入
+use std::sync::LazyLock;                                      // 延迟计算的占位哈希
+
+// 外部库导入
+use actix_session::config::PersistentSession;                 // 用于设置会话有效期
+use actix_session::storage::CookieSessionStore;               // 基于Cookie的会话存储
+use actix_session::{SessionExt, SessionMiddleware};           // 会话中间件及请求扩展
+use actix_web::cookie::{time, Key, SameSite};                 // Cookie密钥和属性
+use actix_web::dev::Payload;                                  // 请求体载荷
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};  // 提取器相关类型
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;                                           // Argon2密码哈希算法
+use futures::future::LocalBoxFuture;                          // 异步提取器返回的Future
+use rand::Rng;                                                // 生成占位密码
+
+// 内部模块导入
+use crate::errors::{AuthError, RepositoryError};  // 认证和存储错误类型
+use crate::jwt::Claims;                           // JWT载荷
+use crate::repository::UserRepository;            // 用户存储
+use crate::settings::AuthSettings;                // 认证配置
+
+/// 会话中保存用户ID的键
+///
+/// 保存不可变的ID而不是用户名：用户名可以被修改，删除后也可能被他人重新注册
+pub const SESSION_USER_KEY: &str = "user_id";
+
+/// 计算密码的Argon2哈希
+///
+/// 每次调用都会生成新的随机盐，结果为PHC格式字符串
+/// 计算开销较大，应在`web::block`中调用
+///
+/// # 参数
+/// * `password` - 明文密码
+///
+/// # 返回值
+/// * 成功时返回PHC格式的哈希字符串
+pub fn hash_password(password: &str) -> Result<String, AuthError> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| AuthError::Internal(err.to_string()))
+}
+
+/// 校验密码是否与哈希匹配
+///
+/// 哈希格式不合法时视为不匹配
+///
+/// # 参数
+/// * `password` - 明文密码
+/// * `hash` - PHC格式的哈希字符串
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// 用于校验不存在的用户的哈希
+///
+/// 用户不存在或未设置密码时也对它执行一次完整的校验，
+/// 使各种失败情况的耗时相同，不能通过响应时间判断用户名是否存在
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    let password: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    hash_password(&password).unwrap_or_default()
+});
+
+/// 对一个不可能匹配的哈希执行校验，耗时与真实的校验相同
+///
+/// # 参数
+/// * `password` - 明文密码
+pub fn verify_dummy_password(password: &str) {
+    let _ = verify_password(password, &DUMMY_PASSWORD_HASH);
+}
+
+/// 根据配置生成会话Cookie的签名密钥
+///
+/// 未配置密钥时随机生成，此时重启后所有会话都会失效
+///
+/// # 参数
+/// * `settings` - 认证配置
+pub fn session_key(settings: &AuthSettings) -> Key {
+    match &settings.session_secret {
+        Some(secret) => Key::from(secret.as_bytes()),
+        None => {
+            log::warn!("未配置auth.session_secret，使用随机生成的会话密钥，重启后会话将失效");
+            Key::generate()
+        }
+    }
+}
+
+/// 创建会话中间件
+///
+/// 会话数据保存在签名加密的Cookie中，Cookie仅通过HTTPS发送且脚本不可读
+///
+/// # 参数
+/// * `settings` - 认证配置
+/// * `key` - Cookie签名密钥
+pub fn session_middleware(settings: &AuthSettings, key: Key) -> SessionMiddleware<CookieSessionStore> {
+    SessionMiddleware::builder(CookieSessionStore::default(), key)
+        .cookie_name(settings.cookie_name.clone())
+        .cookie_secure(true)
+        .cookie_http_only(true)
+        .cookie_same_site(SameSite::Lax)
+        .session_lifecycle(
+            PersistentSession::default()
+                .session_ttl(time::Duration::seconds(settings.session_ttl_secs as i64)),
+        )
+        .build()
+}
+
+/// 已登录用户提取器
+///
+/// 优先使用Bearer令牌中间件放入请求扩展的Claims中的用户ID，
+/// 否则从会话中读取登录时写入的用户ID，再到用户存储中查出当前的用户
+/// 处理函数只要声明该参数，匿名请求和已被删除的用户都会被拒绝并返回401
+/// 提取成功后会放入请求扩展，访问日志据此记录登录用户
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub id: i64,           // 当前登录用户的ID
+    pub username: String,  // 当前登录用户的用户名，反映最近一次改名
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            // 同一请求中已经提取过
+            if let Some(user) = req.extensions().get::<AuthenticatedUser>() {
+                return Ok(user.clone());
+            }
+
+            // 已通过Bearer令牌认证的请求使用令牌中的用户，
+            // 否则读取会话；会话不存在、已损坏或没有用户ID时都视为未登录
+            let claims = req.extensions().get::<Claims>().map(Claims::user_id);
+            let id = match claims {
+                Some(id) => id,
+                None => req.get_session().get::<i64>(SESSION_USER_KEY).ok().flatten(),
+            };
+            let Some(id) = id else {
+                return Err(AuthError::Unauthorized.into());
+            };
+
+            // 用户可能已被删除，每次都到存储中确认
+            let repo = web::Data::<dyn UserRepository>::extract(&req).await?;
+            let found = web::block(move || repo.get_by_id(id)).await?;
+            let user = match found {
+                Ok(user) => AuthenticatedUser { id: user.id, username: user.input.username },
+                Err(RepositoryError::NotFound(_)) => return Err(AuthError::Unauthorized.into()),
+                Err(err) => return Err(err.into()),
+            };
+
+            req.extensions_mut().insert(user.clone());
+            Ok(user)
+        })
+    }
+}
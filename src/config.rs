@@ -1,18 +1,132 @@
 // 外部库导入
-use actix_web::{error, guard, web, HttpResponse};  // 用于Web应用配置和HTTP响应
+use actix_web::{guard, http::StatusCode, web, HttpRequest};  // 用于Web应用配置和HTTP响应
+
+// 导入统一的错误类型
+use crate::errors::ApiError;
 
 // 内部模块导入
 // 导入各种路由处理函数
 use crate::handlers::{
+    self,
     // 基本页面处理函数
-    index, index2, index3,
+    index, index2, index3, users_site, www_site,
+    first_hello, echo, manual_hello, index_resource, my_struct_test, process_data,
+    // 参数提取处理函数
+    path_test, path_test_by_struct, query_test, process_form,
+    // 认证处理函数
+    register, login, logout, issue_token, refresh_token,
+    // 用户资源处理函数
+    get_user, updata_user, delete_user, list_users, create_user,
+    // 事件、计数器和运维处理函数
+    stream_handler, ws_handler, get_counter, update_counter,
+    metrics_handler, readyz, client_identity,
+    // 文档处理函数
+    openapi_json, api_docs,
     // 错误演示处理函数
     index_by_my_new_error_internal,
     index_by_my_new_error_timeout,
     index_by_my_new_error_bad_client_data,
     index_by_simple_error,
-    index_by_user_facing_error
+    index_by_user_facing_error,
+    index_by_my_error,
+    // 明文HTTP监听器的处理函数
+    healthz, not_found, redirect_to_https
 };
+// 导入监听器配置
+use crate::settings::HttpMode;
+// 导入虚拟主机守卫和明文HTTP重定向的目标
+use crate::vhost::{virtual_host, HttpsRedirect};
+
+/// 完整应用的路由配置函数
+///
+/// 注册所有路由和默认的404处理函数；
+/// 新增的路由需要同时加入`openapi::ApiDoc`，`tests/openapi.rs`会检查没有遗漏
+///
+/// # 参数
+/// * `cfg` - 服务配置引用，用于注册路由
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        // 配置路由组
+        .configure(config)         // 配置/app路径下的路由
+        .configure(config_error)   // 配置/error路径下的路由
+
+        // 注册各个路由处理函数
+        .service(first_hello)          // 处理根路径"/"
+        .configure(config2)            // 配置/app2路径下的路由
+        .service(echo)                 // 处理POST /echo
+        .service(path_test)            // 处理GET /path/{user_id}/{name}
+        .service(path_test_by_struct)  // 处理GET /path2/{user_id}/{name}
+        .service(query_test)           // 处理GET /query
+        .service(register)             // 处理POST /register
+        .service(login)                // 处理POST /login
+        .service(logout)               // 处理POST /logout
+        .service(issue_token)          // 处理POST /token
+        .service(refresh_token)        // 处理POST /token/refresh
+        .service(my_struct_test)       // 处理GET /my_struct
+        .service(stream_handler)       // 处理GET /sse
+        .service(ws_handler)           // 处理GET /ws
+        .service(get_counter)          // 处理GET /counters/{name}
+        .service(update_counter)       // 处理POST /counters/{name}
+        .service(metrics_handler)      // 处理GET /metrics
+        .service(healthz)              // 处理GET /healthz
+        .service(readyz)               // 处理GET /readyz
+        .service(client_identity)      // 处理GET /tls/client
+        .service(openapi_json)         // 处理GET /openapi.json
+        .service(api_docs)             // 处理GET /docs
+        .service(process_data)         // 处理GET /process
+        .service(index_by_my_error)    // 处理GET /first_error
+        .service(process_form)         // 处理GET /form_test
+        // 注册一个简单的资源路由，路径为"/perix"
+        // 当访问 /perix 时，所有HTTP方法的请求都会被转发到index_resource处理函数
+        .service(web::resource("/perix").to(index_resource))
+
+        // 注册一个带路径参数的复杂资源路由
+        .service(
+            // 定义资源路径为"user/{name}"，其中{name}是动态路径参数
+            // 例如：user/alice、user/bob等都会匹配这个路由
+            web::resource("user/{name}")
+            // 为该路由指定名称"user_detail"，可用于反向URL生成
+            // 例如：req.url_for("user_detail", &["alice"]) 会生成 /user/alice
+            .name("user_detail")
+            // 添加请求守卫(guard)，只有当请求头中的Content-Type为"application/json"时才会匹配该路由
+            // 如果请求头不符合条件，路由匹配会失败，请求会继续尝试匹配其他路由
+            // 这对于确保只处理特定格式的请求非常有用，例如只接受JSON格式的数据
+            .guard(guard::Header("content-type", "application/json"))
+            // 配置GET请求的处理函数
+            // 当收到 GET /user/{name} 请求时，调用get_user函数处理
+            .route(web::get().to(get_user))
+            // 配置PUT请求的处理函数
+            // 当收到 PUT /user/{name} 请求时，调用updata_user函数处理
+            // 注：这里可能是拼写错误，应为update_user而非updata_user
+            .route(web::put().to(updata_user))
+            // 配置DELETE请求的处理函数
+            .route(web::delete().to(delete_user))
+        )
+        // 注册用户集合资源
+        .service(
+            web::resource("/users")
+            .name("user_list")
+            // GET /users 列出所有用户
+            .route(web::get().to(list_users))
+            // POST /users 创建用户，请求体必须是JSON
+            .route(
+                web::post()
+                    .guard(guard::Header("content-type", "application/json"))
+                    .to(create_user),
+            )
+        )
+        // 手动注册路由，不使用宏
+        .route("/hey", web::get().to(manual_hello))
+
+        // 配置带有JSON配置的路由
+        .service(
+            web::resource("/config")
+                .app_data(json_config(4096))  // 设置JSON请求体最大长度为4096字节
+                .route(web::post().to(handlers::json_test)),  // 设置POST处理函数
+        )
+        // 没有路由匹配时返回统一格式的404
+        .default_service(web::to(not_found));
+}
 
 /// 应用主路由配置函数
 ///
@@ -37,10 +151,9 @@ pub fn config(cfg: &mut web::ServiceConfig) {
                 // 空路径表示/app本身
                 "",
                 web::get()
-                    // 只有当Host头为"users.rust-lang.org"时才匹配
-                    .guard(guard::Host("users.rust-lang.org"))
-                    // 使用内联异步闭包处理请求
-                    .to(|| async { HttpResponse::Ok().body("users site") }),
+                    // 只有请求属于虚拟主机表中的"users"时才匹配（默认为users.rust-lang.org）
+                    .guard(virtual_host("users"))
+                    .to(users_site),
             ),
     );
 }
@@ -66,13 +179,12 @@ pub fn config2(cfg: &mut web::ServiceConfig) {
             // 注册带有主机守卫的路由
             .route(
                 // 空路径表示/app2本身
-                // 测试这个守卫路由使用： curl -H "Host: www.rust-lang.org" http://127.0.0.1:8087/app2
+                // 测试这个守卫路由使用： curl -k -H "Host: www.rust-lang.org" https://127.0.0.1:8087/app2
                 "",
                 web::get()
-                    // 只有当Host头为"www.rust-lang.org"时才匹配
-                    .guard(guard::Host("www.rust-lang.org"))
-                    // 使用内联异步闭包处理请求
-                    .to(|| async { HttpResponse::Ok().body("www site") }),
+                    // 只有请求属于虚拟主机表中的"www"时才匹配（默认为www.rust-lang.org）
+                    .guard(virtual_host("www"))
+                    .to(www_site),
             ),
     );
 }
@@ -108,6 +220,31 @@ pub fn config_error(cfg: &mut web::ServiceConfig) {
     );
 }
 
+/// 明文HTTP监听器路由配置函数
+///
+/// redirect模式下所有请求都308重定向到HTTPS上的同一路径，只重定向到本服务器提供的主机；
+/// health模式下只提供/healthz，供只能探测明文HTTP的负载均衡使用，其他路径返回404
+///
+/// # 参数
+/// * `mode` - 明文HTTP监听器的行为
+/// * `redirect` - 重定向的目标端口和允许的主机
+///
+/// # 返回值
+/// * 返回用于`App::configure`的配置函数
+pub fn config_plain_http(mode: HttpMode, redirect: HttpsRedirect) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| match mode {
+        HttpMode::Redirect => {
+            cfg.default_service(web::to(move |req: HttpRequest| {
+                let redirect = redirect.clone();
+                async move { redirect_to_https(&req, &redirect) }
+            }));
+        }
+        HttpMode::Health => {
+            cfg.service(healthz).default_service(web::to(not_found));
+        }
+    }
+}
+
 /// JSON配置函数
 ///
 /// 创建自定义JSON配置，设置最大请求体大小和错误处理
@@ -125,15 +262,13 @@ pub fn json_config(limit: usize) -> web::JsonConfig {
         // 自定义错误处理器
         .error_handler(|err, _| {
             // 处理JSON解析错误
-            // 打印错误信息到控制台
-            println!("JSON error: {}", err);
-
-            // 创建一个内部错误，返回409 Conflict状态码
-            // 将原始错误包装在自定义响应中
-            error::InternalError::from_response(
-                err,
-                HttpResponse::Conflict().body("JSON error")
-            )
-            .into()  // 转换为actix_web::Error类型
+            // 记录错误信息到日志
+            log::warn!("JSON error: {}", err);
+
+            // 返回409 Conflict状态码，使用统一的错误信封
+            // 原始的解析错误放在details中
+            ApiError::new(StatusCode::CONFLICT, "invalid_json", "JSON error")
+                .with_details(serde_json::Value::String(err.to_string()))
+                .into()  // 转换为actix_web::Error类型
         })
 }
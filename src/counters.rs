@@ -0,0 +1,279 @@
+// 标准库导入
+use std::collections::{BTreeMap, HashMap};          // 快照内容和计数器表
+use std::path::{Path, PathBuf};                     // 快照文件路径
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};  // 无锁计数
+use std::sync::{Arc, RwLock};                       // 计数器表的读写锁
+use std::time::Duration;                            // 快照间隔
+
+// 内部模块导入
+use crate::errors::CounterLimitError;               // 计数器数量达到上限
+use crate::settings::CounterSettings;               // 命名计数器配置
+
+/// 命名计数器
+///
+/// 计数器在第一次修改时创建，读取不存在的计数器得到0；
+/// 数量达到`max_counters`后不能再创建新的计数器，快照中恢复的计数器也计入上限
+///
+/// 计数本身是无锁的原子操作：已存在的计数器只需要读锁就能拿到，
+/// 只有创建新计数器时才需要写锁，热点计数器之间互不阻塞；
+/// 数值使用饱和运算，到达i64的上下限后不再变化，不会溢出回绕
+///
+/// 配置了快照文件时，启动时从文件恢复，之后定期把有变化的数值写回文件
+pub struct CounterStore {
+    counters: RwLock<HashMap<String, Arc<AtomicI64>>>,  // 计数器名到数值
+    dirty: AtomicBool,                                  // 上次快照之后是否有修改
+    snapshot_file: Option<PathBuf>,                     // 快照文件路径
+    snapshot_interval: Duration,                        // 快照间隔
+    max_counters: usize,                                // 计数器数量上限
+}
+
+impl CounterStore {
+    /// 根据配置创建计数器，快照文件存在时从中恢复
+    ///
+    /// # 参数
+    /// * `settings` - 命名计数器配置
+    ///
+    /// # 返回值
+    /// * 快照文件无法读取或格式错误时返回io::Error，避免用空数据覆盖原有快照
+    pub fn from_settings(settings: &CounterSettings) -> std::io::Result<Self> {
+        let counters = match &settings.snapshot_file {
+            Some(path) if path.exists() => load_snapshot(path)?,
+            _ => HashMap::new(),
+        };
+
+        Ok(CounterStore {
+            counters: RwLock::new(counters),
+            dirty: AtomicBool::new(false),
+            snapshot_file: settings.snapshot_file.clone(),
+            snapshot_interval: Duration::from_secs(settings.snapshot_interval_secs),
+            max_counters: settings.max_counters,
+        })
+    }
+
+    /// 读取计数器的当前值，不存在时返回0
+    pub fn get(&self, name: &str) -> i64 {
+        self.read()
+            .get(name)
+            .map_or(0, |counter| counter.load(Ordering::Relaxed))
+    }
+
+    /// 给计数器加上`delta`（可以为负数），返回修改后的值
+    ///
+    /// # 参数
+    /// * `name` - 计数器名
+    /// * `delta` - 增量
+    ///
+    /// # 返回值
+    /// * 计数器不存在且数量已达上限时返回CounterLimitError
+    pub fn add(&self, name: &str, delta: i64) -> Result<i64, CounterLimitError> {
+        let counter = self.counter(name)?;
+        // fetch_update在并发修改时会重试，闭包总是返回Some，因此不会失败
+        let previous = counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |value| Some(value.saturating_add(delta)))
+            .unwrap_or_else(|value| value);
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(previous.saturating_add(delta))
+    }
+
+    /// 将计数器重置为0
+    pub fn reset(&self, name: &str) {
+        if let Some(counter) = self.read().get(name) {
+            counter.store(0, Ordering::Relaxed);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 所有计数器的当前值，按名称排序
+    pub fn all(&self) -> BTreeMap<String, i64> {
+        self.read()
+            .iter()
+            .map(|(name, counter)| (name.clone(), counter.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// 有修改时把所有计数器写入快照文件
+    ///
+    /// 先写入临时文件再重命名，写到一半崩溃也不会破坏原有快照
+    /// 没有配置快照文件时什么也不做
+    pub fn snapshot(&self) -> std::io::Result<()> {
+        let Some(path) = &self.snapshot_file else {
+            return Ok(());
+        };
+        // 先清除标记再读取数值，期间的修改会留到下一次快照
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let result = write_snapshot(path, &self.all());
+        if result.is_err() {
+            // 写入失败时保留标记，下次重试
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// 定期写入快照，直到进程退出
+    ///
+    /// 文件读写放到阻塞线程池中执行，写入失败只记录日志
+    pub async fn run_snapshots(self: Arc<Self>) {
+        if self.snapshot_file.is_none() {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(self.snapshot_interval);
+        // 第一次tick立即完成，跳过
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let store = self.clone();
+            match tokio::task::spawn_blocking(move || store.snapshot()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => log::error!("计数器快照写入失败: {}", err),
+                Err(err) => log::error!("计数器快照任务异常: {}", err),
+            }
+        }
+    }
+
+    /// 获取计数器，不存在时在上限之内创建
+    fn counter(&self, name: &str) -> Result<Arc<AtomicI64>, CounterLimitError> {
+        if let Some(counter) = self.read().get(name) {
+            return Ok(counter.clone());
+        }
+        let mut counters = self.counters.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // 等待写锁期间可能已被其他线程创建
+        if let Some(counter) = counters.get(name) {
+            return Ok(counter.clone());
+        }
+        if counters.len() >= self.max_counters {
+            return Err(CounterLimitError { max: self.max_counters });
+        }
+        Ok(counters.entry(name.to_string()).or_default().clone())
+    }
+
+    /// 获取计数器表的读锁
+    ///
+    /// 锁内只做简单的表操作，不会panic，锁中毒时直接沿用内部数据
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, Arc<AtomicI64>>> {
+        self.counters.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 读取快照文件
+fn load_snapshot(path: &Path) -> std::io::Result<HashMap<String, Arc<AtomicI64>>> {
+    let content = std::fs::read_to_string(path)?;
+    let values: BTreeMap<String, i64> = serde_json::from_str(&content).map_err(|err| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err))
+    })?;
+    log::info!("从{}恢复了{}个计数器", path.display(), values.len());
+
+    Ok(values
+        .into_iter()
+        .map(|(name, value)| (name, Arc::new(AtomicI64::new(value))))
+        .collect())
+}
+
+/// 写入快照文件
+fn write_snapshot(path: &Path, values: &BTreeMap<String, i64>) -> std::io::Result<()> {
+    let content = serde_json::to_string_pretty(values)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个测试使用自己的快照文件，测试结束时删除
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("counters-{}-{}.json", name, std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn store(snapshot_file: Option<PathBuf>) -> CounterStore {
+        CounterStore::from_settings(&CounterSettings { snapshot_file, ..CounterSettings::default() }).unwrap()
+    }
+
+    #[test]
+    fn counters_are_created_on_first_write_and_saturate() {
+        let counters = store(None);
+        assert_eq!(counters.get("visits"), 0);
+        assert_eq!(counters.add("visits", 2).unwrap(), 2);
+        assert_eq!(counters.add("visits", -5).unwrap(), -3);
+
+        assert_eq!(counters.add("big", i64::MAX).unwrap(), i64::MAX);
+        assert_eq!(counters.add("big", 1).unwrap(), i64::MAX);
+        assert_eq!(counters.add("small", i64::MIN).unwrap(), i64::MIN);
+        assert_eq!(counters.add("small", -1).unwrap(), i64::MIN);
+
+        counters.reset("visits");
+        // 重置不存在的计数器不会创建它
+        counters.reset("missing");
+        let all = counters.all();
+        assert_eq!(all.get("visits"), Some(&0));
+        assert!(!all.contains_key("missing"));
+    }
+
+    #[test]
+    fn snapshot_round_trips_and_skips_unchanged_state() {
+        let file = TempFile::new("round-trip");
+        let counters = store(Some(file.0.clone()));
+
+        // 没有修改时不写文件
+        counters.snapshot().unwrap();
+        assert!(!file.0.exists());
+
+        counters.add("visits", 7).unwrap();
+        counters.add("likes", 1).unwrap();
+        counters.snapshot().unwrap();
+
+        let restored = store(Some(file.0.clone()));
+        assert_eq!(restored.all(), counters.all());
+    }
+
+    #[test]
+    fn corrupt_snapshot_is_rejected() {
+        let file = TempFile::new("corrupt");
+        std::fs::write(&file.0, "not json").unwrap();
+
+        let err = CounterStore::from_settings(&CounterSettings {
+            snapshot_file: Some(file.0.clone()),
+            ..CounterSettings::default()
+        })
+        .err()
+        .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn new_counters_are_capped() {
+        let counters = CounterStore::from_settings(&CounterSettings {
+            snapshot_file: None,
+            max_counters: 2,
+            ..CounterSettings::default()
+        })
+        .unwrap();
+        counters.add("a", 1).unwrap();
+        counters.add("b", 1).unwrap();
+
+        let err = counters.add("c", 1).unwrap_err();
+        assert_eq!(err.max, 2);
+        assert_eq!(counters.all().len(), 2);
+        // 已有的计数器不受上限影响
+        assert_eq!(counters.add("a", 1).unwrap(), 2);
+        counters.reset("b");
+        assert_eq!(counters.get("b"), 0);
+    }
+}
@@ -1,22 +1,352 @@
 // 外部库导入
 use actix_web::{error, http, HttpResponse, ResponseError};  // 用于错误处理和HTTP响应
-use actix_web::body::BoxBody;  // 用于HTTP响应体
+use actix_web::body::{BoxBody, MessageBody};  // 用于HTTP响应体
+use actix_web::dev::{ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::http::header::{self, Header, HeaderValue};  // 请求头解析
+use actix_web::middleware::Next;  // 中间件链中的下一个服务
+use actix_web::HttpRequest;  // 用于内容协商
 use derive_more::{Display, Error};  // 用于自动派生Display和Error trait
+use serde::Serialize;  // 用于序列化错误信封
+use utoipa::ToSchema;  // 用于在OpenAPI文档中描述错误响应
+
+// 内部模块导入
+use crate::i18n::{self, Locale};  // 错误描述的本地化
+use crate::request_id::RequestId;  // 请求ID
+
+/// 统一的API错误
+///
+/// 所有自定义错误都会转换为ApiError，渲染为同一种JSON信封：
+/// `{"code": "...", "message": "...", "field": "...", "details": ..., "request_id": "..."}`
+/// 其中`code`是稳定的错误码，客户端应以它而不是`message`作判断；
+/// `field`和`details`只在有内容时出现
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: http::StatusCode,           // HTTP状态码
+    pub code: String,                       // 稳定的错误码，例如"validation_error"
+    pub message: String,                    // 给人看的错误描述
+    pub field: Option<String>,              // 出错的字段
+    pub details: Option<serde_json::Value>, // 附加的结构化信息
+}
+
+/// 错误信封的JSON结构
+///
+/// 在OpenAPI文档中以`ErrorEnvelope`的名称出现
+#[derive(Serialize, ToSchema)]
+#[schema(as = ErrorEnvelope)]
+pub struct Envelope<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a serde_json::Value>,
+    request_id: Option<&'a str>,
+}
+
+/// RFC 7807问题详情文档的JSON结构
+///
+/// 除标准字段外，还携带`code`、`field`、`details`和`request_id`扩展成员
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDetails<'a> {
+    #[serde(rename = "type")]
+    type_: String,
+    title: &'a str,
+    status: u16,
+    detail: &'a str,
+    instance: &'a str,
+    code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a serde_json::Value>,
+    request_id: Option<&'a str>,
+}
+
+/// 问题详情文档的媒体类型
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// 错误响应的渲染格式
+///
+/// 由请求的Accept头协商得出
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Envelope,  // 本服务的JSON信封（默认）
+    Problem,   // RFC 7807 application/problem+json
+    Text,      // 纯文本，只包含错误描述
+}
+
+impl ErrorFormat {
+    /// 根据Accept头选择错误响应格式
+    ///
+    /// 按q值从高到低依次尝试，q=0的类型视为不可接受；
+    /// 没有Accept头、无法解析或没有支持的类型时使用JSON信封
+    ///
+    /// # 参数
+    /// * `req` - HTTP请求
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let Ok(accept) = header::Accept::parse(req) else {
+            return ErrorFormat::Envelope;
+        };
+
+        let mut items: Vec<_> = accept
+            .iter()
+            .filter(|item| item.quality > header::Quality::ZERO)
+            .collect();
+        // 稳定排序，q值相同时保持客户端给出的顺序
+        items.sort_by_key(|item| std::cmp::Reverse(item.quality));
+
+        for item in items {
+            match (item.item.type_().as_str(), item.item.subtype().as_str(), item.item.suffix()) {
+                ("application", "problem", Some(suffix)) if suffix.as_str() == "json" => {
+                    return ErrorFormat::Problem;
+                }
+                ("application", "json", _) | ("application", "*", _) | ("*", "*", _) => {
+                    return ErrorFormat::Envelope;
+                }
+                ("text", "plain", _) | ("text", "*", _) => return ErrorFormat::Text,
+                _ => {}
+            }
+        }
+        ErrorFormat::Envelope
+    }
+}
+
+impl ApiError {
+    /// 创建一个ApiError
+    ///
+    /// # 参数
+    /// * `status` - HTTP状态码
+    /// * `code` - 稳定的错误码
+    /// * `message` - 错误描述
+    pub fn new(status: http::StatusCode, code: impl Into<String>, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code: code.into(),
+            message: message.into(),
+            field: None,
+            details: None,
+        }
+    }
+
+    /// 根据状态码创建通用的ApiError
+    ///
+    /// 用于没有实现自定义转换的错误（例如actix-web内置的提取器错误），
+    /// 错误码由状态码的标准原因短语生成，例如404对应"not_found"
+    pub fn from_status(status: http::StatusCode, message: impl Into<String>) -> Self {
+        let code = status
+            .canonical_reason()
+            .unwrap_or("error")
+            .to_ascii_lowercase()
+            .replace([' ', '-'], "_");
+        ApiError::new(status, code, message)
+    }
+
+    /// 设置出错的字段
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// 设置附加的结构化信息
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// 按目标语言翻译错误描述
+    ///
+    /// 以错误码查找消息目录，`field`和`details`中的字符串、数字成员作为占位符参数；
+    /// 目录中没有该错误码时保留原始描述
+    ///
+    /// # 参数
+    /// * `locale` - 目标语言
+    pub fn localize(mut self, locale: Locale) -> Self {
+        let mut owned: Vec<(&str, String)> = Vec::new();
+        if let Some(field) = &self.field {
+            owned.push(("field", field.clone()));
+        }
+        if let Some(serde_json::Value::Object(details)) = &self.details {
+            owned.extend(details.iter().filter_map(|(key, value)| match value {
+                serde_json::Value::String(value) => Some((key.as_str(), value.clone())),
+                serde_json::Value::Number(value) => Some((key.as_str(), value.to_string())),
+                _ => None,
+            }));
+        }
+        let args: Vec<(&str, &str)> = owned.iter().map(|(key, value)| (*key, value.as_str())).collect();
+
+        if let Some(message) = i18n::translate(&self.code, locale, &args) {
+            self.message = message;
+        }
+        self
+    }
+
+    /// 渲染JSON信封
+    ///
+    /// # 参数
+    /// * `request_id` - 当前请求的ID，没有时输出null
+    pub fn to_json(&self, request_id: Option<&str>) -> String {
+        let envelope = Envelope {
+            code: &self.code,
+            message: &self.message,
+            field: self.field.as_deref(),
+            details: self.details.as_ref(),
+            request_id,
+        };
+        // 信封只包含字符串和JSON值，序列化不会失败
+        serde_json::to_string(&envelope).unwrap_or_default()
+    }
+
+    /// 渲染RFC 7807问题详情文档
+    ///
+    /// `type`为由错误码生成的URN，`title`为状态码的标准原因短语
+    ///
+    /// # 参数
+    /// * `request_id` - 当前请求的ID
+    /// * `instance` - 出错的请求路径
+    pub fn to_problem_json(&self, request_id: Option<&str>, instance: &str) -> String {
+        let problem = ProblemDetails {
+            type_: format!("urn:web_learning:error:{}", self.code),
+            title: self.status.canonical_reason().unwrap_or("Error"),
+            status: self.status.as_u16(),
+            detail: &self.message,
+            instance,
+            code: &self.code,
+            field: self.field.as_deref(),
+            details: self.details.as_ref(),
+            request_id,
+        };
+        serde_json::to_string(&problem).unwrap_or_default()
+    }
+
+    /// 按指定格式渲染错误
+    ///
+    /// # 参数
+    /// * `format` - 渲染格式
+    /// * `request_id` - 当前请求的ID
+    /// * `instance` - 出错的请求路径
+    ///
+    /// # 返回值
+    /// * 返回(Content-Type, 响应体)
+    pub fn render(&self, format: ErrorFormat, request_id: Option<&str>, instance: &str) -> (&'static str, String) {
+        match format {
+            ErrorFormat::Envelope => ("application/json", self.to_json(request_id)),
+            ErrorFormat::Problem => (PROBLEM_JSON, self.to_problem_json(request_id, instance)),
+            ErrorFormat::Text => ("text/plain; charset=utf-8", self.message.clone()),
+        }
+    }
+}
+
+/// 为ApiError实现Display trait
+///
+/// 输出错误描述
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// 为ApiError实现ResponseError trait
+///
+/// 生成JSON信封响应，同时把ApiError本身放入响应扩展，
+/// 由`render_api_errors`中间件补上请求ID
+impl ResponseError for ApiError {
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        let mut res = HttpResponse::build(self.status)
+            .content_type("application/json")
+            .body(self.to_json(None));
+        res.extensions_mut().insert(self.clone());
+        res
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        self.status
+    }
+}
+
+/// API错误渲染中间件
+///
+/// 处理函数和内层中间件产生的错误响应都会在这里统一渲染，
+/// 格式按Accept头协商（见ErrorFormat），默认为带请求ID的JSON信封；
+/// 错误描述按请求的语言（见Locale::resolve）翻译，并通过Content-Language告知客户端
+/// （内层中间件应通过`ServiceRequest::error_response`返回错误响应，而不是返回Err）：
+/// * 自定义错误通过响应扩展中的ApiError渲染
+/// * actix-web内置错误（路径、查询参数解析失败等）按状态码生成通用的ApiError
+/// * 处理函数主动构造的普通响应（没有附带错误）保持原样
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `next` - 中间件链中的下一个服务
+pub async fn render_api_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    // 注意：路由匹配前不能克隆HttpRequest，请求信息从响应中取回
+    let res = next.call(req).await?.map_into_boxed_body();
+
+    let status = res.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(res);
+    }
+
+    let locale = Locale::resolve(res.request());
+    let api_error = match res.response().extensions().get::<ApiError>() {
+        Some(api_error) => Some(api_error.clone()),
+        None => res.response().error().map(|err| {
+            // 服务器错误不对外暴露内部细节
+            let message = if status.is_server_error() {
+                status.canonical_reason().unwrap_or("Internal Server Error").to_string()
+            } else {
+                err.to_string()
+            };
+            ApiError::from_status(status, message)
+        }),
+    };
+    let Some(api_error) = api_error else {
+        return Ok(res);
+    };
+    let api_error = api_error.localize(locale);
+
+    let format = ErrorFormat::negotiate(res.request());
+    let request_id = RequestId::of(res.request());
+    let instance = res.request().path().to_string();
+    let (content_type, body) =
+        api_error.render(format, request_id.as_ref().map(|id| id.0.as_str()), &instance);
+
+    Ok(res.map_body(|head, _| {
+        head.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        head.headers.insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale.tag()));
+        // 错误响应的格式和语言取决于Accept和Accept-Language头，需要告知缓存
+        head.headers.insert(header::VARY, HeaderValue::from_static("accept, accept-language"));
+        BoxBody::new(body)
+    }))
+}
 
 /// 简单错误结构体
 ///
 /// 最基本的错误类型，只包含一个错误消息
-/// 使用默认的ResponseError实现，返回500 Internal Server Error
+/// 默认返回500 Internal Server Error
 #[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
 pub struct MyError {
     pub name: &'static str,  // 错误消息，使用静态生命周期字符串
 }
 
+/// 将MyError转换为ApiError
+impl From<&MyError> for ApiError {
+    fn from(err: &MyError) -> Self {
+        ApiError::new(http::StatusCode::INTERNAL_SERVER_ERROR, "my_error", err.name)
+    }
+}
+
 /// 为MyError实现ResponseError trait
 ///
-/// 使用默认实现，不自定义错误响应
-/// 默认返回500 Internal Server Error状态码
-impl error::ResponseError for MyError {}
+/// 使用默认的500 Internal Server Error状态码，响应体为统一的错误信封
+impl error::ResponseError for MyError {
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        ApiError::from(self).error_response()
+    }
+}
 
 /// 枚举错误类型
 ///
@@ -34,6 +364,18 @@ pub enum MyNewError {
     BadClientData,                // 客户端数据错误
 }
 
+/// 将MyNewError转换为ApiError
+impl From<&MyNewError> for ApiError {
+    fn from(err: &MyNewError) -> Self {
+        let code = match err {
+            MyNewError::InternalError => "internal_error",
+            MyNewError::Timeout => "timeout",
+            MyNewError::BadClientData => "bad_client_data",
+        };
+        ApiError::new(err.status_code(), code, err.to_string())
+    }
+}
+
 /// 为MyNewError实现ResponseError trait
 ///
 /// 自定义错误响应和状态码
@@ -41,14 +383,9 @@ impl error::ResponseError for MyNewError {
     /// 定义当错误发生时如何生成HTTP响应
     ///
     /// # 返回值
-    /// * 返回包含错误信息的HTTP响应
+    /// * 返回包含统一错误信封的HTTP响应
     fn error_response(&self) -> HttpResponse<BoxBody> {
-        // 使用self.status_code()获取对应的HTTP状态码，构建响应
-        HttpResponse::build(self.status_code())
-            // 设置响应头Content-Type为application/json
-            .content_type("application/json")
-            // 响应体内容为错误的字符串描述（即枚举的Display实现）
-            .body(self.to_string())
+        ApiError::from(self).error_response()
     }
 
     /// 定义每种错误对应的HTTP状态码
@@ -85,9 +422,42 @@ pub enum UserError {
     #[display(fmt = "验证错误: {field}")]  // 定义Display输出格式，包含字段名
     /// 验证错误变体
     ///
-    /// 包含一个field字段，指示哪个字段验证失败
+    /// field为所有验证失败的字段，多个字段以", "分隔
+    /// violations逐条列出失败的字段和规则
     /// 这里的花括号用于定义结构体变体（struct variant）
-    ValidationError { field: String },  // 表示ValidationError变体携带一个名为field的String字段
+    ValidationError {
+        field: String,                    // 验证失败的字段
+        #[error(not(source))]
+        violations: Vec<FieldViolation>,  // 每条失败的规则
+    },
+}
+
+/// 一条字段校验失败记录
+///
+/// 出现在验证错误的details.violations中，例如
+/// `{"field": "email", "rule": "email", "params": {}}`
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldViolation {
+    pub field: String,                                        // 字段路径，嵌套字段以"."连接
+    pub rule: String,                                         // 失败的规则，例如"length"、"email"
+    pub params: serde_json::Map<String, serde_json::Value>,   // 规则参数，例如{"min": 3}
+}
+
+/// 将UserError转换为ApiError
+impl From<&UserError> for ApiError {
+    fn from(err: &UserError) -> Self {
+        match err {
+            UserError::ValidationError { field, violations } => {
+                let api_error = ApiError::new(err.status_code(), "validation_error", err.to_string())
+                    .with_field(field.clone());
+                if violations.is_empty() {
+                    api_error
+                } else {
+                    api_error.with_details(serde_json::json!({ "violations": violations }))
+                }
+            }
+        }
+    }
 }
 
 /// 为UserError实现ResponseError trait
@@ -97,12 +467,9 @@ impl error::ResponseError for UserError {
     /// 当发生UserError错误时，如何生成HTTP响应
     ///
     /// # 返回值
-    /// * 返回包含错误信息的HTTP响应
+    /// * 返回包含统一错误信封的HTTP响应，信封中的field为验证失败的字段
     fn error_response(&self) -> HttpResponse<BoxBody> {
-        // 构建HTTP响应，设置状态码和响应头
-        HttpResponse::build(self.status_code())  // 设置HTTP状态码（由下方status_code方法决定）
-            .content_type("application/json")    // 设置响应头Content-Type为application/json
-            .body(self.to_string())              // 响应体为错误的字符串描述（即Display实现的内容）
+        ApiError::from(self).error_response()
     }
 
     /// 指定每种UserError错误对应的HTTP状态码
@@ -150,6 +517,13 @@ pub enum UserFacingError {
     InternalError,  // 内部错误变体
 }
 
+/// 将UserFacingError转换为ApiError
+impl From<&UserFacingError> for ApiError {
+    fn from(err: &UserFacingError) -> Self {
+        ApiError::new(err.status_code(), "internal_error", err.to_string())
+    }
+}
+
 /// 为UserFacingError实现ResponseError trait
 ///
 /// 自定义错误响应和状态码
@@ -157,13 +531,9 @@ impl ResponseError for UserFacingError {
     /// 当发生UserFacingError错误时，如何生成HTTP响应
     ///
     /// # 返回值
-    /// * 返回包含用户友好错误信息的HTTP响应
+    /// * 返回包含用户友好错误信息的统一错误信封
     fn error_response(&self) -> HttpResponse<BoxBody> {
-        // 构建HTTP响应
-        // 使用InternalServerError()快捷方法创建500状态码响应
-        HttpResponse::InternalServerError()
-            .content_type("application/json")  // 设置Content-Type为application/json
-            .body(self.to_string())            // 响应体为错误的字符串描述
+        ApiError::from(self).error_response()
     }
 
     /// 指定UserFacingError错误对应的HTTP状态码
@@ -175,3 +545,372 @@ impl ResponseError for UserFacingError {
         http::StatusCode::INTERNAL_SERVER_ERROR
     }
 }
+
+/// 用户存储错误
+///
+/// 表示UserRepository操作中发生的错误
+/// NotFound和Conflict可以直接告诉用户，Storage只在日志中记录细节
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+pub enum RepositoryError {
+    #[display(fmt = "用户不存在: {_0}")]
+    /// 指定的用户不存在
+    NotFound(#[error(not(source))] String),
+
+    #[display(fmt = "用户名已存在: {_0}")]
+    /// 用户名已被占用
+    Conflict(#[error(not(source))] String),
+
+    #[display(fmt = "存储错误: {_0}")]
+    /// 底层存储发生错误
+    Storage(#[error(not(source))] String),
+}
+
+/// 将RepositoryError转换为ApiError
+///
+/// 存储错误的细节只记录到日志中，不暴露给客户端
+impl From<&RepositoryError> for ApiError {
+    fn from(err: &RepositoryError) -> Self {
+        match err {
+            RepositoryError::NotFound(username) => {
+                ApiError::new(err.status_code(), "user_not_found", err.to_string())
+                    .with_details(serde_json::json!({ "username": username }))
+            }
+            RepositoryError::Conflict(username) => {
+                ApiError::new(err.status_code(), "username_taken", err.to_string())
+                    .with_field("username")
+                    .with_details(serde_json::json!({ "username": username }))
+            }
+            RepositoryError::Storage(detail) => {
+                log::error!("用户存储错误: {}", detail);
+                ApiError::new(err.status_code(), "storage_error", InternalDbError.to_string())
+            }
+        }
+    }
+}
+
+/// 为RepositoryError实现ResponseError trait
+///
+/// 自定义错误响应和状态码
+impl ResponseError for RepositoryError {
+    /// 当发生RepositoryError错误时，如何生成HTTP响应
+    ///
+    /// # 返回值
+    /// * 返回统一错误信封，存储错误不暴露内部细节
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        ApiError::from(self).error_response()
+    }
+
+    /// 指定每种RepositoryError错误对应的HTTP状态码
+    ///
+    /// # 返回值
+    /// * 返回对应错误类型的HTTP状态码
+    fn status_code(&self) -> http::StatusCode {
+        match self {
+            // 用户不存在，返回404 Not Found
+            RepositoryError::NotFound(_) => http::StatusCode::NOT_FOUND,
+            // 用户名冲突，返回409 Conflict
+            RepositoryError::Conflict(_) => http::StatusCode::CONFLICT,
+            // 存储错误，返回500 Internal Server Error
+            RepositoryError::Storage(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// 认证错误
+///
+/// 表示登录、会话和权限检查中发生的错误
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+pub enum AuthError {
+    #[display(fmt = "需要登录")]
+    /// 请求未携带有效的会话
+    Unauthorized,
+
+    #[display(fmt = "用户名或密码错误")]
+    /// 用户名或密码错误，不区分是哪一项错误
+    InvalidCredentials,
+
+    #[display(fmt = "令牌无效或已过期")]
+    /// Bearer令牌无法通过校验
+    InvalidToken,
+
+    #[display(fmt = "无权操作其他用户")]
+    /// 已登录但无权访问目标资源
+    Forbidden,
+
+    #[display(fmt = "需要客户端证书")]
+    /// 连接没有提供通过校验的客户端证书（mTLS）
+    ClientCertificateRequired,
+
+    #[display(fmt = "登录失败次数过多，请在{retry_after}秒后重试")]
+    /// 用户名或IP因多次登录失败被暂时锁定
+    TooManyAttempts { retry_after: u64 },
+
+    #[display(fmt = "密钥加载失败: {_0}")]
+    /// 启动时无法加载签名密钥
+    KeyLoad(#[error(not(source))] String),
+
+    #[display(fmt = "密码处理失败: {_0}")]
+    /// 密码哈希计算失败或会话写入失败
+    Internal(#[error(not(source))] String),
+}
+
+/// 将AuthError转换为ApiError
+///
+/// 内部错误的细节只记录到日志中，不暴露给客户端
+impl From<&AuthError> for ApiError {
+    fn from(err: &AuthError) -> Self {
+        let code = match err {
+            AuthError::Unauthorized => "unauthorized",
+            AuthError::InvalidCredentials => "invalid_credentials",
+            AuthError::InvalidToken => "invalid_token",
+            AuthError::Forbidden => "forbidden",
+            AuthError::ClientCertificateRequired => "client_certificate_required",
+            AuthError::TooManyAttempts { retry_after } => {
+                return ApiError::new(err.status_code(), "too_many_attempts", err.to_string())
+                    .with_details(serde_json::json!({ "retry_after": retry_after }));
+            }
+            AuthError::KeyLoad(detail) | AuthError::Internal(detail) => {
+                log::error!("认证内部错误: {}", detail);
+                return ApiError::new(err.status_code(), "internal_error", MyNewError::InternalError.to_string());
+            }
+        };
+        ApiError::new(err.status_code(), code, err.to_string())
+    }
+}
+
+/// 为AuthError实现ResponseError trait
+///
+/// 自定义错误响应和状态码
+impl ResponseError for AuthError {
+    /// 当发生AuthError错误时，如何生成HTTP响应
+    ///
+    /// # 返回值
+    /// * 返回统一错误信封，内部错误不暴露细节
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        let mut res = ApiError::from(self).error_response();
+        match self {
+            // 令牌错误按RFC 6750的要求告知客户端认证方式
+            AuthError::InvalidToken => {
+                res.headers_mut().insert(
+                    header::WWW_AUTHENTICATE,
+                    HeaderValue::from_static(r#"Bearer error="invalid_token""#),
+                );
+            }
+            // 锁定时告知客户端何时可以重试
+            AuthError::TooManyAttempts { retry_after } => {
+                res.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(*retry_after));
+            }
+            _ => {}
+        }
+        res
+    }
+
+    /// 指定每种AuthError错误对应的HTTP状态码
+    ///
+    /// # 返回值
+    /// * 返回对应错误类型的HTTP状态码
+    fn status_code(&self) -> http::StatusCode {
+        match self {
+            // 未登录或凭据错误，返回401 Unauthorized
+            AuthError::Unauthorized
+            | AuthError::InvalidCredentials
+            | AuthError::InvalidToken
+            | AuthError::ClientCertificateRequired => {
+                http::StatusCode::UNAUTHORIZED
+            }
+            // 无权操作，返回403 Forbidden
+            AuthError::Forbidden => http::StatusCode::FORBIDDEN,
+            // 登录尝试过多，返回429 Too Many Requests
+            AuthError::TooManyAttempts { .. } => http::StatusCode::TOO_MANY_REQUESTS,
+            // 内部错误，返回500 Internal Server Error
+            AuthError::Internal(_) | AuthError::KeyLoad(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// 限流错误
+///
+/// 客户端超出配额时返回，`retry_after`是建议的重试等待时间（秒）
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+#[display(fmt = "请求过于频繁，请在{retry_after}秒后重试")]
+pub struct RateLimitError {
+    pub retry_after: u64,  // 建议的重试等待时间（秒）
+}
+
+/// 将RateLimitError转换为ApiError
+impl From<&RateLimitError> for ApiError {
+    fn from(err: &RateLimitError) -> Self {
+        ApiError::new(err.status_code(), "rate_limited", err.to_string())
+            .with_details(serde_json::json!({ "retry_after": err.retry_after }))
+    }
+}
+
+/// 为RateLimitError实现ResponseError trait
+///
+/// 返回429，并通过Retry-After告知客户端等待时间
+impl ResponseError for RateLimitError {
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        let mut res = ApiError::from(self).error_response();
+        res.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(self.retry_after));
+        res
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+/// 计数器数量达到上限的错误
+///
+/// 每个计数器都会成为/metrics中的一个标签值，数量必须有上限；
+/// 已有的计数器仍然可以修改，只是不能再创建新的
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+#[display(fmt = "计数器数量已达上限{max}，不能再创建新的计数器")]
+pub struct CounterLimitError {
+    pub max: usize,  // 计数器数量上限
+}
+
+/// 将CounterLimitError转换为ApiError
+impl From<&CounterLimitError> for ApiError {
+    fn from(err: &CounterLimitError) -> Self {
+        ApiError::new(err.status_code(), "counter_limit_reached", err.to_string())
+            .with_details(serde_json::json!({ "max": err.max }))
+    }
+}
+
+/// 为CounterLimitError实现ResponseError trait
+///
+/// 返回409 Conflict
+impl ResponseError for CounterLimitError {
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        ApiError::from(self).error_response()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::CONFLICT
+    }
+}
+
+/// 主机与连接不符的错误
+///
+/// TLS握手时的SNI与请求的Host属于不同的虚拟主机时返回，
+/// 客户端应当为该主机建立新的连接后重试
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+#[display(fmt = "此连接不能处理主机{host}的请求")]
+pub struct MisdirectedRequest {
+    #[error(not(source))]
+    pub host: String,  // 请求的主机名
+}
+
+/// 将MisdirectedRequest转换为ApiError
+impl From<&MisdirectedRequest> for ApiError {
+    fn from(err: &MisdirectedRequest) -> Self {
+        ApiError::new(err.status_code(), "misdirected_request", err.to_string())
+            .with_details(serde_json::json!({ "host": err.host }))
+    }
+}
+
+/// 为MisdirectedRequest实现ResponseError trait
+///
+/// 返回421 Misdirected Request
+impl ResponseError for MisdirectedRequest {
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        ApiError::from(self).error_response()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        http::StatusCode::MISDIRECTED_REQUEST
+    }
+}
+
+/// 内容协商错误
+///
+/// 请求或响应的数据格式无法处理时返回
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+pub enum NegotiationError {
+    #[display(fmt = "没有可接受的响应格式，支持: {}", "supported.join(\", \")")]
+    /// Accept头中没有服务端支持的格式
+    NotAcceptable {
+        #[error(not(source))]
+        supported: Vec<&'static str>,  // 支持的媒体类型
+    },
+
+    #[display(fmt = "不支持的请求体格式: {content_type}")]
+    /// 请求体的Content-Type不是支持的格式
+    UnsupportedMediaType {
+        #[error(not(source))]
+        content_type: String,          // 请求的Content-Type，没有时为空
+        #[error(not(source))]
+        supported: Vec<&'static str>,  // 支持的媒体类型
+    },
+
+    #[display(fmt = "请求体无法按{format}解析: {message}")]
+    /// 请求体与声明的格式不符
+    InvalidBody {
+        #[error(not(source))]
+        format: &'static str,          // 请求体的媒体类型
+        #[error(not(source))]
+        message: String,               // 解析错误
+    },
+
+    #[display(fmt = "响应无法按{format}序列化: {message}")]
+    /// 响应数据无法序列化为协商出的格式
+    Serialize {
+        #[error(not(source))]
+        format: &'static str,          // 响应的媒体类型
+        #[error(not(source))]
+        message: String,               // 序列化错误
+    },
+}
+
+/// 将NegotiationError转换为ApiError
+///
+/// 序列化失败属于服务端的问题，对外只返回通用的内部错误
+impl From<&NegotiationError> for ApiError {
+    fn from(err: &NegotiationError) -> Self {
+        match err {
+            NegotiationError::NotAcceptable { supported } => {
+                ApiError::new(err.status_code(), "not_acceptable", err.to_string())
+                    .with_details(serde_json::json!({ "supported": supported.join(", ") }))
+            }
+            NegotiationError::UnsupportedMediaType { supported, .. } => {
+                ApiError::new(err.status_code(), "unsupported_media_type", err.to_string())
+                    .with_details(serde_json::json!({ "supported": supported.join(", ") }))
+            }
+            NegotiationError::InvalidBody { format, message } => {
+                ApiError::new(err.status_code(), "invalid_body", err.to_string())
+                    .with_details(serde_json::json!({ "format": format, "reason": message }))
+            }
+            NegotiationError::Serialize { .. } => {
+                ApiError::new(err.status_code(), "internal_error", MyNewError::InternalError.to_string())
+            }
+        }
+    }
+}
+
+/// 为NegotiationError实现ResponseError trait
+impl ResponseError for NegotiationError {
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        ApiError::from(self).error_response()
+    }
+
+    fn status_code(&self) -> http::StatusCode {
+        match self {
+            // 没有可接受的格式，返回406 Not Acceptable
+            NegotiationError::NotAcceptable { .. } => http::StatusCode::NOT_ACCEPTABLE,
+            // 不支持的请求体格式，返回415 Unsupported Media Type
+            NegotiationError::UnsupportedMediaType { .. } => http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            // 请求体解析失败，返回400 Bad Request
+            NegotiationError::InvalidBody { .. } => http::StatusCode::BAD_REQUEST,
+            // 序列化失败，返回500 Internal Server Error
+            NegotiationError::Serialize { .. } => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// 将SQLite错误转换为存储错误
+impl From<rusqlite::Error> for RepositoryError {
+    fn from(err: rusqlite::Error) -> Self {
+        RepositoryError::Storage(err.to_string())
+    }
+}
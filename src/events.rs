@@ -0,0 +1,351 @@
+// 标准库导入
+use std::collections::{HashMap, VecDeque};          // 频道表和每个频道的最近事件
+use std::sync::{Arc, Mutex};                        // 用于线程安全的共享状态
+use std::time::Duration;                            // 保活间隔
+
+// 外部库导入
+use actix_web::http::header::HeaderName;            // Last-Event-ID请求头
+use actix_web::web;                                 // 用于Bytes类型
+use futures::stream::{self, Stream};                // 用于把订阅转换为响应流
+use tokio::sync::{broadcast, watch};               // 有界的广播通道和停止通知
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};  // 保活定时器
+
+// 内部模块导入
+use crate::settings::EventSettings;                 // 事件总线配置
+
+/// 未指定频道时使用的默认频道
+pub const DEFAULT_CHANNEL: &str = "default";
+
+/// 客户端重连时携带的最后一个事件ID
+pub const LAST_EVENT_ID_HEADER: HeaderName = HeaderName::from_static("last-event-id");
+
+/// 一条事件
+///
+/// 同一个事件会被所有订阅者共享，因此通过Arc传递
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub id: u64,         // 事件ID，在所属频道内从1开始单调递增
+    pub event: String,   // 事件类型，对应SSE的event字段
+    pub data: String,    // 事件内容，对应SSE的data字段
+}
+
+impl Event {
+    /// 编码为SSE格式的消息
+    ///
+    /// 多行内容会拆成多个data行，客户端会重新用换行符拼接
+    pub fn to_sse(&self) -> String {
+        let mut msg = format!("id: {}\nevent: {}\n", self.id, self.event);
+        for line in self.data.split('\n') {
+            msg.push_str("data: ");
+            msg.push_str(line);
+            msg.push('\n');
+        }
+        msg.push('\n');
+        msg
+    }
+}
+
+/// 一个频道
+struct Channel {
+    sender: broadcast::Sender<Arc<Event>>,  // 实时事件的广播发送端
+    history: VecDeque<Arc<Event>>,          // 最近的事件，用于重连补发
+    next_id: u64,                           // 下一个事件ID
+}
+
+/// 一次订阅
+///
+/// `replay`中是需要先补发的历史事件，之后从`receiver`接收实时事件；
+/// 两者在同一把锁内取得，补发和实时事件之间不会遗漏也不会重复
+pub struct Subscription {
+    pub replay: Vec<Arc<Event>>,                    // 需要补发的事件
+    pub skipped: u64,                               // 已经不在缓冲区、无法补发的事件数
+    pub receiver: broadcast::Receiver<Arc<Event>>,  // 实时事件
+}
+
+/// 事件总线
+///
+/// 服务端代码通过`publish`向命名频道发布事件，`/sse?channel=...`的订阅者实时收到这些事件
+///
+/// # 慢订阅者策略
+/// 每个频道是一个容量为`channel_capacity`的广播环形缓冲区：
+/// * 发布永远不会阻塞，也不会因为某个订阅者跟不上而失败
+/// * 订阅者落后超过缓冲区容量时，最旧的事件被覆盖（丢弃），
+///   订阅者会先收到一条`event: lagged`，data为被跳过的事件数，然后从仍在缓冲区中的最旧事件继续
+///
+/// # 断线重连
+/// 每个频道另外保留最近`replay_buffer`条事件，客户端重连时带上`Last-Event-ID`，
+/// 会先补发该ID之后的事件；需要的事件已经被挤出缓冲区时，同样先收到一条`lagged`
+pub struct EventHub {
+    capacity: usize,                           // 每个订阅者的实时缓冲区大小
+    replay_buffer: usize,                      // 每个频道保留的最近事件数
+    retry: Duration,                           // 建议客户端的重连间隔
+    keepalive: Duration,                       // 空闲保活间隔
+    channels: Mutex<HashMap<String, Channel>>, // 频道名到频道
+    closing: watch::Sender<bool>,              // 是否正在停止
+}
+
+impl EventHub {
+    /// 根据配置创建事件总线
+    pub fn from_settings(settings: &EventSettings) -> Self {
+        EventHub {
+            capacity: settings.channel_capacity,
+            replay_buffer: settings.replay_buffer,
+            retry: Duration::from_millis(settings.retry_ms),
+            keepalive: Duration::from_secs(settings.keepalive_secs),
+            channels: Mutex::new(HashMap::new()),
+            closing: watch::channel(false).0,
+        }
+    }
+
+    /// 向频道发布一条事件
+    ///
+    /// # 参数
+    /// * `channel` - 频道名
+    /// * `event` - 事件类型
+    /// * `data` - 事件内容
+    ///
+    /// # 返回值
+    /// * 返回分配给该事件的ID
+    pub fn publish(&self, channel: &str, event: impl Into<String>, data: impl Into<String>) -> u64 {
+        let mut channels = self.channels();
+        let channel = self.channel(&mut channels, channel);
+
+        let event = Arc::new(Event { id: channel.next_id, event: event.into(), data: data.into() });
+        channel.next_id += 1;
+
+        if self.replay_buffer > 0 {
+            if channel.history.len() == self.replay_buffer {
+                channel.history.pop_front();
+            }
+            channel.history.push_back(event.clone());
+        }
+        // 没有订阅者时send返回错误，事件只保留在历史中
+        let _ = channel.sender.send(event.clone());
+        event.id
+    }
+
+    /// 订阅频道
+    ///
+    /// # 参数
+    /// * `channel` - 频道名
+    /// * `last_event_id` - 客户端收到的最后一个事件ID，首次连接时为None
+    pub fn subscribe(&self, channel: &str, last_event_id: Option<u64>) -> Subscription {
+        let mut channels = self.channels();
+        // 顺便清理没有订阅者、也从未发布过事件的频道，避免频道表无限增长；
+        // 发布过事件的频道要保留next_id，即使replay_buffer为0、历史为空，ID也不能重新从1开始
+        channels.retain(|_, channel| channel.sender.receiver_count() > 0 || channel.next_id > 1);
+        let channel = self.channel(&mut channels, channel);
+
+        let (replay, skipped) = match last_event_id {
+            // 客户端的ID比当前所有事件都新，说明服务端已经重启，ID重新从1开始，补发全部历史
+            Some(last) if last >= channel.next_id => (channel.history.iter().cloned().collect(), 0),
+            Some(last) => {
+                let oldest = channel.history.front().map_or(channel.next_id, |event| event.id);
+                let replay = channel.history.iter().filter(|event| event.id > last).cloned().collect();
+                (replay, oldest.saturating_sub(last + 1))
+            }
+            None => (Vec::new(), 0),
+        };
+
+        Subscription { replay, skipped, receiver: channel.sender.subscribe() }
+    }
+
+    /// 当前频道的订阅者数量
+    pub fn subscriber_count(&self, channel: &str) -> usize {
+        self.channels()
+            .get(channel)
+            .map(|channel| channel.sender.receiver_count())
+            .unwrap_or(0)
+    }
+
+    /// 当前的频道数和所有频道的订阅者总数
+    pub fn stats(&self) -> (usize, usize) {
+        let channels = self.channels();
+        let subscribers = channels.values().map(|channel| channel.sender.receiver_count()).sum();
+        (channels.len(), subscribers)
+    }
+
+    /// 结束所有SSE流
+    ///
+    /// 每个打开的流发送一条`shutdown`事件后结束，之后新建立的流同样在开头部分之后立即结束
+    pub fn close(&self) {
+        self.closing.send_replace(true);
+    }
+
+    /// 获取频道，不存在时创建
+    fn channel<'a>(&self, channels: &'a mut HashMap<String, Channel>, name: &str) -> &'a mut Channel {
+        channels.entry(name.to_string()).or_insert_with(|| Channel {
+            sender: broadcast::channel(self.capacity).0,
+            history: VecDeque::with_capacity(self.replay_buffer),
+            next_id: 1,
+        })
+    }
+
+    /// 获取频道表的锁
+    ///
+    /// 锁内只做简单的表操作，不会panic，锁中毒时直接沿用内部数据
+    fn channels(&self) -> std::sync::MutexGuard<'_, HashMap<String, Channel>> {
+        self.channels.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 将订阅转换为SSE响应流
+    ///
+    /// 流依次输出：`retry:`重连间隔、无法补发时的`lagged`事件、补发的历史事件、实时事件；
+    /// 超过保活间隔没有事件时输出一行`: keepalive`注释，防止代理断开空闲连接；
+    /// 订阅者落后时输出`lagged`事件（见慢订阅者策略）；
+    /// 调用`close`后输出`shutdown`事件并结束，事件总线被销毁时流直接结束
+    ///
+    /// # 参数
+    /// * `subscription` - 频道订阅
+    pub fn sse_stream(
+        &self,
+        subscription: Subscription,
+    ) -> impl Stream<Item = Result<web::Bytes, std::io::Error>> + use<> {
+        let mut preamble = format!("retry: {}\n\n", self.retry.as_millis());
+        if subscription.skipped > 0 {
+            preamble.push_str(&lagged(subscription.skipped));
+        }
+        for event in &subscription.replay {
+            preamble.push_str(&event.to_sse());
+        }
+
+        let mut keepalive = interval_at(Instant::now() + self.keepalive, self.keepalive);
+        keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let state = StreamState {
+            preamble: Some(preamble),
+            receiver: subscription.receiver,
+            keepalive,
+            closing: self.closing.subscribe(),
+            retry: self.retry,
+            finished: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            if let Some(preamble) = state.preamble.take() {
+                return Some((Ok(web::Bytes::from(preamble)), state));
+            }
+
+            let msg = tokio::select! {
+                // 优先处理停止，避免停止期间继续推送新事件
+                biased;
+                closed = state.closing.wait_for(|closing| *closing) => {
+                    // 发送端随事件总线一起销毁时直接结束
+                    closed.ok()?;
+                    state.finished = true;
+                    shutdown(state.retry)
+                }
+                received = state.receiver.recv() => match received {
+                    Ok(event) => event.to_sse(),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("SSE订阅者落后，丢弃了{}条事件", skipped);
+                        lagged(skipped)
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+                _ = state.keepalive.tick() => ": keepalive\n\n".to_string(),
+            };
+            // 有数据发出后重新计算保活间隔
+            state.keepalive.reset();
+            Some((Ok(web::Bytes::from(msg)), state))
+        })
+    }
+}
+
+/// SSE流的内部状态
+struct StreamState {
+    preamble: Option<String>,                      // 尚未发送的开头部分
+    receiver: broadcast::Receiver<Arc<Event>>,     // 实时事件
+    keepalive: Interval,                           // 保活定时器
+    closing: watch::Receiver<bool>,                // 停止通知
+    retry: Duration,                               // 建议客户端的重连间隔
+    finished: bool,                                // 已经发送shutdown事件，流应当结束
+}
+
+/// 生成一条lagged事件，data为被跳过的事件数
+fn lagged(skipped: u64) -> String {
+    format!("event: lagged\ndata: {skipped}\n\n")
+}
+
+/// 生成一条shutdown事件，data中提示客户端的重连间隔
+///
+/// 不带id，客户端重连时的Last-Event-ID仍然是最后一个真实事件
+fn shutdown(retry: Duration) -> String {
+    format!("event: shutdown\ndata: {{\"retry_ms\":{}}}\n\n", retry.as_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hub(replay_buffer: usize) -> EventHub {
+        EventHub::from_settings(&EventSettings { replay_buffer, ..EventSettings::default() })
+    }
+
+    fn ids(subscription: &Subscription) -> Vec<u64> {
+        subscription.replay.iter().map(|event| event.id).collect()
+    }
+
+    #[test]
+    fn ids_stay_monotonic_without_replay_buffer() {
+        let hub = hub(0);
+        let subscription = hub.subscribe("news", None);
+        assert_eq!(hub.publish("news", "update", "1"), 1);
+        drop(subscription);
+
+        // 频道此时没有订阅者也没有历史，订阅其他频道触发清理后ID仍然继续递增
+        let _other = hub.subscribe("other", None);
+        assert_eq!(hub.publish("news", "update", "2"), 2);
+    }
+
+    #[test]
+    fn channels_that_never_published_are_evicted() {
+        let hub = hub(0);
+        drop(hub.subscribe("idle", None));
+        let _other = hub.subscribe("other", None);
+        assert_eq!(hub.stats(), (1, 1));
+    }
+
+    #[test]
+    fn replays_events_after_last_event_id() {
+        let hub = hub(3);
+        for n in 0..5 {
+            hub.publish("news", "update", n.to_string());
+        }
+
+        // 缓冲区中保留3、4、5
+        let subscription = hub.subscribe("news", Some(3));
+        assert_eq!((ids(&subscription), subscription.skipped), (vec![4, 5], 0));
+
+        let subscription = hub.subscribe("news", Some(5));
+        assert_eq!((ids(&subscription), subscription.skipped), (vec![], 0));
+    }
+
+    #[test]
+    fn reports_events_older_than_the_buffer_as_skipped() {
+        let hub = hub(3);
+        for n in 0..5 {
+            hub.publish("news", "update", n.to_string());
+        }
+
+        // 事件2已经被挤出缓冲区
+        let subscription = hub.subscribe("news", Some(1));
+        assert_eq!((ids(&subscription), subscription.skipped), (vec![3, 4, 5], 1));
+
+        let subscription = hub.subscribe("news", Some(0));
+        assert_eq!((ids(&subscription), subscription.skipped), (vec![3, 4, 5], 2));
+    }
+
+    #[test]
+    fn replays_all_history_when_last_event_id_is_ahead() {
+        let hub = hub(3);
+        hub.publish("news", "update", "1");
+        hub.publish("news", "update", "2");
+
+        // 服务端重启后ID重新从1开始，客户端的ID比当前所有事件都新
+        let subscription = hub.subscribe("news", Some(40));
+        assert_eq!((ids(&subscription), subscription.skipped), (vec![1, 2], 0));
+    }
+}
@@ -6,17 +6,56 @@ use rand::Rng;  // 随机数生成
 // 内部模块导入
 // 导入数据模型
 use crate::models::{
-    AppState, AppStateWithCounter,  // 应用状态结构体
+    AppState,                       // 应用状态结构体
+    CounterAction, CounterPath, CounterUpdate, CounterValue, // 命名计数器结构体
     LoginInfo, MyStruct,            // 登录信息和响应结构体
-    SearchQuery, UserInfo, UserIput // 查询参数和用户信息结构体
+    SearchQuery, UserInfo, UserIput, // 查询参数和用户信息结构体
+    ContactInfo, SseQuery,          // 联系方式和SSE订阅查询参数结构体
+    User, RegisterInfo,             // 用户资源和注册表单结构体
+    RefreshRequest, TokenPair, TokenRequest // 令牌请求和响应结构体
 };
+// 导入JWT组件
+use crate::jwt::{JwtKeys, TokenKind};
+// 导入认证相关组件
+use crate::auth::{hash_password, verify_dummy_password, verify_password, AuthenticatedUser, SESSION_USER_KEY};
+// 导入登录防暴力破解
+use crate::login_guard::{LoginGuard, AUDIT_LOG_TARGET};
+// 导入用户存储接口
+use crate::repository::UserRepository;
 // 导入错误类型
 use crate::errors::{
     MyError, MyNewError, MySimpleError,  // 基本错误类型
-    UserError, UserFacingError           // 用户相关错误类型
+    UserError, UserFacingError,          // 用户相关错误类型
+    AuthError, RepositoryError,          // 认证和存储错误类型
+    ApiError                             // 统一的错误类型
 };
+use actix_session::Session;  // 会话
 // 导入工具函数
-use crate::utils::{create_sse_stream, do_thing_that_may_fail};
+use crate::utils::do_thing_that_may_fail;
+// 导入事件总线
+use crate::events::{EventHub, DEFAULT_CHANNEL, LAST_EVENT_ID_HEADER};
+// 导入WebSocket房间
+use crate::ws::{self, Rooms};
+// 导入命名计数器
+use crate::counters::CounterStore;
+// 导入服务器指标
+use crate::metrics::{self, Metrics};
+// 导入健康检查
+use crate::health::Health;
+// 导入客户端证书
+use crate::tls::ClientCertificate;
+// 导入明文HTTP重定向的目标
+use crate::vhost::HttpsRedirect;
+// 导入本地化
+use crate::i18n::{self, Locale};
+// 导入带校验的提取器
+use crate::validation::{ValidatedBody, ValidatedForm, ValidatedJson, ValidatedPath, ValidatedQuery};
+// 导入内容协商
+use crate::negotiation::Negotiated;
+// 导入OpenAPI文档，错误信封和就绪检查结果只在文档注解中引用
+use crate::errors::Envelope;
+use crate::health::Readiness;
+use crate::openapi;
 
 /// 处理结果类型别名
 ///
@@ -25,8 +64,8 @@ use crate::utils::{create_sse_stream, do_thing_that_may_fail};
 /// 2. 失败时返回HTTP错误响应
 pub type ProcessResult = Either<MyStruct, HttpResponse>;
 
-/// 路由处理函数部分
-/// 包含各种HTTP请求处理函数
+// 路由处理函数部分
+// 包含各种HTTP请求处理函数
 
 /// 根路径处理函数
 ///
@@ -35,6 +74,10 @@ pub type ProcessResult = Either<MyStruct, HttpResponse>;
 ///
 /// # 返回值
 /// * 返回包含文本消息的HTTP 200 OK响应
+#[utoipa::path(
+    get, path = "/", tag = "示例",
+    responses((status = 200, description = "欢迎消息", body = String, content_type = "text/plain")),
+)]
 #[actix_web::get("/")]
 pub async fn first_hello() -> impl actix_web::Responder {
     HttpResponse::Ok().body("hello actix-web")
@@ -50,6 +93,11 @@ pub async fn first_hello() -> impl actix_web::Responder {
 ///
 /// # 返回值
 /// * 返回包含请求体内容的HTTP 200 OK响应
+#[utoipa::path(
+    post, path = "/echo", tag = "示例",
+    request_body(content = String, content_type = "text/plain"),
+    responses((status = 200, description = "请求体内容加上\"test \"前缀", body = String, content_type = "text/plain")),
+)]
 #[actix_web::post("/echo")]
 pub async fn echo(req_body: String) -> impl Responder {
     HttpResponse::Ok().body("test ".to_string() + &req_body)
@@ -62,10 +110,46 @@ pub async fn echo(req_body: String) -> impl Responder {
 ///
 /// # 返回值
 /// * 返回包含文本消息的HTTP 200 OK响应
+#[utoipa::path(
+    get, path = "/hey", tag = "示例",
+    responses((status = 200, description = "欢迎消息", body = String, content_type = "text/plain")),
+)]
 pub async fn manual_hello() -> impl Responder {
     HttpResponse::Ok().body("hello there")
 }
 
+/// users站点处理函数
+///
+/// 处理虚拟主机表中"users"主机（默认为users.rust-lang.org）的GET /app请求
+/// 演示虚拟主机守卫，其他主机的请求不会匹配这个路由
+///
+/// # 返回值
+/// * 返回包含站点名称的HTTP 200 OK响应
+#[utoipa::path(
+    get, path = "/app", tag = "示例",
+    params(("Host" = String, Header, description = "必须属于虚拟主机users，默认为users.rust-lang.org")),
+    responses((status = 200, description = "站点名称", body = String, content_type = "text/plain")),
+)]
+pub async fn users_site() -> HttpResponse {
+    HttpResponse::Ok().body("users site")
+}
+
+/// www站点处理函数
+///
+/// 处理虚拟主机表中"www"主机（默认为www.rust-lang.org）的GET /app2请求
+/// 测试这个守卫路由使用： curl -k -H "Host: www.rust-lang.org" https://127.0.0.1:8087/app2
+///
+/// # 返回值
+/// * 返回包含站点名称的HTTP 200 OK响应
+#[utoipa::path(
+    get, path = "/app2", tag = "示例",
+    params(("Host" = String, Header, description = "必须属于虚拟主机www，默认为www.rust-lang.org")),
+    responses((status = 200, description = "站点名称", body = String, content_type = "text/plain")),
+)]
+pub async fn www_site() -> HttpResponse {
+    HttpResponse::Ok().body("www site")
+}
+
 /// 带应用状态的处理函数
 ///
 /// 处理GET /app/index2请求，从应用状态中获取应用名称
@@ -76,6 +160,10 @@ pub async fn manual_hello() -> impl Responder {
 ///
 /// # 返回值
 /// * 返回包含应用名称的字符串
+#[utoipa::path(
+    get, path = "/app/index2", tag = "示例",
+    responses((status = 200, description = "包含应用名称的消息", body = String, content_type = "text/plain")),
+)]
 pub async fn index2(data: web::Data<AppState>) -> String {
     // 从app_state中获取app_name
     let app_name = &data.app_name;
@@ -88,6 +176,10 @@ pub async fn index2(data: web::Data<AppState>) -> String {
 ///
 /// # 返回值
 /// * 返回包含文本消息的HTTP 200 OK响应
+#[utoipa::path(
+    get, path = "/app/index", tag = "示例",
+    responses((status = 200, description = "欢迎消息", body = String, content_type = "text/plain")),
+)]
 pub async fn index() -> impl Responder {
     HttpResponse::Ok().body("hello from index")
 }
@@ -95,20 +187,26 @@ pub async fn index() -> impl Responder {
 /// 带计数器的处理函数
 ///
 /// 处理GET /app2/index3请求，递增计数器并返回当前值
-/// 演示如何修改共享状态
+/// 演示如何修改共享状态，计数器保存在命名计数器"index3"中，重启后不会丢失
 ///
 /// # 参数
-/// * `data` - 带计数器的应用状态，通过依赖注入获取
+/// * `counters` - 命名计数器，通过依赖注入获取
 ///
 /// # 返回值
 /// * 返回包含计数器值的字符串
-pub async fn index3(data: web::Data<AppStateWithCounter>) -> String {
-    // 获取计数器的互斥锁
-    let mut counter = data.counter.lock().unwrap();
-    // 递增计数器
-    *counter += 1;
+/// * 计数器数量已达上限且"index3"尚未创建时返回409 Conflict
+#[utoipa::path(
+    get, path = "/app2/index3", tag = "示例",
+    responses(
+        (status = 200, description = "递增后的计数器值", body = String, content_type = "text/plain"),
+        (status = 409, description = "计数器数量已达上限", body = Envelope),
+    ),
+)]
+pub async fn index3(counters: web::Data<CounterStore>) -> ActixResult<String> {
+    // 递增计数器，原子操作无需加锁
+    let counter = counters.add("index3", 1)?;
     // 返回包含计数器值的消息
-    format!("Hello from index3! Counter: {}", counter)
+    Ok(format!("Hello from index3! Counter: {}", counter))
 }
 
 /// 路径参数处理函数（使用元组）
@@ -122,6 +220,17 @@ pub async fn index3(data: web::Data<AppStateWithCounter>) -> String {
 /// # 返回值
 /// * 成功时返回包含用户ID和名称的字符串
 /// * 失败时返回actix_web错误
+#[utoipa::path(
+    get, path = "/path/{user_id}/{name}", tag = "示例",
+    params(
+        ("user_id" = u32, Path, description = "用户ID"),
+        ("name" = String, Path, description = "用户名称"),
+    ),
+    responses(
+        (status = 200, description = "包含用户ID和名称的消息", body = String, content_type = "text/plain"),
+        (status = 404, description = "路径参数无法解析", body = Envelope),
+    ),
+)]
 #[actix_web::get("/path/{user_id}/{name}")]
 pub async fn path_test(path: web::Path<(u32, String)>) -> Result<String, actix_web::Error> {
     // 获取路径参数
@@ -141,13 +250,21 @@ pub async fn path_test(path: web::Path<(u32, String)>) -> Result<String, actix_w
 /// 演示如何使用结构体提取路径参数
 ///
 /// # 参数
-/// * `path` - 路径参数，自动提取为UserInfo结构体
+/// * `path` - 路径参数，自动提取为UserInfo结构体并校验
 ///
 /// # 返回值
 /// * 成功时返回包含用户ID和名称的字符串
 /// * 失败时返回actix_web错误
+#[utoipa::path(
+    get, path = "/path2/{user_id}/{name}", tag = "示例",
+    params(UserInfo),
+    responses(
+        (status = 200, description = "包含用户ID和名称的消息", body = String, content_type = "text/plain"),
+        (status = 400, description = "路径参数校验失败", body = Envelope),
+    ),
+)]
 #[actix_web::get("/path2/{user_id}/{name}")]
-pub async fn path_test_by_struct(path: web::Path<UserInfo>) -> Result<String, actix_web::Error> {
+pub async fn path_test_by_struct(path: ValidatedPath<UserInfo>) -> Result<String, actix_web::Error> {
     // 获取路径参数
     // into_inner()方法将路径参数转换为结构体
     let user_info = path.into_inner();
@@ -163,22 +280,40 @@ pub async fn path_test_by_struct(path: web::Path<UserInfo>) -> Result<String, ac
 ///
 /// 处理GET /query?q=xxx&lang=yyy请求，从URL查询字符串中提取参数
 /// 演示如何处理必需和可选的查询参数
+/// 响应语言由lang参数决定，未提供或不支持时按Accept-Language协商
 ///
 /// # 参数
-/// * `query` - 查询参数，自动提取为SearchQuery结构体
+/// * `query` - 查询参数，自动提取为SearchQuery结构体并校验
+/// * `locale` - 按Accept-Language协商的语言
 ///
 /// # 返回值
-/// * 返回包含查询参数的字符串
+/// * 返回包含查询参数的本地化字符串
+#[utoipa::path(
+    get, path = "/query", tag = "示例",
+    params(SearchQuery, ("Accept-Language" = Option<String>, Header, description = "未提供lang时用于语言协商")),
+    responses(
+        (status = 200, description = "本地化的问候语", body = String, content_type = "text/plain"),
+        (status = 400, description = "查询参数校验失败", body = Envelope),
+    ),
+)]
 #[actix_web::get("/query")]
-pub async fn query_test(query: web::Query<SearchQuery>) -> String {
-    // 检查查询参数中是否包含lang字段
-    if let Some(lang) = &query.lang {
-        // 如果包含lang字段，返回带有lang的响应字符串
-        format!("Hello from query_test! Query: {}, lang: {}", query.q, lang)
-    } else {
-        // 如果不包含lang字段，仅返回q字段
-        format!("Hello from query_test! Query: {}", query.q)
-    }
+pub async fn query_test(query: ValidatedQuery<SearchQuery>, locale: Locale) -> HttpResponse {
+    // lang参数只在这个路由上覆盖Accept-Language
+    let locale = match &query.lang {
+        Some(lang) => {
+            let chosen = Locale::from_tag(lang).unwrap_or(locale);
+            info!("query_test请求的语言: {}, 实际使用: {}", lang, chosen.tag());
+            chosen
+        }
+        None => locale,
+    };
+
+    let message = i18n::translate("query_greeting", locale, &[("q", &query.q)]).unwrap_or_default();
+    HttpResponse::Ok()
+        .insert_header((actix_web::http::header::CONTENT_LANGUAGE, locale.tag()))
+        .insert_header((actix_web::http::header::VARY, "accept-language"))
+        .content_type("text/plain; charset=utf-8")
+        .body(message)
 }
 
 /// JSON请求处理函数
@@ -187,11 +322,20 @@ pub async fn query_test(query: web::Query<SearchQuery>) -> String {
 /// 演示如何处理JSON请求体
 ///
 /// # 参数
-/// * `user` - JSON请求体，自动提取为UserIput结构体
+/// * `user` - JSON请求体，自动提取为UserIput结构体并校验
 ///
 /// # 返回值
 /// * 返回包含用户名和邮箱的字符串
-pub async fn json_test(user: web::Json<UserIput>) -> String {
+#[utoipa::path(
+    post, path = "/config", tag = "示例",
+    request_body(content = UserIput, description = "最大4096字节"),
+    responses(
+        (status = 200, description = "包含用户名和邮箱的消息", body = String, content_type = "text/plain"),
+        (status = 400, description = "字段校验失败", body = Envelope),
+        (status = 409, description = "JSON无法解析或超过长度限制", body = Envelope),
+    ),
+)]
+pub async fn json_test(user: ValidatedJson<UserIput>) -> String {
     // 获取JSON参数
     // into_inner()方法将JSON参数转换为结构体
     let user = user.into_inner();
@@ -203,40 +347,300 @@ pub async fn json_test(user: web::Json<UserIput>) -> String {
     )
 }
 
+/// 注册处理函数
+///
+/// 处理POST /register请求，从表单数据创建带密码的用户
+/// 密码使用Argon2哈希后保存，明文密码不会被存储
+/// 用户名已存在时返回与成功时相同的响应，并且同样先计算哈希，不能借此探测用户名是否已被注册；
+/// 客户端用该用户名和密码登录即可确认注册结果
+///
+/// # 参数
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `locale` - 按Accept-Language协商的语言
+/// * `form` - 表单数据，自动提取为RegisterInfo结构体并校验
+///
+/// # 返回值
+/// * 注册成功或用户名已存在时都返回202 Accepted和本地化的提示
+/// * 表单校验失败时返回400 Bad Request
+#[utoipa::path(
+    post, path = "/register", tag = "认证",
+    request_body(content = RegisterInfo, content_type = "application/x-www-form-urlencoded"),
+    params(("Accept-Language" = Option<String>, Header, description = "提示消息的语言")),
+    responses(
+        (status = 202, description = "注册请求已受理，不表明用户名是否已被占用", body = String, content_type = "text/plain"),
+        (status = 400, description = "表单校验失败", body = Envelope),
+    ),
+)]
+#[actix_web::post("/register")]
+pub async fn register(
+    repo: web::Data<dyn UserRepository>,
+    locale: Locale,
+    form: ValidatedForm<RegisterInfo>,
+) -> ActixResult<HttpResponse> {
+    let info = form.into_inner();
+
+    // 哈希计算和存储写入都可能阻塞，放到线程池中执行
+    let password = info.password;
+    let hash = web::block(move || hash_password(&password)).await??;
+    let input = UserIput { username: info.username, email: info.email };
+    match web::block(move || repo.register(input, hash)).await? {
+        Ok(user) => info!("新用户已注册，ID: {}", user.id),
+        // 用户名已存在只记录在日志中
+        Err(RepositoryError::Conflict(_)) => info!("注册的用户名已被占用"),
+        Err(err) => return Err(err.into()),
+    }
+
+    let message = i18n::translate("registration_accepted", locale, &[]).unwrap_or_default();
+    Ok(HttpResponse::Accepted()
+        .insert_header((actix_web::http::header::CONTENT_LANGUAGE, locale.tag()))
+        .insert_header((actix_web::http::header::VARY, "accept-language"))
+        .content_type("text/plain; charset=utf-8")
+        .body(message))
+}
+
 /// 表单处理函数
 ///
 /// 处理POST /login请求，从请求体中提取表单数据
-/// 演示如何处理表单提交
+/// 校验密码成功后将用户ID写入会话，响应中会带上签名的会话Cookie
 ///
 /// # 参数
-/// * `form` - 表单数据，自动提取为LoginInfo结构体
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `session` - 当前请求的会话
+/// * `form` - 表单数据，自动提取为LoginInfo结构体并校验
 ///
 /// # 返回值
-/// * 返回包含用户名和密码的字符串
+/// * 成功时返回欢迎消息
+/// * 用户名或密码错误时返回401 Unauthorized，不区分具体原因
+/// * 多次失败被锁定时返回429 Too Many Requests
+#[utoipa::path(
+    post, path = "/login", tag = "认证",
+    request_body(content = LoginInfo, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "登录成功，响应带有会话Cookie", body = String, content_type = "text/plain"),
+        (status = 400, description = "表单校验失败", body = Envelope),
+        (status = 401, description = "用户名或密码错误", body = Envelope),
+        (status = 429, description = "失败次数过多，已被锁定", body = Envelope),
+    ),
+)]
 #[actix_web::post("/login")]
-pub async fn login(form: web::Form<LoginInfo>) -> String {
+pub async fn login(
+    req: HttpRequest,
+    repo: web::Data<dyn UserRepository>,
+    guard: web::Data<LoginGuard>,
+    session: Session,
+    form: ValidatedForm<LoginInfo>,
+) -> ActixResult<String> {
     // 获取表单参数
     // into_inner()方法将表单参数转换为结构体
     let login_info = form.into_inner();
 
+    let user = check_credentials(&req, repo, &guard, login_info.username, login_info.password).await?;
+
+    // 登录成功后更换会话ID，防止会话固定攻击
+    session.renew();
+    session
+        .insert(SESSION_USER_KEY, user.id)
+        .map_err(|err| AuthError::Internal(err.to_string()))?;
+
     // 返回格式化的响应
-    format!(
-        "Hello from login! Username: {}, Password: {}",
-        login_info.username, login_info.password
-    )
+    Ok(format!("Hello from login! Username: {}", user.input.username))
+}
+
+/// 校验用户名和密码
+///
+/// 用户不存在、未设置密码和密码错误都返回同样的InvalidCredentials，
+/// 并且都执行一次完整的哈希校验，耗时相同，避免泄露用户名是否存在；
+/// 校验之前先由LoginGuard计数，失败按它的策略延迟响应，用户名或IP被锁定时直接返回TooManyAttempts
+///
+/// # 参数
+/// * `req` - HTTP请求，用于读取对端IP
+/// * `repo` - 用户存储
+/// * `guard` - 登录防暴力破解
+/// * `username` - 用户名
+/// * `password` - 明文密码
+///
+/// # 返回值
+/// * 校验成功时返回该用户
+async fn check_credentials(
+    req: &HttpRequest,
+    repo: web::Data<dyn UserRepository>,
+    guard: &LoginGuard,
+    username: String,
+    password: String,
+) -> ActixResult<User> {
+    let ip = req.peer_addr().map(|addr| addr.ip());
+    // 先计数再校验，并发的尝试不能同时绕过锁定
+    let ticket = guard
+        .begin_attempt(&username, ip)
+        .map_err(|remaining| AuthError::TooManyAttempts { retry_after: remaining.as_secs_f64().ceil() as u64 })?;
+
+    // 读取密码哈希并校验，哈希计算较慢，放到线程池中执行
+    let lookup = username.clone();
+    let verified = web::block(move || -> Result<Option<User>, RepositoryError> {
+        match repo.password_hash(&lookup) {
+            Ok(Some(hash)) if verify_password(&password, &hash) => repo.get(&lookup).map(Some),
+            Ok(Some(_)) => Ok(None),
+            Ok(None) | Err(RepositoryError::NotFound(_)) => {
+                verify_dummy_password(&password);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    })
+    .await;
+    let verified = match verified {
+        Ok(Ok(verified)) => verified,
+        // 校验没有完成，不计为失败
+        Ok(Err(err)) => {
+            guard.cancel(ticket);
+            return Err(err.into());
+        }
+        Err(err) => {
+            guard.cancel(ticket);
+            return Err(err.into());
+        }
+    };
+
+    if let Some(user) = verified {
+        guard.record_success(ticket);
+        return Ok(user);
+    }
+
+    for lockout in &ticket.lockouts {
+        // 审计日志使用单独的日志目标，便于单独采集
+        log::warn!(
+            target: AUDIT_LOG_TARGET,
+            "{}",
+            serde_json::json!({
+                "event": "login_lockout",
+                "subject": lockout.subject.to_string(),
+                "failures": lockout.failures,
+                "locked_secs": lockout.duration.as_secs(),
+                "request_id": crate::logging::current_request_id(),
+            })
+        );
+    }
+    actix_web::rt::time::sleep(ticket.delay).await;
+    Err(AuthError::InvalidCredentials.into())
+}
+
+/// 令牌签发处理函数
+///
+/// 处理POST /token请求，供无法使用Cookie的API客户端换取JWT
+///
+/// # 参数
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `keys` - JWT密钥，通过依赖注入获取
+/// * `body` - JSON请求体，自动提取为TokenRequest结构体并校验
+///
+/// # 返回值
+/// * 成功时返回访问令牌和刷新令牌
+/// * 用户名或密码错误时返回401 Unauthorized
+/// * 多次失败被锁定时返回429 Too Many Requests
+#[utoipa::path(
+    post, path = "/token", tag = "认证",
+    request_body = TokenRequest,
+    responses(
+        (status = 200, description = "访问令牌和刷新令牌", body = TokenPair),
+        (status = 400, description = "请求体校验失败", body = Envelope),
+        (status = 401, description = "用户名或密码错误", body = Envelope),
+        (status = 429, description = "失败次数过多，已被锁定", body = Envelope),
+    ),
+)]
+#[actix_web::post("/token")]
+pub async fn issue_token(
+    req: HttpRequest,
+    repo: web::Data<dyn UserRepository>,
+    guard: web::Data<LoginGuard>,
+    keys: web::Data<JwtKeys>,
+    body: ValidatedJson<TokenRequest>,
+) -> ActixResult<web::Json<TokenPair>> {
+    let request = body.into_inner();
+    let user = check_credentials(&req, repo, &guard, request.username, request.password).await?;
+
+    Ok(web::Json(keys.issue(user.id)?))
+}
+
+/// 令牌刷新处理函数
+///
+/// 处理POST /token/refresh请求，用刷新令牌换取一对新令牌
+/// 用户已被删除时刷新令牌同样失效
+///
+/// # 参数
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `keys` - JWT密钥，通过依赖注入获取
+/// * `body` - JSON请求体，自动提取为RefreshRequest结构体并校验
+///
+/// # 返回值
+/// * 成功时返回新的访问令牌和刷新令牌
+/// * 刷新令牌无效或已过期时返回401 Unauthorized
+#[utoipa::path(
+    post, path = "/token/refresh", tag = "认证",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "新的访问令牌和刷新令牌", body = TokenPair),
+        (status = 401, description = "刷新令牌无效或已过期", body = Envelope),
+    ),
+)]
+#[actix_web::post("/token/refresh")]
+pub async fn refresh_token(
+    repo: web::Data<dyn UserRepository>,
+    keys: web::Data<JwtKeys>,
+    body: ValidatedJson<RefreshRequest>,
+) -> ActixResult<web::Json<TokenPair>> {
+    let claims = keys.verify(&body.refresh_token, TokenKind::Refresh)?;
+
+    // 确认用户仍然存在；ID不会被重新分配，用户被删除后同名的新用户不能用旧令牌刷新
+    let id = claims.user_id().ok_or(AuthError::InvalidToken)?;
+    let user = match web::block(move || repo.get_by_id(id)).await? {
+        Ok(user) => user,
+        Err(RepositoryError::NotFound(_)) => return Err(AuthError::InvalidToken.into()),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(web::Json(keys.issue(user.id)?))
+}
+
+/// 退出登录处理函数
+///
+/// 处理POST /logout请求，清空会话并让浏览器删除会话Cookie
+///
+/// # 参数
+/// * `session` - 当前请求的会话
+///
+/// # 返回值
+/// * 返回204 No Content
+#[utoipa::path(
+    post, path = "/logout", tag = "认证",
+    responses((status = 204, description = "会话已清空")),
+)]
+#[actix_web::post("/logout")]
+pub async fn logout(session: Session) -> HttpResponse {
+    session.purge();
+    HttpResponse::NoContent().finish()
 }
 
 /// 结构体响应处理函数
 ///
-/// 处理GET /my_struct请求，返回JSON格式的结构体
-/// 演示如何返回自定义结构体作为JSON响应
+/// 处理GET /my_struct请求，按Accept头返回JSON、MessagePack、CBOR或XML格式的结构体
+/// 演示如何返回实现了Responder的自定义结构体
 ///
 /// # 返回值
-/// * 返回MyStruct结构体，自动序列化为JSON
+/// * 返回MyStruct结构体，按内容协商序列化
+#[utoipa::path(
+    get, path = "/my_struct", tag = "示例",
+    responses(
+        (status = 200, description = "固定的结构体", content(
+            (MyStruct = "application/json"), (MyStruct = "application/msgpack"),
+            (MyStruct = "application/cbor"), (MyStruct = "application/xml"),
+        )),
+        (status = 406, description = "没有可接受的响应格式", body = Envelope),
+    ),
+)]
 #[actix_web::get("/my_struct")]
 pub async fn my_struct_test() -> impl Responder {
     // 创建并返回MyStruct实例
-    // 会自动序列化为JSON响应
+    // 会按Accept头自动序列化
     MyStruct {
         name: "Kayano".to_string(),  // 设置名称
         age: 18,                      // 设置年龄
@@ -245,21 +649,91 @@ pub async fn my_struct_test() -> impl Responder {
 
 /// 服务器发送事件(SSE)处理函数
 ///
-/// 处理GET /sse请求，返回实时更新的事件流
-/// 演示如何实现服务器推送功能
+/// 处理GET /sse?channel=xxx请求，订阅事件总线上的一个频道并实时推送事件
+/// 每条事件带有`id:`和`event:`字段，未指定频道时订阅默认频道
+/// 客户端重连时带上`Last-Event-ID`请求头，会先补发错过的事件
+///
+/// # 参数
+/// * `req` - HTTP请求，用于读取Last-Event-ID
+/// * `hub` - 事件总线，通过依赖注入获取
+/// * `query` - 经过校验的查询参数
 ///
 /// # 返回值
 /// * 返回包含事件流的HTTP响应
+#[utoipa::path(
+    get, path = "/sse", tag = "事件",
+    params(SseQuery, ("Last-Event-ID" = Option<u64>, Header, description = "重连时补发该ID之后的事件")),
+    responses(
+        (status = 200, description = "事件流，服务停止时发送shutdown事件后结束", body = String, content_type = "text/event-stream"),
+        (status = 400, description = "频道名校验失败", body = Envelope),
+    ),
+)]
 #[actix_web::get("/sse")]
-pub async fn stream_handler() -> HttpResponse {
-    // 创建SSE流
-    // create_sse_stream函数返回一个每秒发送一次数据的流
-    let stream = create_sse_stream();
+pub async fn stream_handler(
+    req: HttpRequest,
+    hub: web::Data<EventHub>,
+    metrics: web::Data<Metrics>,
+    query: ValidatedQuery<SseQuery>,
+) -> HttpResponse {
+    let channel = query.into_inner().channel.unwrap_or_else(|| DEFAULT_CHANNEL.to_string());
+    // 无法解析的Last-Event-ID按首次连接处理
+    let last_event_id = req
+        .headers()
+        .get(&LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+
+    // 订阅事件总线上的频道，订阅者跟不上时按EventHub的策略丢弃旧事件
+    let subscription = hub.subscribe(&channel, last_event_id);
+    info!(
+        "SSE订阅频道: {}, Last-Event-ID: {:?}, 补发: {}, 当前订阅者: {}",
+        channel,
+        last_event_id,
+        subscription.replay.len(),
+        hub.subscriber_count(&channel)
+    );
+
+    // 客户端断开时流被丢弃，打开的SSE流计数随之减一
+    let stream = metrics::track_sse_stream(metrics.into_inner(), hub.sse_stream(subscription));
 
     // 返回流式响应
     HttpResponse::Ok()
-        .content_type("text/event-stream")  // 设置SSE内容类型
-        .streaming(stream)                  // 使用流作为响应体
+        .content_type("text/event-stream")                          // 设置SSE内容类型
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-cache"))  // 事件流不能被缓存
+        .streaming(stream)                                          // 使用流作为响应体
+}
+
+/// WebSocket处理函数
+///
+/// 处理GET /ws请求，升级为WebSocket连接后按JSON消息协议（见ws::ClientMessage和ws::ServerMessage）
+/// 加入、离开房间以及向房间发布消息
+/// 已登录用户发布的消息会带上用户名，匿名连接也可以使用
+///
+/// # 参数
+/// * `req` - HTTP请求
+/// * `body` - 请求体载荷，升级后作为WebSocket消息流
+/// * `rooms` - 房间表，通过依赖注入获取
+/// * `user` - 当前登录用户，可选
+///
+/// # 返回值
+/// * 成功时返回101 Switching Protocols
+/// * 不是合法的WebSocket握手请求时返回400
+#[utoipa::path(
+    get, path = "/ws", tag = "事件",
+    security((), ("session" = []), ("bearer" = [])),
+    responses(
+        (status = 101, description = "升级为WebSocket连接"),
+        (status = 400, description = "不是合法的WebSocket握手请求"),
+    ),
+)]
+#[actix_web::get("/ws")]
+pub async fn ws_handler(
+    req: HttpRequest,
+    body: web::Payload,
+    rooms: web::Data<Rooms>,
+    user: Option<AuthenticatedUser>,
+) -> ActixResult<HttpResponse> {
+    ws::start(rooms.into_inner(), &req, body, user.map(|user| user.username))
 }
 
 /// 随机处理结果函数
@@ -270,6 +744,13 @@ pub async fn stream_handler() -> HttpResponse {
 /// # 返回值
 /// * 70%概率返回MyStruct结构体（成功）
 /// * 30%概率返回HTTP 500错误（失败）
+#[utoipa::path(
+    get, path = "/process", tag = "错误演示",
+    responses(
+        (status = 200, description = "成功（70%概率）", body = MyStruct),
+        (status = 500, description = "失败（30%概率）", body = String, content_type = "text/plain"),
+    ),
+)]
 #[actix_web::get("/process")]
 pub async fn process_data() -> ProcessResult {
     // 生成随机布尔值，70%概率为true
@@ -277,7 +758,7 @@ pub async fn process_data() -> ProcessResult {
 
     if success {
         // 成功情况
-        println!("success");
+        info!("process_data: success");
         // 返回MyStruct实例
         Either::Left(MyStruct {
             name: "Kayano".to_string(),
@@ -297,6 +778,10 @@ pub async fn process_data() -> ProcessResult {
 ///
 /// # 返回值
 /// * 总是返回MyError错误
+#[utoipa::path(
+    get, path = "/first_error", tag = "错误演示",
+    responses((status = 500, description = "总是失败", body = Envelope)),
+)]
 #[actix_web::get("/first_error")]
 pub async fn index_by_my_error() -> Result<&'static str, MyError> {
     // 记录日志
@@ -315,6 +800,10 @@ pub async fn index_by_my_error() -> Result<&'static str, MyError> {
 ///
 /// # 返回值
 /// * 总是返回MyNewError::InternalError错误
+#[utoipa::path(
+    get, path = "/error/internal_error", tag = "错误演示",
+    responses((status = 500, description = "总是返回内部错误", body = Envelope)),
+)]
 #[actix_web::get("/internal_error")]
 pub async fn index_by_my_new_error_internal() -> Result<&'static str, MyNewError> {
     // 返回内部错误
@@ -328,6 +817,10 @@ pub async fn index_by_my_new_error_internal() -> Result<&'static str, MyNewError
 ///
 /// # 返回值
 /// * 总是返回MyNewError::Timeout错误
+#[utoipa::path(
+    get, path = "/error/timeout", tag = "错误演示",
+    responses((status = 408, description = "总是返回超时错误", body = Envelope)),
+)]
 #[actix_web::get("/timeout")]
 pub async fn index_by_my_new_error_timeout() -> Result<&'static str, MyNewError> {
     // 返回超时错误
@@ -341,6 +834,10 @@ pub async fn index_by_my_new_error_timeout() -> Result<&'static str, MyNewError>
 ///
 /// # 返回值
 /// * 总是返回MyNewError::BadClientData错误
+#[utoipa::path(
+    get, path = "/error/bad_client_data", tag = "错误演示",
+    responses((status = 400, description = "总是返回客户端数据错误", body = Envelope)),
+)]
 #[actix_web::get("/bad_client_data")]
 pub async fn index_by_my_new_error_bad_client_data() -> Result<&'static str, MyNewError> {
     // 返回客户端数据错误
@@ -354,6 +851,10 @@ pub async fn index_by_my_new_error_bad_client_data() -> Result<&'static str, MyN
 ///
 /// # 返回值
 /// * 总是返回映射后的错误
+#[utoipa::path(
+    get, path = "/error/simple_error", tag = "错误演示",
+    responses((status = 400, description = "总是失败", body = Envelope)),
+)]
 #[actix_web::get("/simple_error")]
 pub async fn index_by_simple_error() -> ActixResult<String> {
     // 创建一个Result类型的变量result，模拟一个错误（Err），错误类型为MySimpleError
@@ -370,27 +871,34 @@ pub async fn index_by_simple_error() -> ActixResult<String> {
 
 /// 表单验证错误演示函数
 ///
-/// 处理GET /form_test请求，演示表单验证错误
-/// 展示如何进行简单验证并返回适当的错误
+/// 处理GET /form_test?contact_method=email&email=xxx请求，演示声明式校验
+/// 校验规则声明在ContactInfo上，包括邮箱格式、正则和条件必填，
+/// 所有失败的字段会在同一个UserError::ValidationError中返回
+///
+/// # 参数
+/// * `contact` - 经过校验的查询参数
 ///
 /// # 返回值
-/// * 验证失败时返回UserError::ValidationError错误
+/// * 验证失败时由提取器返回UserError::ValidationError错误
 /// * 验证成功时返回成功消息
+#[utoipa::path(
+    get, path = "/form_test", tag = "错误演示",
+    params(ContactInfo),
+    responses(
+        (status = 200, description = "校验通过", body = String, content_type = "text/plain"),
+        (status = 400, description = "校验失败，包含所有失败的字段", body = Envelope),
+    ),
+)]
 #[actix_web::get("/form_test")]
-pub async fn process_form() -> Result<&'static str, UserError> {
-    // 假设这是用户输入
-    let user_input = "invalid_email";
-
-    // 简单的验证：检查是否包含@符号
-    if !user_input.contains('@') {
-        // 验证失败，返回一个用户可见的错误
-        return Err(UserError::ValidationError {
-            field: "email".to_string(),  // 指定验证失败的字段
-        });
-    }
+pub async fn process_form(contact: ValidatedQuery<ContactInfo>) -> Result<String, UserError> {
+    // 校验已由提取器完成，这里直接使用通过校验的数据
+    let contact = contact.into_inner();
+    let value = match contact.contact_method.as_str() {
+        "email" => contact.email,
+        _ => contact.phone,
+    };
 
-    // 验证成功
-    Ok("处理成功")
+    Ok(format!("处理成功: {}", value.unwrap_or_default()))
 }
 
 /// 用户可见错误演示函数
@@ -400,6 +908,10 @@ pub async fn process_form() -> Result<&'static str, UserError> {
 ///
 /// # 返回值
 /// * 总是返回UserFacingError::InternalError错误
+#[utoipa::path(
+    get, path = "/error/user_facing_error", tag = "错误演示",
+    responses((status = 500, description = "总是失败", body = Envelope)),
+)]
 #[actix_web::get("/user_facing_error")]
 pub async fn index_by_user_facing_error() -> Result<&'static str, UserFacingError> {
     // 调用可能失败的函数，并将内部错误转换为用户可见错误
@@ -412,18 +924,494 @@ pub async fn index_by_user_facing_error() -> Result<&'static str, UserFacingErro
     Ok("处理成功")
 }
 
+/// 默认处理函数
+///
+/// 没有任何路由匹配时调用，返回统一错误信封格式的404
+///
+/// # 返回值
+/// * 总是返回404 Not Found
+pub async fn not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::from_status(actix_web::http::StatusCode::NOT_FOUND, "资源不存在"))
+}
 
+/// 资源处理函数
+///
+/// 处理/perix上所有HTTP方法的请求
+///
+/// # 返回值
+/// * 总是返回包含固定消息的200响应
+#[utoipa::path(
+    get, path = "/perix", tag = "示例",
+    description = "接受所有HTTP方法",
+    responses((status = 200, description = "固定的消息", body = String, content_type = "text/plain")),
+)]
 pub async fn index_resource() -> HttpResponse {
     HttpResponse::Ok().body("index resource")
 }
 
+/// 用户列表处理函数
+///
+/// 处理GET /users请求，返回按用户名排序的所有用户
+/// 列表包含邮箱，只有已登录用户可以查看
+///
+/// # 参数
+/// * `_auth` - 当前登录用户，匿名请求返回401
+/// * `repo` - 用户存储，通过依赖注入获取
+///
+/// # 返回值
+/// * 成功时返回用户列表的JSON数组
+/// * 失败时返回存储错误
+#[utoipa::path(
+    get, path = "/users", tag = "用户",
+    security(("session" = []), ("bearer" = [])),
+    responses(
+        (status = 200, description = "按用户名排序的所有用户", body = Vec<User>),
+        (status = 401, description = "未登录", body = Envelope),
+        (status = 500, description = "存储错误", body = Envelope),
+    ),
+)]
+pub async fn list_users(
+    _auth: AuthenticatedUser,
+    repo: web::Data<dyn UserRepository>,
+) -> ActixResult<web::Json<Vec<User>>> {
+    // 存储操作可能阻塞，放到线程池中执行
+    let users = web::block(move || repo.list()).await??;
+    Ok(web::Json(users))
+}
 
-pub async fn get_user(req: HttpRequest) -> HttpResponse {
-    let name = req.match_info().get("name").unwrap_or("World");
-    HttpResponse::Ok().body(format!("Hello {}!", name))
+/// 创建用户处理函数
+///
+/// 处理POST /users请求，从JSON请求体创建没有密码的用户
+/// 只有已登录用户可以创建，匿名客户端不能借此抢占用户名
+/// 成功时通过命名资源"user_detail"生成Location响应头
+///
+/// # 参数
+/// * `_auth` - 当前登录用户，匿名请求返回401
+/// * `req` - HTTP请求，用于生成资源URL
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `hub` - 事件总线，创建成功后向"users"频道发布user_created事件
+/// * `rooms` - WebSocket房间，同一事件也会推送到"users"房间
+/// * `input` - JSON请求体，自动提取为UserIput结构体并校验
+///
+/// # 返回值
+/// * 成功时返回201 Created和新建的用户
+/// * 用户名已存在时返回409 Conflict
+#[utoipa::path(
+    post, path = "/users", tag = "用户",
+    request_body = UserIput,
+    security(("session" = []), ("bearer" = [])),
+    responses(
+        (status = 201, description = "新建的用户，Location指向用户资源", body = User),
+        (status = 400, description = "字段校验失败", body = Envelope),
+        (status = 401, description = "未登录", body = Envelope),
+        (status = 409, description = "用户名已存在", body = Envelope),
+    ),
+)]
+pub async fn create_user(
+    _auth: AuthenticatedUser,
+    req: HttpRequest,
+    repo: web::Data<dyn UserRepository>,
+    hub: web::Data<EventHub>,
+    rooms: web::Data<Rooms>,
+    input: ValidatedJson<UserIput>,
+) -> ActixResult<HttpResponse> {
+    let input = input.into_inner();
+    let user = web::block(move || repo.create(input)).await??;
+    publish_user_event(&hub, &rooms, "user_created", &user);
+
+    // 根据路由名称反向生成用户资源的URL
+    let location = req.url_for("user_detail", [&user.input.username])?;
+    Ok(HttpResponse::Created()
+        .insert_header((actix_web::http::header::LOCATION, location.to_string()))
+        .json(user))
+}
+
+/// 获取用户处理函数
+///
+/// 处理GET /user/{name}请求，按用户名查找用户
+/// 只有已登录用户可以查询，匿名客户端不能借404探测用户名是否存在
+///
+/// # 参数
+/// * `_auth` - 当前登录用户，匿名请求返回401
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `name` - 路径参数中的用户名
+///
+/// # 返回值
+/// * 成功时返回用户的JSON
+/// * 用户不存在时返回404 Not Found
+#[utoipa::path(
+    get, path = "/user/{name}", tag = "用户",
+    params(("name" = String, Path, description = "用户名"), ("Content-Type" = String, Header, description = "必须是application/json")),
+    security(("session" = []), ("bearer" = [])),
+    responses(
+        (status = 200, description = "用户", body = User),
+        (status = 401, description = "未登录", body = Envelope),
+        (status = 404, description = "用户不存在", body = Envelope),
+    ),
+)]
+pub async fn get_user(
+    _auth: AuthenticatedUser,
+    repo: web::Data<dyn UserRepository>,
+    name: web::Path<String>,
+) -> ActixResult<web::Json<User>> {
+    let name = name.into_inner();
+    let user = web::block(move || repo.get(&name)).await??;
+    Ok(web::Json(user))
+}
+
+/// 更新用户处理函数
+///
+/// 处理PUT /user/{name}请求，用JSON请求体替换用户的用户名和邮箱
+/// 只有已登录的用户本人可以修改
+///
+/// # 参数
+/// * `auth` - 当前登录用户，匿名请求返回401
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `hub` - 事件总线，更新成功后向"users"频道发布user_updated事件
+/// * `rooms` - WebSocket房间，同一事件也会推送到"users"房间
+/// * `name` - 路径参数中的用户名
+/// * `input` - JSON请求体，自动提取为UserIput结构体并校验
+///
+/// # 返回值
+/// * 成功时返回更新后的用户
+/// * 用户不存在时返回404 Not Found，新用户名被占用时返回409 Conflict
+/// * 修改其他用户时返回403 Forbidden
+#[utoipa::path(
+    put, path = "/user/{name}", tag = "用户",
+    params(("name" = String, Path, description = "用户名")),
+    request_body = UserIput,
+    security(("session" = []), ("bearer" = [])),
+    responses(
+        (status = 200, description = "更新后的用户", body = User),
+        (status = 400, description = "字段校验失败", body = Envelope),
+        (status = 401, description = "未登录", body = Envelope),
+        (status = 403, description = "只能修改本人", body = Envelope),
+        (status = 404, description = "用户不存在", body = Envelope),
+        (status = 409, description = "新用户名已被占用", body = Envelope),
+    ),
+)]
+pub async fn updata_user(
+    auth: AuthenticatedUser,
+    repo: web::Data<dyn UserRepository>,
+    hub: web::Data<EventHub>,
+    rooms: web::Data<Rooms>,
+    name: web::Path<String>,
+    input: ValidatedJson<UserIput>,
+) -> ActixResult<web::Json<User>> {
+    let name = name.into_inner();
+    if auth.username != name {
+        return Err(AuthError::Forbidden.into());
+    }
+    let input = input.into_inner();
+    let user = web::block(move || repo.update(&name, input)).await??;
+    publish_user_event(&hub, &rooms, "user_updated", &user);
+    Ok(web::Json(user))
+}
+
+/// 删除用户处理函数
+///
+/// 处理DELETE /user/{name}请求
+/// 只有已登录的用户本人可以删除，删除后清空当前会话
+///
+/// # 参数
+/// * `auth` - 当前登录用户，匿名请求返回401
+/// * `session` - 当前请求的会话
+/// * `repo` - 用户存储，通过依赖注入获取
+/// * `hub` - 事件总线，删除成功后向"users"频道发布user_deleted事件
+/// * `rooms` - WebSocket房间，同一事件也会推送到"users"房间
+/// * `name` - 路径参数中的用户名
+///
+/// # 返回值
+/// * 成功时返回204 No Content
+/// * 用户不存在时返回404 Not Found，删除其他用户时返回403 Forbidden
+#[utoipa::path(
+    delete, path = "/user/{name}", tag = "用户",
+    params(("name" = String, Path, description = "用户名"), ("Content-Type" = String, Header, description = "必须是application/json")),
+    security(("session" = []), ("bearer" = [])),
+    responses(
+        (status = 204, description = "已删除"),
+        (status = 401, description = "未登录", body = Envelope),
+        (status = 403, description = "只能删除本人", body = Envelope),
+        (status = 404, description = "用户不存在", body = Envelope),
+    ),
+)]
+pub async fn delete_user(
+    auth: AuthenticatedUser,
+    session: Session,
+    repo: web::Data<dyn UserRepository>,
+    hub: web::Data<EventHub>,
+    rooms: web::Data<Rooms>,
+    name: web::Path<String>,
+) -> ActixResult<HttpResponse> {
+    let name = name.into_inner();
+    if auth.username != name {
+        return Err(AuthError::Forbidden.into());
+    }
+    let deleted = name.clone();
+    web::block(move || repo.delete(&name)).await??;
+    session.purge();
+    let data = serde_json::json!({ "username": deleted });
+    hub.publish(USERS_CHANNEL, "user_deleted", data.to_string());
+    rooms.broadcast(USERS_CHANNEL, serde_json::json!({ "event": "user_deleted", "data": data }));
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 用户变更事件所在的SSE频道和WebSocket房间
+const USERS_CHANNEL: &str = "users";
+
+/// 发布用户变更事件
+///
+/// SSE频道的事件内容为用户的JSON，
+/// WebSocket房间收到`{"event": "...", "data": 用户}`
+fn publish_user_event(hub: &EventHub, rooms: &Rooms, event: &str, user: &User) {
+    match serde_json::to_value(user) {
+        Ok(data) => {
+            hub.publish(USERS_CHANNEL, event, data.to_string());
+            rooms.broadcast(USERS_CHANNEL, serde_json::json!({ "event": event, "data": data }));
+        }
+        Err(err) => log::error!("无法序列化用户事件: {}", err),
+    }
+}
+
+/// 读取计数器处理函数
+///
+/// 处理GET /counters/{name}请求，不存在的计数器返回0
+///
+/// # 参数
+/// * `counters` - 命名计数器，通过依赖注入获取
+/// * `path` - 路径参数，自动提取为CounterPath结构体并校验
+///
+/// # 返回值
+/// * 返回计数器，按Accept头选择JSON、MessagePack、CBOR或XML
+#[utoipa::path(
+    get, path = "/counters/{name}", tag = "计数器",
+    params(CounterPath),
+    responses(
+        (status = 200, description = "计数器的当前值，不存在时为0", content(
+            (CounterValue = "application/json"), (CounterValue = "application/msgpack"),
+            (CounterValue = "application/cbor"), (CounterValue = "application/xml"),
+        )),
+        (status = 400, description = "计数器名校验失败", body = Envelope),
+        (status = 406, description = "没有可接受的响应格式", body = Envelope),
+    ),
+)]
+#[actix_web::get("/counters/{name}")]
+pub async fn get_counter(
+    counters: web::Data<CounterStore>,
+    path: ValidatedPath<CounterPath>,
+) -> Negotiated<CounterValue> {
+    let name = path.into_inner().name;
+    let value = counters.get(&name);
+    Negotiated(CounterValue { name, value })
+}
+
+/// 修改计数器处理函数
+///
+/// 处理POST /counters/{name}请求，按请求体增加、减少或重置计数器
+/// 只有已登录用户可以修改，匿名客户端不能随意创建计数器
+///
+/// # 参数
+/// * `_auth` - 当前登录用户，匿名请求返回401
+/// * `counters` - 命名计数器，通过依赖注入获取
+/// * `path` - 路径参数，自动提取为CounterPath结构体并校验
+/// * `body` - 请求体，按Content-Type解析JSON、MessagePack、CBOR或XML，提取为CounterUpdate结构体并校验
+///
+/// # 返回值
+/// * 返回修改后的计数器，按Accept头选择格式
+/// * 计数器不存在且数量已达上限时返回409 Conflict
+#[utoipa::path(
+    post, path = "/counters/{name}", tag = "计数器",
+    params(CounterPath),
+    request_body(content(
+        (CounterUpdate = "application/json"), (CounterUpdate = "application/msgpack"),
+        (CounterUpdate = "application/cbor"), (CounterUpdate = "application/xml"),
+    )),
+    security(("session" = []), ("bearer" = [])),
+    responses(
+        (status = 200, description = "修改后的计数器", content(
+            (CounterValue = "application/json"), (CounterValue = "application/msgpack"),
+            (CounterValue = "application/cbor"), (CounterValue = "application/xml"),
+        )),
+        (status = 400, description = "计数器名或请求体校验失败", body = Envelope),
+        (status = 401, description = "未登录", body = Envelope),
+        (status = 406, description = "没有可接受的响应格式", body = Envelope),
+        (status = 409, description = "计数器数量已达上限", body = Envelope),
+        (status = 415, description = "不支持的请求体格式", body = Envelope),
+    ),
+)]
+#[actix_web::post("/counters/{name}")]
+pub async fn update_counter(
+    _auth: AuthenticatedUser,
+    counters: web::Data<CounterStore>,
+    path: ValidatedPath<CounterPath>,
+    body: ValidatedBody<CounterUpdate>,
+) -> ActixResult<Negotiated<CounterValue>> {
+    let name = path.into_inner().name;
+    let amount = body.amount.unwrap_or(1);
+    let value = match body.action {
+        CounterAction::Increment => counters.add(&name, amount)?,
+        CounterAction::Decrement => counters.add(&name, -amount)?,
+        CounterAction::Reset => {
+            counters.reset(&name);
+            0
+        }
+    };
+    Ok(Negotiated(CounterValue { name, value }))
 }
 
-pub async fn updata_user(req: HttpRequest) -> HttpResponse {
-    let name = req.match_info().get("name").unwrap_or("World");
-    HttpResponse::Ok().body(format!("Hello {}!", name))
-}
\ No newline at end of file
+/// 指标处理函数
+///
+/// 处理GET /metrics请求，以Prometheus文本格式返回请求数、耗时直方图、
+/// 正在处理的请求数、SSE流数、TLS握手失败数和命名计数器的值
+///
+/// # 参数
+/// * `metrics` - 服务器指标，通过依赖注入获取
+/// * `counters` - 命名计数器，通过依赖注入获取
+///
+/// # 返回值
+/// * 返回Prometheus文本格式的指标
+#[utoipa::path(
+    get, path = "/metrics", tag = "运维",
+    responses((status = 200, description = "Prometheus文本格式的指标", body = String, content_type = "text/plain")),
+)]
+#[actix_web::get("/metrics")]
+pub async fn metrics_handler(
+    metrics: web::Data<Metrics>,
+    counters: web::Data<CounterStore>,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(metrics::CONTENT_TYPE)
+        .body(metrics.render(&counters))
+}
+
+/// 重定向到HTTPS
+///
+/// 明文HTTP监听器在redirect模式下的默认服务，保留主机名、路径和查询字符串，
+/// 把端口换成HTTPS端口，443时省略；308要求客户端用原来的方法和请求体重新发送
+///
+/// # 参数
+/// * `req` - HTTP请求
+/// * `redirect` - 重定向的目标端口和允许的主机
+///
+/// # 返回值
+/// * 返回308和Location；主机不属于本服务器或无法构造合法的地址时返回400
+pub fn redirect_to_https(req: &HttpRequest, redirect: &HttpsRedirect) -> HttpResponse {
+    let conn = req.connection_info();
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+    let location = redirect
+        .location(conn.host(), path)
+        .and_then(|location| actix_web::http::header::HeaderValue::from_str(&location).ok());
+
+    match location {
+        Some(location) => HttpResponse::PermanentRedirect()
+            .insert_header((actix_web::http::header::LOCATION, location))
+            .finish(),
+        None => HttpResponse::BadRequest().finish(),
+    }
+}
+
+/// 存活检查处理函数
+///
+/// 处理GET /healthz请求，进程能处理请求就返回200，不检查任何依赖
+///
+/// # 返回值
+/// * 返回`{"status": "ok"}`
+#[utoipa::path(
+    get, path = "/healthz", tag = "运维",
+    responses((status = 200, description = "进程存活", body = Object, example = json!({"status": "ok"}))),
+)]
+#[actix_web::get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
+        .json(serde_json::json!({ "status": "ok" }))
+}
+
+/// 就绪检查处理函数
+///
+/// 处理GET /readyz请求，在线程池中执行所有已注册的检查
+///
+/// # 参数
+/// * `health` - 健康检查注册表，通过依赖注入获取
+///
+/// # 返回值
+/// * 就绪时返回200和各项检查的结果
+/// * 任何关键检查失败或服务正在停止时返回503
+#[utoipa::path(
+    get, path = "/readyz", tag = "运维",
+    responses(
+        (status = 200, description = "服务就绪", body = Readiness),
+        (status = 503, description = "关键检查失败或服务正在停止", body = Readiness),
+    ),
+)]
+#[actix_web::get("/readyz")]
+pub async fn readyz(health: web::Data<Health>) -> ActixResult<HttpResponse> {
+    let readiness = web::block(move || health.run()).await?;
+    let status = if readiness.is_ready() {
+        actix_web::http::StatusCode::OK
+    } else {
+        actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(HttpResponse::build(status)
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
+        .json(readiness))
+}
+
+/// 客户端证书处理函数
+///
+/// 处理GET /tls/client请求，返回当前连接经过校验的客户端证书身份，
+/// 用于确认mTLS配置和服务间调用方的身份
+///
+/// # 参数
+/// * `cert` - 客户端证书，连接没有提供证书时返回401
+///
+/// # 返回值
+/// * 返回证书的主题、签发者、SAN、序列号、有效期和指纹
+#[utoipa::path(
+    get, path = "/tls/client", tag = "运维",
+    responses(
+        (status = 200, description = "客户端证书身份", body = ClientCertificate),
+        (status = 401, description = "连接没有提供经过校验的客户端证书", body = Envelope),
+    ),
+)]
+#[actix_web::get("/tls/client")]
+pub async fn client_identity(cert: ClientCertificate) -> web::Json<ClientCertificate> {
+    web::Json(cert)
+}
+
+/// OpenAPI文档处理函数
+///
+/// 处理GET /openapi.json请求，返回由处理函数和数据模型生成的OpenAPI 3文档
+///
+/// # 返回值
+/// * 返回JSON格式的OpenAPI文档
+#[utoipa::path(
+    get, path = "/openapi.json", tag = "文档",
+    responses((status = 200, description = "OpenAPI 3文档", body = Object)),
+)]
+#[actix_web::get("/openapi.json")]
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(openapi::spec())
+}
+
+/// API文档页面处理函数
+///
+/// 处理GET /docs请求，返回加载/openapi.json的Swagger UI页面，
+/// 加上`?ui=redoc`时改用Redoc
+///
+/// # 参数
+/// * `query` - 查询参数，`ui`选择文档界面
+///
+/// # 返回值
+/// * 返回HTML页面
+#[utoipa::path(
+    get, path = "/docs", tag = "文档",
+    params(("ui" = Option<String>, Query, description = "文档界面，swagger（默认）或redoc")),
+    responses((status = 200, description = "Swagger UI和Redoc页面", body = String, content_type = "text/html")),
+)]
+#[actix_web::get("/docs")]
+pub async fn api_docs(query: web::Query<openapi::DocsQuery>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(openapi::docs_page(query.ui))
+}
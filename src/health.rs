@@ -0,0 +1,233 @@
+// 标准库导入
+use std::sync::atomic::{AtomicBool, Ordering};  // 停止标记
+use std::sync::Arc;                        // 共享被检查的组件
+use std::time::Instant;                    // 计算检查耗时
+
+// 外部库导入
+use openssl::asn1::Asn1Time;               // 证书有效期
+use serde::Serialize;                      // 检查结果的序列化
+use serde_json::{json, Value};             // 检查结果的附加信息
+use utoipa::ToSchema;                      // 用于生成OpenAPI文档
+
+// 内部模块导入
+use crate::events::EventHub;               // 事件总线
+use crate::repository::UserRepository;     // 用户存储
+use crate::tls::CertReloader;              // 当前使用的TLS证书
+
+/// 单项检查的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,    // 正常
+    Warn,  // 仍然可用，但需要关注，不影响就绪状态
+    Fail,  // 不可用，关键检查失败时服务未就绪
+}
+
+/// 单项检查的结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CheckOutcome {
+    pub status: CheckStatus,  // 检查状态
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,  // 警告或失败的原因
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub details: Value,       // 附加信息
+}
+
+impl CheckOutcome {
+    /// 检查通过
+    pub fn ok() -> Self {
+        CheckOutcome { status: CheckStatus::Ok, message: None, details: Value::Null }
+    }
+
+    /// 检查通过，但需要关注
+    pub fn warn(message: impl Into<String>) -> Self {
+        CheckOutcome { status: CheckStatus::Warn, message: Some(message.into()), details: Value::Null }
+    }
+
+    /// 检查失败
+    pub fn fail(message: impl Into<String>) -> Self {
+        CheckOutcome { status: CheckStatus::Fail, message: Some(message.into()), details: Value::Null }
+    }
+
+    /// 附加信息
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// 就绪检查
+///
+/// 各个组件实现该接口并注册到`Health`中，`/readyz`会依次执行所有检查
+/// `check`可以阻塞，`Health::run`应通过`web::block`在线程池中调用
+pub trait HealthCheck: Send + Sync {
+    /// 检查名称，出现在`/readyz`的结果中
+    fn name(&self) -> &str;
+
+    /// 失败时是否使整个服务未就绪，默认为true
+    fn critical(&self) -> bool {
+        true
+    }
+
+    /// 执行检查
+    fn check(&self) -> CheckOutcome;
+}
+
+/// 单项检查的报告
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CheckReport {
+    pub name: String,       // 检查名称
+    pub critical: bool,     // 是否是关键检查
+    #[serde(flatten)]
+    pub outcome: CheckOutcome,  // 检查结果
+    pub duration_ms: f64,   // 检查耗时（毫秒）
+}
+
+/// `/readyz`的响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Readiness {
+    pub status: &'static str,     // ready、not_ready或shutting_down
+    pub checks: Vec<CheckReport>, // 各项检查的报告，停止过程中为空
+}
+
+impl Readiness {
+    /// 服务是否就绪
+    pub fn is_ready(&self) -> bool {
+        self.status == "ready"
+    }
+}
+
+/// 健康检查注册表
+///
+/// 启动时注册各个组件的检查，之后放入`web::Data`在工作线程之间共享
+/// 收到停止信号后调用`begin_shutdown`，`/readyz`立即返回503，
+/// 让负载均衡在服务器停止接受连接之前把流量切走
+pub struct Health {
+    checks: Vec<Box<dyn HealthCheck>>,  // 已注册的检查
+    shutting_down: AtomicBool,          // 是否正在停止
+}
+
+impl Health {
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        Health { checks: Vec::new(), shutting_down: AtomicBool::new(false) }
+    }
+
+    /// 注册一项检查
+    pub fn register(&mut self, check: impl HealthCheck + 'static) {
+        self.checks.push(Box::new(check));
+    }
+
+    /// 标记服务正在停止，之后的`/readyz`都返回未就绪
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
+    /// 服务是否正在停止
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// 依次执行所有检查
+    ///
+    /// 任何关键检查失败时服务未就绪；正在停止时不再执行检查
+    pub fn run(&self) -> Readiness {
+        if self.is_shutting_down() {
+            return Readiness { status: "shutting_down", checks: Vec::new() };
+        }
+
+        let checks: Vec<CheckReport> = self
+            .checks
+            .iter()
+            .map(|check| {
+                let started = Instant::now();
+                let outcome = check.check();
+                CheckReport {
+                    name: check.name().to_string(),
+                    critical: check.critical(),
+                    outcome,
+                    duration_ms: (started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0,
+                }
+            })
+            .collect();
+
+        let ready = checks
+            .iter()
+            .all(|report| !report.critical || report.outcome.status != CheckStatus::Fail);
+        Readiness { status: if ready { "ready" } else { "not_ready" }, checks }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 用户存储检查：存储无法访问时服务未就绪
+pub struct RepositoryCheck(pub Arc<dyn UserRepository>);
+
+impl HealthCheck for RepositoryCheck {
+    fn name(&self) -> &str {
+        "repository"
+    }
+
+    fn check(&self) -> CheckOutcome {
+        match self.0.ping() {
+            Ok(()) => CheckOutcome::ok(),
+            Err(err) => CheckOutcome::fail(err.to_string()),
+        }
+    }
+}
+
+/// TLS证书有效期检查
+///
+/// 检查服务器当前实际使用的证书，热更新后立即反映新证书的有效期；
+/// 证书已过期时失败，剩余有效期少于`warn_days`时警告
+pub struct TlsCertificateCheck {
+    pub reloader: Arc<CertReloader>,  // 可热更新的服务器证书
+    pub warn_days: u64,               // 剩余天数少于该值时警告
+}
+
+impl HealthCheck for TlsCertificateCheck {
+    fn name(&self) -> &str {
+        "tls_certificate"
+    }
+
+    fn check(&self) -> CheckOutcome {
+        let cert = self.reloader.certificate();
+        let remaining = match Asn1Time::days_from_now(0).and_then(|now| now.diff(cert.not_after())) {
+            Ok(diff) => diff,
+            Err(err) => return CheckOutcome::fail(format!("无法计算证书有效期: {err}")),
+        };
+        let not_after = cert.not_after().to_string();
+        let details = json!({ "not_after": not_after, "days_remaining": remaining.days });
+
+        let outcome = if remaining.days < 0 || (remaining.days == 0 && remaining.secs <= 0) {
+            CheckOutcome::fail(format!("证书已于{not_after}过期"))
+        } else if (remaining.days as u64) < self.warn_days {
+            CheckOutcome::warn(format!("证书将于{not_after}过期"))
+        } else {
+            CheckOutcome::ok()
+        };
+        outcome.with_details(details)
+    }
+}
+
+/// 事件总线检查：报告频道数和订阅者数，不影响就绪状态
+pub struct EventHubCheck(pub Arc<EventHub>);
+
+impl HealthCheck for EventHubCheck {
+    fn name(&self) -> &str {
+        "event_hub"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    fn check(&self) -> CheckOutcome {
+        let (channels, subscribers) = self.0.stats();
+        CheckOutcome::ok().with_details(json!({ "channels": channels, "subscribers": subscribers }))
+    }
+}
@@ -0,0 +1,138 @@
+// 外部库导入
+use actix_web::dev::Payload;                                      // 请求体载荷
+use actix_web::http::header::{AcceptLanguage, Header, Preference, Quality}; // Accept-Language解析
+use actix_web::{FromRequest, HttpRequest};                        // 提取器相关类型
+use futures::future::{ready, Ready};                              // 用于同步提取器的立即完成的Future
+
+/// 支持的语言
+///
+/// 消息目录以中文为源语言，找不到某种语言的翻译时回退到中文
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Zh,  // 中文（默认）
+    En,  // 英文
+    Ja,  // 日文
+}
+
+impl Locale {
+    /// 默认语言
+    pub const DEFAULT: Locale = Locale::Zh;
+
+    /// 根据语言标签选择语言，只比较主语言子标签
+    ///
+    /// 例如"en-US"、"EN"都对应英文，无法识别时返回None
+    ///
+    /// # 参数
+    /// * `tag` - 语言标签
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let primary = tag.split(['-', '_']).next()?.trim().to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Locale::Zh),
+            "en" => Some(Locale::En),
+            "ja" => Some(Locale::Ja),
+            _ => None,
+        }
+    }
+
+    /// 语言标签，用于Content-Language响应头
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::Zh => "zh",
+            Locale::En => "en",
+            Locale::Ja => "ja",
+        }
+    }
+
+    /// 解析请求的语言
+    ///
+    /// 优先级：Accept-Language请求头（按q值） > 默认语言
+    /// `lang`查询参数只由/query的处理函数自行读取，不参与这里的协商
+    ///
+    /// # 参数
+    /// * `req` - HTTP请求
+    pub fn resolve(req: &HttpRequest) -> Locale {
+        Self::from_accept_language(req).unwrap_or(Locale::DEFAULT)
+    }
+
+    /// 从Accept-Language请求头中选出第一个支持的语言
+    fn from_accept_language(req: &HttpRequest) -> Option<Locale> {
+        let accept = AcceptLanguage::parse(req).ok()?;
+        let mut items: Vec<_> = accept
+            .iter()
+            .filter(|item| item.quality > Quality::ZERO)
+            .collect();
+        // 稳定排序，q值相同时保持客户端给出的顺序
+        items.sort_by_key(|item| std::cmp::Reverse(item.quality));
+
+        items.into_iter().find_map(|item| match &item.item {
+            Preference::Specific(tag) => Locale::from_tag(tag.primary_language()),
+            // "*"表示任意语言均可，使用默认语言
+            Preference::Any => Some(Locale::DEFAULT),
+        })
+    }
+}
+
+impl FromRequest for Locale {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(Locale::resolve(req)))
+    }
+}
+
+/// 消息目录
+///
+/// 以稳定的消息码为键，依次为中文、英文、日文模板
+/// 模板中的`{name}`占位符由调用方提供的参数替换
+const CATALOGUE: &[(&str, [&str; 3])] = &[
+    // 错误消息
+    ("internal_error", ["内部错误", "Internal error", "内部エラー"]),
+    ("timeout", ["请求超时", "Request timed out", "リクエストがタイムアウトしました"]),
+    ("bad_client_data", ["请求错误", "Bad request data", "リクエストが不正です"]),
+    ("validation_error", ["验证错误: {field}", "Validation failed: {field}", "検証エラー: {field}"]),
+    ("user_not_found", ["用户不存在: {username}", "User not found: {username}", "ユーザーが見つかりません: {username}"]),
+    ("username_taken", ["用户名已存在: {username}", "Username already taken: {username}", "ユーザー名は既に使用されています: {username}"]),
+    ("storage_error", ["数据库内部错误", "Internal database error", "データベース内部エラー"]),
+    ("unauthorized", ["需要登录", "Authentication required", "ログインが必要です"]),
+    ("invalid_credentials", ["用户名或密码错误", "Invalid username or password", "ユーザー名またはパスワードが正しくありません"]),
+    ("invalid_token", ["令牌无效或已过期", "Invalid or expired token", "トークンが無効か期限切れです"]),
+    ("forbidden", ["无权操作其他用户", "Not allowed to modify other users", "他のユーザーを操作する権限がありません"]),
+    ("client_certificate_required", ["需要客户端证书", "Client certificate required", "クライアント証明書が必要です"]),
+    ("too_many_attempts", ["登录失败次数过多，请在{retry_after}秒后重试", "Too many failed login attempts, retry after {retry_after} seconds", "ログインの失敗が多すぎます。{retry_after}秒後に再試行してください"]),
+    ("invalid_json", ["JSON格式错误", "Invalid JSON", "JSONの形式が正しくありません"]),
+    ("not_found", ["资源不存在", "Resource not found", "リソースが見つかりません"]),
+    ("rate_limited", ["请求过于频繁，请在{retry_after}秒后重试", "Too many requests, retry after {retry_after} seconds", "リクエストが多すぎます。{retry_after}秒後に再試行してください"]),
+    ("counter_limit_reached", ["计数器数量已达上限{max}，不能再创建新的计数器", "Counter limit of {max} reached, no new counters can be created", "カウンター数が上限の{max}に達したため、新しいカウンターは作成できません"]),
+    ("misdirected_request", ["此连接不能处理主机{host}的请求", "This connection cannot serve requests for host {host}", "この接続ではホスト{host}へのリクエストを処理できません"]),
+    ("not_acceptable", ["没有可接受的响应格式，支持: {supported}", "No acceptable response format, supported: {supported}", "受け入れ可能なレスポンス形式がありません。対応形式: {supported}"]),
+    ("unsupported_media_type", ["不支持的请求体格式，支持: {supported}", "Unsupported request body format, supported: {supported}", "サポートされていないリクエスト形式です。対応形式: {supported}"]),
+    ("invalid_body", ["请求体无法按{format}解析", "Request body is not valid {format}", "リクエスト本文を{format}として解析できません"]),
+    // 处理函数的响应消息
+    ("registration_accepted", ["注册请求已受理，请使用该用户名和密码登录", "Registration received, please log in with this username and password", "登録を受け付けました。このユーザー名とパスワードでログインしてください"]),
+    ("query_greeting", ["来自query_test的问候！查询: {q}", "Hello from query_test! Query: {q}", "query_testからこんにちは！クエリ: {q}"]),
+];
+
+/// 查找并渲染消息
+///
+/// # 参数
+/// * `code` - 消息码
+/// * `locale` - 目标语言
+/// * `args` - 占位符参数，例如`[("field", "email")]`
+///
+/// # 返回值
+/// * 消息码存在时返回渲染后的消息；目标语言的模板为空时回退到中文
+/// * 消息码不存在时返回None，调用方应使用原始消息
+pub fn translate(code: &str, locale: Locale, args: &[(&str, &str)]) -> Option<String> {
+    let (_, templates) = CATALOGUE.iter().find(|(key, _)| *key == code)?;
+    let template = match templates[locale as usize] {
+        "" => templates[Locale::DEFAULT as usize],
+        template => template,
+    };
+
+    let mut message = template.to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{name}}}"), value);
+    }
+    Some(message)
+}
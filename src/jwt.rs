@@ -0,0 +1,315 @@
+// 标准库导入
+use std::time::{SystemTime, UNIX_EPOCH};  // 用于计算令牌的签发和过期时间
+
+// 外部库导入
+use actix_web::body::{BoxBody, MessageBody};            // 响应体类型
+use actix_web::dev::{ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::middleware::Next;                        // 中间件链中的下一个服务
+use actix_web::{http::header, web, HttpMessage};        // 请求头和请求扩展
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};                    // 用于Claims的序列化
+use rand::RngCore;                                      // 用于生成随机密钥
+
+// 内部模块导入
+use crate::errors::AuthError;                       // 认证错误类型
+use crate::models::TokenPair;                       // 令牌响应结构体
+use crate::rate_limit::reject_unauthenticated;      // 认证失败的请求同样计入限流
+use crate::settings::{JwtAlgorithm, JwtSettings};   // JWT配置
+
+/// 令牌类型
+///
+/// 访问令牌和刷新令牌使用同一套密钥签名，通过该字段区分，
+/// 防止刷新令牌被当作访问令牌使用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,   // 访问令牌
+    Refresh,  // 刷新令牌
+}
+
+/// JWT载荷
+///
+/// 校验通过后由中间件放入请求扩展中，处理函数可以通过
+/// `req.extensions().get::<Claims>()`读取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,      // 用户ID的十进制字符串，用户名可以修改和重新注册，不能用来识别用户
+    pub iss: String,      // 签发者
+    pub iat: u64,         // 签发时间（Unix秒）
+    pub exp: u64,         // 过期时间（Unix秒）
+    pub kind: TokenKind,  // 令牌类型
+}
+
+impl Claims {
+    /// 令牌所属用户的ID
+    ///
+    /// 通过`JwtKeys::verify`校验的令牌总能返回Some
+    pub fn user_id(&self) -> Option<i64> {
+        self.sub.parse().ok()
+    }
+}
+
+/// JWT密钥和签发参数
+///
+/// 启动时根据配置加载一次，通过web::Data在工作线程之间共享
+pub struct JwtKeys {
+    algorithm: Algorithm,      // 签名算法
+    encoding: EncodingKey,     // 签名密钥
+    decoding: DecodingKey,     // 校验密钥
+    issuer: String,            // 签发者
+    access_ttl_secs: u64,      // 访问令牌有效期（秒）
+    refresh_ttl_secs: u64,     // 刷新令牌有效期（秒）
+}
+
+impl JwtKeys {
+    /// 根据配置加载密钥
+    ///
+    /// HS256未配置密钥时随机生成，此时重启后已签发的令牌全部失效
+    /// RS256从PEM文件读取私钥，未配置公钥文件时从私钥推导公钥
+    ///
+    /// # 参数
+    /// * `settings` - JWT配置
+    ///
+    /// # 返回值
+    /// * 密钥文件无法读取或格式错误时返回AuthError::KeyLoad
+    pub fn from_settings(settings: &JwtSettings) -> Result<Self, AuthError> {
+        let (algorithm, encoding, decoding) = match settings.algorithm {
+            JwtAlgorithm::HS256 => {
+                let secret = match &settings.secret {
+                    Some(secret) => secret.as_bytes().to_vec(),
+                    None => {
+                        log::warn!("未配置jwt.secret，使用随机生成的密钥，重启后令牌将失效");
+                        let mut secret = vec![0u8; 64];
+                        rand::rngs::OsRng.fill_bytes(&mut secret);
+                        secret
+                    }
+                };
+                (
+                    Algorithm::HS256,
+                    EncodingKey::from_secret(&secret),
+                    DecodingKey::from_secret(&secret),
+                )
+            }
+            JwtAlgorithm::RS256 => {
+                let private_pem = read_pem(&settings.private_key_file)?;
+                let public_pem = match &settings.public_key_file {
+                    Some(path) => read_pem(path)?,
+                    None => openssl::pkey::PKey::private_key_from_pem(&private_pem)
+                        .and_then(|key| key.public_key_to_pem())
+                        .map_err(|err| AuthError::KeyLoad(format!("无法从私钥推导公钥: {err}")))?,
+                };
+                (
+                    Algorithm::RS256,
+                    EncodingKey::from_rsa_pem(&private_pem)
+                        .map_err(|err| AuthError::KeyLoad(format!("RS256私钥格式错误: {err}")))?,
+                    DecodingKey::from_rsa_pem(&public_pem)
+                        .map_err(|err| AuthError::KeyLoad(format!("RS256公钥格式错误: {err}")))?,
+                )
+            }
+        };
+
+        Ok(JwtKeys {
+            algorithm,
+            encoding,
+            decoding,
+            issuer: settings.issuer.clone(),
+            access_ttl_secs: settings.access_ttl_secs,
+            refresh_ttl_secs: settings.refresh_ttl_secs,
+        })
+    }
+
+    /// 为用户签发一对访问令牌和刷新令牌
+    ///
+    /// # 参数
+    /// * `user_id` - 用户ID，写入sub字段
+    pub fn issue(&self, user_id: i64) -> Result<TokenPair, AuthError> {
+        Ok(TokenPair {
+            access_token: self.sign(user_id, TokenKind::Access, self.access_ttl_secs)?,
+            refresh_token: self.sign(user_id, TokenKind::Refresh, self.refresh_ttl_secs)?,
+            token_type: "Bearer",
+            expires_in: self.access_ttl_secs,
+        })
+    }
+
+    /// 校验令牌并返回其载荷
+    ///
+    /// 签名、签发者、过期时间、令牌类型任何一项不符或sub不是用户ID都返回InvalidToken
+    ///
+    /// # 参数
+    /// * `token` - 令牌字符串
+    /// * `kind` - 期望的令牌类型
+    pub fn verify(&self, token: &str, kind: TokenKind) -> Result<Claims, AuthError> {
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.leeway = 0;
+
+        let claims = decode::<Claims>(token, &self.decoding, &validation)
+            .map_err(|err| {
+                log::debug!("令牌校验失败: {}", err);
+                AuthError::InvalidToken
+            })?
+            .claims;
+        if claims.kind != kind || claims.user_id().is_none() {
+            return Err(AuthError::InvalidToken);
+        }
+        Ok(claims)
+    }
+
+    /// 签名一个令牌
+    fn sign(&self, user_id: i64, kind: TokenKind, ttl_secs: u64) -> Result<String, AuthError> {
+        let now = unix_now();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            iss: self.issuer.clone(),
+            iat: now,
+            exp: now + ttl_secs,
+            kind,
+        };
+        encode(&Header::new(self.algorithm), &claims, &self.encoding)
+            .map_err(|err| AuthError::Internal(err.to_string()))
+    }
+}
+
+/// 读取PEM文件
+fn read_pem(path: &std::path::Path) -> Result<Vec<u8>, AuthError> {
+    std::fs::read(path).map_err(|err| AuthError::KeyLoad(format!("{}: {}", path.display(), err)))
+}
+
+/// 当前Unix时间（秒）
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Bearer令牌认证中间件
+///
+/// 请求携带`Authorization: Bearer <token>`时校验访问令牌，认证方案不区分大小写，
+/// 校验通过后把Claims放入请求扩展，校验失败直接返回401，并按对端IP计入限流；
+/// 未携带该请求头的请求原样放行，由具体的处理函数决定是否需要登录
+///
+/// 校验失败时直接生成错误响应而不是返回Err，以便外层的错误渲染中间件处理
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `next` - 中间件链中的下一个服务
+pub async fn bearer_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+        .map(|(_, token)| token.trim().to_string());
+
+    if let Some(token) = token {
+        let verified = match req.app_data::<web::Data<JwtKeys>>() {
+            Some(keys) => keys.verify(&token, TokenKind::Access),
+            None => Err(AuthError::Internal("未注册JwtKeys".to_string())),
+        };
+        match verified {
+            Ok(claims) => {
+                req.extensions_mut().insert(claims);
+            }
+            Err(err) => return Ok(reject_unauthenticated(req, err)),
+        }
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+
+    /// 每个测试使用自己的密钥目录，测试结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("jwt-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        /// 生成一对RSA密钥，写入`{name}.key.pem`和`{name}.pub.pem`
+        fn write_rsa_pair(&self, name: &str) -> (PathBuf, PathBuf) {
+            let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+            let private = self.0.join(format!("{name}.key.pem"));
+            let public = self.0.join(format!("{name}.pub.pem"));
+            std::fs::write(&private, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+            std::fs::write(&public, key.public_key_to_pem().unwrap()).unwrap();
+            (private, public)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn rs256(private_key_file: PathBuf, public_key_file: Option<PathBuf>) -> JwtSettings {
+        JwtSettings { algorithm: JwtAlgorithm::RS256, private_key_file, public_key_file, ..JwtSettings::default() }
+    }
+
+    #[test]
+    fn rs256_keys_load_from_pem_files() {
+        let dir = TempDir::new("rs256");
+        let (private, public) = dir.write_rsa_pair("server");
+
+        let keys = JwtKeys::from_settings(&rs256(private.clone(), Some(public))).unwrap();
+        let pair = keys.issue(42).unwrap();
+        let claims = keys.verify(&pair.access_token, TokenKind::Access).unwrap();
+        assert_eq!(claims.user_id(), Some(42));
+        assert!(keys.verify(&pair.refresh_token, TokenKind::Access).is_err());
+
+        // 未配置公钥文件时从私钥推导，能校验用同一私钥签发的令牌
+        let derived = JwtKeys::from_settings(&rs256(private, None)).unwrap();
+        assert_eq!(derived.verify(&pair.access_token, TokenKind::Access).unwrap().user_id(), Some(42));
+    }
+
+    #[test]
+    fn rs256_rejects_tokens_from_another_key_and_bad_files() {
+        let dir = TempDir::new("rs256-mismatch");
+        let (private, _) = dir.write_rsa_pair("server");
+        let (other_private, other_public) = dir.write_rsa_pair("other");
+
+        let keys = JwtKeys::from_settings(&rs256(private.clone(), Some(other_public))).unwrap();
+        let token = keys.issue(1).unwrap().access_token;
+        assert!(matches!(keys.verify(&token, TokenKind::Access), Err(AuthError::InvalidToken)));
+        let other = JwtKeys::from_settings(&rs256(other_private, None)).unwrap();
+        assert!(matches!(other.verify(&token, TokenKind::Access), Err(AuthError::InvalidToken)));
+
+        let missing = rs256(dir.0.join("missing.pem"), None);
+        assert!(matches!(JwtKeys::from_settings(&missing), Err(AuthError::KeyLoad(_))));
+        let not_a_key = dir.0.join("garbage.pem");
+        std::fs::write(&not_a_key, "not a key").unwrap();
+        assert!(matches!(JwtKeys::from_settings(&rs256(not_a_key, None)), Err(AuthError::KeyLoad(_))));
+    }
+
+    #[test]
+    fn tokens_must_name_a_user_id() {
+        let settings = JwtSettings { secret: Some("x".repeat(32)), ..JwtSettings::default() };
+        let keys = JwtKeys::from_settings(&settings).unwrap();
+
+        // 旧版本以用户名作为sub签发的令牌不再有效
+        let claims = Claims {
+            sub: "alice".to_string(),
+            iss: settings.issuer.clone(),
+            iat: unix_now(),
+            exp: unix_now() + 60,
+            kind: TokenKind::Access,
+        };
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &keys.encoding).unwrap();
+        assert!(matches!(keys.verify(&token, TokenKind::Access), Err(AuthError::InvalidToken)));
+    }
+}
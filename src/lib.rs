@@ -1,14 +1,35 @@
-/// Actix-Web学习示例库
-///
-/// 这个库包含了使用Actix-Web框架的各种示例，
-/// 包括路由处理、错误处理、请求参数提取等功能。
-///
-/// # 模块
-/// * `models` - 数据模型和结构体
-/// * `handlers` - HTTP请求处理函数
-/// * `errors` - 自定义错误类型和实现
-/// * `config` - 应用配置函数
-/// * `utils` - 工具函数
+//! Actix-Web学习示例库
+//!
+//! 这个库包含了使用Actix-Web框架的各种示例，
+//! 包括路由处理、错误处理、请求参数提取等功能。
+//!
+//! # 模块
+//! * `models` - 数据模型和结构体
+//! * `handlers` - HTTP请求处理函数
+//! * `errors` - 自定义错误类型和实现
+//! * `config` - 应用配置函数
+//! * `utils` - 工具函数
+//! * `settings` - 分层加载的服务器配置
+//! * `repository` - 用户存储接口及其实现
+//! * `auth` - 密码哈希、会话和登录用户提取器
+//! * `jwt` - JWT令牌签发、校验和Bearer认证中间件
+//! * `request_id` - 请求ID的分配与传递
+//! * `i18n` - 语言协商和本地化消息目录
+//! * `validation` - 带声明式校验的请求提取器
+//! * `events` - 基于命名频道的事件总线和SSE推送
+//! * `ws` - WebSocket房间和JSON消息协议
+//! * `counters` - 可持久化的命名计数器
+//! * `logging` - 日志初始化和结构化访问日志
+//! * `metrics` - Prometheus格式的服务器指标
+//! * `health` - 存活和就绪检查
+//! * `rate_limit` - 按客户端的令牌桶限流
+//! * `login_guard` - 登录失败计数、延迟和锁定
+//! * `tls` - TLS证书加载和热更新
+//! * `shutdown` - 优雅停止协调
+//! * `openapi` - OpenAPI文档和文档页面
+//! * `app` - 共享组件的创建和完整应用的构建
+//! * `vhost` - 可配置的虚拟主机表和SNI检查
+//! * `negotiation` - JSON、MessagePack、CBOR和XML的内容协商
 
 // 导出所有模块，使它们可以被其他模块引用
 pub mod models;    // 数据模型和结构体
@@ -16,3 +37,24 @@ pub mod handlers;  // HTTP请求处理函数
 pub mod errors;    // 自定义错误类型和实现
 pub mod config;    // 应用配置函数
 pub mod utils;     // 工具函数
+pub mod settings;  // 服务器配置
+pub mod repository; // 用户存储
+pub mod auth;      // 认证和会话
+pub mod jwt;       // JWT令牌
+pub mod request_id; // 请求ID
+pub mod i18n;      // 本地化
+pub mod validation; // 请求校验
+pub mod events;    // 事件总线
+pub mod ws;        // WebSocket
+pub mod counters;  // 命名计数器
+pub mod logging;   // 日志
+pub mod metrics;   // 服务器指标
+pub mod health;    // 健康检查
+pub mod rate_limit; // 限流
+pub mod login_guard; // 登录防暴力破解
+pub mod tls;       // TLS证书
+pub mod shutdown;  // 优雅停止
+pub mod openapi;   // OpenAPI文档
+pub mod app;       // 应用工厂
+pub mod vhost;     // 虚拟主机
+pub mod negotiation; // 内容协商
@@ -0,0 +1,164 @@
+// 标准库导入
+use std::io::Write;                              // 写入日志行
+use std::sync::atomic::{AtomicBool, Ordering};   // 访问日志开关
+use std::time::Instant;                          // 计算请求耗时
+
+// 外部库导入
+use actix_web::body::{BodySize, MessageBody};    // 响应体大小
+use actix_web::dev::{ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::middleware::Next;                 // 中间件链中的下一个服务
+use actix_web::{HttpMessage, HttpRequest};       // 请求扩展
+use serde_json::json;                            // 构造结构化日志
+
+// 内部模块导入
+use crate::auth::AuthenticatedUser;              // 已登录用户
+use crate::jwt::Claims;                          // Bearer令牌的载荷
+use crate::request_id::RequestId;                // 请求ID
+use crate::settings::{LogFormat, LoggingSettings};  // 日志配置
+
+/// 访问日志使用的日志目标
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// 是否输出访问日志，由`init`根据配置设置
+static ACCESS_LOG_ENABLED: AtomicBool = AtomicBool::new(true);
+
+tokio::task_local! {
+    /// 当前请求的ID
+    ///
+    /// 由`access_log`中间件在处理请求的任务中设置，
+    /// 同一任务中输出的日志都会带上该ID；`web::block`等切换到其他线程的代码不会带上
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// 初始化日志系统
+///
+/// 直接按配置构建日志器，不读取也不修改RUST_LOG等环境变量
+///
+/// # 参数
+/// * `settings` - 日志配置
+pub fn init(settings: &LoggingSettings) {
+    ACCESS_LOG_ENABLED.store(settings.access_log, Ordering::Relaxed);
+    let format = settings.format;
+
+    env_logger::Builder::new()
+        .parse_filters(&settings.level)
+        .format(move |buf, record| {
+            let timestamp = buf.timestamp_millis().to_string();
+            let request_id = current_request_id();
+
+            // 访问日志本身就是一个JSON对象，原样输出
+            if record.target() == ACCESS_LOG_TARGET {
+                return writeln!(buf, "{}", record.args());
+            }
+
+            match format {
+                LogFormat::Json => {
+                    let line = json!({
+                        "ts": timestamp,
+                        "level": record.level().as_str(),
+                        "target": record.target(),
+                        "message": record.args().to_string(),
+                        "request_id": request_id,
+                    });
+                    writeln!(buf, "{line}")
+                }
+                LogFormat::Text => match request_id {
+                    Some(id) => writeln!(
+                        buf,
+                        "[{} {} {}] [{}] {}",
+                        timestamp,
+                        record.level(),
+                        record.target(),
+                        id,
+                        record.args()
+                    ),
+                    None => writeln!(buf, "[{} {} {}] {}", timestamp, record.level(), record.target(), record.args()),
+                },
+            }
+        })
+        .init();
+}
+
+/// 当前任务正在处理的请求ID，不在请求处理中时返回None
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// 访问日志中间件
+///
+/// 每个请求输出一行JSON访问日志，包括方法、路径、匹配的路由、状态码、耗时、响应字节数、
+/// 对端地址和登录用户；处理请求期间输出的其他日志也会带上同一个请求ID
+/// 必须位于`request_id`中间件之内，才能读到请求ID
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `next` - 中间件链中的下一个服务
+pub async fn access_log(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_else(|| RequestId::generate().0);
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let peer = req.peer_addr().map(|addr| addr.to_string());
+
+    let result = CURRENT_REQUEST_ID.scope(request_id.clone(), next.call(req)).await;
+    if !ACCESS_LOG_ENABLED.load(Ordering::Relaxed) {
+        return result;
+    }
+
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let mut line = json!({
+        "ts": now_rfc3339(),
+        "type": "access",
+        "request_id": request_id,
+        "method": method,
+        "path": path,
+        "peer": peer,
+        "latency_ms": (latency_ms * 1000.0).round() / 1000.0,
+    });
+
+    match &result {
+        Ok(res) => {
+            let bytes = match res.response().body().size() {
+                BodySize::Sized(size) => Some(size),
+                BodySize::None => Some(0),
+                BodySize::Stream => None,  // 流式响应的大小在日志输出时还不知道
+            };
+            line["status"] = json!(res.status().as_u16());
+            line["bytes"] = json!(bytes);
+            line["route"] = json!(res.request().match_pattern());
+            line["route_name"] = json!(res.request().match_name());
+            line["user"] = json!(user_of(res.request()));
+        }
+        Err(err) => {
+            line["status"] = json!(err.as_response_error().status_code().as_u16());
+            line["error"] = json!(err.to_string());
+        }
+    }
+
+    log::info!(target: ACCESS_LOG_TARGET, "{line}");
+    result
+}
+
+/// 读取请求对应的登录用户
+///
+/// 处理函数提取过AuthenticatedUser时记录用户名；
+/// 否则Bearer令牌认证的请求只能从Claims读到用户ID，会话登录的请求不记录
+fn user_of(req: &HttpRequest) -> Option<String> {
+    let extensions = req.extensions();
+    extensions
+        .get::<AuthenticatedUser>()
+        .map(|user| user.username.clone())
+        .or_else(|| extensions.get::<Claims>().map(|claims| claims.sub.clone()))
+}
+
+/// 当前时间的RFC 3339字符串（UTC，毫秒精度）
+fn now_rfc3339() -> String {
+    humantime::format_rfc3339_millis(std::time::SystemTime::now()).to_string()
+}
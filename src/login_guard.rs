@@ -0,0 +1,367 @@
+// 标准库导入
+use std::collections::HashMap;          // 失败记录表
+use std::fmt;                           // 显示被锁定的对象
+use std::net::IpAddr;                   // 对端IP
+use std::sync::{Arc, Mutex};            // 失败记录表的互斥锁
+use std::time::{Duration, Instant};     // 时间窗口和锁定时间
+
+// 内部模块导入
+use crate::settings::LoginGuardSettings;  // 登录防暴力破解配置
+
+/// 锁定等安全事件使用的审计日志目标
+pub const AUDIT_LOG_TARGET: &str = "audit";
+
+/// 统计失败次数的对象
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Subject {
+    User(String),  // 用户名，不论该用户是否存在
+    Ip(IpAddr),    // 对端IP
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::User(username) => write!(f, "user:{username}"),
+            Subject::Ip(ip) => write!(f, "ip:{ip}"),
+        }
+    }
+}
+
+/// 一个对象的失败记录
+#[derive(Debug)]
+struct Record {
+    failures: u32,                  // 时间窗口内的失败次数
+    window_start: Instant,          // 时间窗口的开始时间
+    locked_until: Option<Instant>,  // 锁定的结束时间
+}
+
+/// 一次新的锁定，用于记录审计事件
+#[derive(Debug, Clone)]
+pub struct Lockout {
+    pub subject: Subject,    // 被锁定的对象
+    pub failures: u32,       // 锁定前的失败次数
+    pub duration: Duration,  // 锁定时间
+}
+
+/// 登录防暴力破解
+///
+/// 分别按用户名和对端IP统计登录失败次数：
+/// * 每次失败后按失败次数逐步延迟响应，拖慢自动化的尝试
+/// * 时间窗口内失败达到上限后锁定，锁定期间即使密码正确也会被拒绝
+/// * 不存在的用户名同样计数和锁定，锁定本身不会泄露用户名是否存在
+///
+/// 每次尝试在校验密码之前就计为失败，校验成功后再撤销，并发的尝试同样受限；
+/// 登录成功只清除该用户名的记录，IP的记录保留到时间窗口结束
+/// 记录只保存在内存中，由`run_sweeper`清理过期的记录
+pub struct LoginGuard {
+    settings: LoginGuardSettings,              // 配置
+    records: Mutex<HashMap<Subject, Record>>,  // 对象到失败记录
+}
+
+impl LoginGuard {
+    /// 根据配置创建
+    pub fn from_settings(settings: &LoginGuardSettings) -> Self {
+        LoginGuard {
+            settings: settings.clone(),
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 开始一次登录尝试
+    ///
+    /// 在同一次加锁中检查锁定并预先把这次尝试计为失败，
+    /// 并发的尝试在校验密码之前就已计数，不能同时绕过锁定；
+    /// 校验成功后用`record_success`撤销计数，校验未能完成时用`cancel`撤销
+    ///
+    /// # 参数
+    /// * `username` - 用户名
+    /// * `ip` - 对端IP，未知时只按用户名计数
+    ///
+    /// # 返回值
+    /// * 成功时返回这次尝试的凭据，包含失败时应当延迟的时间和这次计数新触发的锁定
+    /// * 用户名或IP已被锁定时返回剩余的锁定时间
+    pub fn begin_attempt(&self, username: &str, ip: Option<IpAddr>) -> Result<AttemptTicket, Duration> {
+        self.begin_attempt_at(username, ip, Instant::now())
+    }
+
+    /// 在给定时刻开始一次登录尝试
+    fn begin_attempt_at(&self, username: &str, ip: Option<IpAddr>, now: Instant) -> Result<AttemptTicket, Duration> {
+        let subjects = subjects(username, ip);
+        let mut ticket = AttemptTicket {
+            username: username.to_string(),
+            counted: Vec::new(),
+            delay: Duration::ZERO,
+            lockouts: Vec::new(),
+        };
+        if !self.settings.enabled {
+            return Ok(ticket);
+        }
+
+        let window = Duration::from_secs(self.settings.failure_window_secs);
+        let lockout = Duration::from_secs(self.settings.lockout_secs);
+        let mut records = self.records();
+        if let Some(remaining) = remaining_lock(&records, &subjects, now) {
+            return Err(remaining);
+        }
+
+        let mut max_failures = 0;
+        for subject in subjects {
+            let limit = match subject {
+                Subject::User(_) => self.settings.max_failures_per_user,
+                Subject::Ip(_) => self.settings.max_failures_per_ip,
+            };
+            let record = records.entry(subject.clone()).or_insert(Record {
+                failures: 0,
+                window_start: now,
+                locked_until: None,
+            });
+            // 时间窗口已过，重新计数
+            if now.duration_since(record.window_start) > window {
+                record.failures = 0;
+                record.window_start = now;
+            }
+            record.failures += 1;
+            max_failures = max_failures.max(record.failures);
+
+            // 上面已确认没有被锁定，达到上限即触发新的锁定
+            if record.failures >= limit {
+                record.locked_until = Some(now + lockout);
+                ticket.lockouts.push(Lockout { subject: subject.clone(), failures: record.failures, duration: lockout });
+            }
+            ticket.counted.push(subject);
+        }
+
+        ticket.delay = self.delay_for(max_failures);
+        Ok(ticket)
+    }
+
+    /// 登录成功：撤销这次尝试的计数，并清除该用户名的失败记录
+    ///
+    /// IP的记录保留到时间窗口结束
+    pub fn record_success(&self, ticket: AttemptTicket) {
+        let username = ticket.username.clone();
+        let mut records = self.records();
+        undo(&mut records, ticket);
+        records.remove(&Subject::User(username));
+    }
+
+    /// 校验未能完成（例如存储出错）：撤销这次尝试的计数和它触发的锁定
+    pub fn cancel(&self, ticket: AttemptTicket) {
+        undo(&mut self.records(), ticket);
+    }
+
+    /// 第`failures`次失败后的延迟：从`base_delay_ms`开始每次翻倍，不超过`max_delay_ms`
+    fn delay_for(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return Duration::ZERO;
+        }
+        let factor = 1u64.checked_shl(failures - 1).unwrap_or(u64::MAX);
+        let delay = self.settings.base_delay_ms.saturating_mul(factor).min(self.settings.max_delay_ms);
+        Duration::from_millis(delay)
+    }
+
+    /// 定期清理时间窗口和锁定都已结束的记录，直到进程退出
+    pub async fn run_sweeper(self: Arc<Self>) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let window = Duration::from_secs(self.settings.failure_window_secs);
+        let mut ticker = tokio::time::interval(window.min(Duration::from_secs(60)));
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            self.records().retain(|_, record| {
+                now.duration_since(record.window_start) <= window
+                    || record.locked_until.is_some_and(|until| until > now)
+            });
+        }
+    }
+
+    /// 获取失败记录表的锁
+    ///
+    /// 锁内只做简单的表操作，不会panic，锁中毒时直接沿用内部数据
+    fn records(&self) -> std::sync::MutexGuard<'_, HashMap<Subject, Record>> {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 一次已计数的登录尝试
+///
+/// 由`LoginGuard::begin_attempt`返回，校验失败时直接使用其中的延迟和锁定，
+/// 校验成功或未完成时交回`LoginGuard`撤销计数
+#[derive(Debug)]
+pub struct AttemptTicket {
+    username: String,             // 用户名
+    counted: Vec<Subject>,        // 已计数的对象
+    pub delay: Duration,          // 失败响应应当延迟的时间
+    pub lockouts: Vec<Lockout>,   // 这次计数新触发的锁定
+}
+
+/// 对象中最长的剩余锁定时间
+fn remaining_lock(records: &HashMap<Subject, Record>, subjects: &[Subject], now: Instant) -> Option<Duration> {
+    subjects
+        .iter()
+        .filter_map(|subject| records.get(subject)?.locked_until)
+        .filter(|until| *until > now)
+        .map(|until| until - now)
+        .max()
+}
+
+/// 撤销一次尝试的计数和它触发的锁定
+fn undo(records: &mut HashMap<Subject, Record>, ticket: AttemptTicket) {
+    for subject in ticket.counted {
+        let Some(record) = records.get_mut(&subject) else {
+            continue;
+        };
+        record.failures = record.failures.saturating_sub(1);
+        if ticket.lockouts.iter().any(|lockout| lockout.subject == subject) {
+            record.locked_until = None;
+        }
+    }
+}
+
+/// 一次登录尝试涉及的对象
+fn subjects(username: &str, ip: Option<IpAddr>) -> Vec<Subject> {
+    let mut subjects = vec![Subject::User(username.to_string())];
+    subjects.extend(ip.map(Subject::Ip));
+    subjects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+    /// 用户名3次、IP5次失败后锁定，时间窗口和锁定时间都是60秒
+    fn guard() -> LoginGuard {
+        LoginGuard::from_settings(&LoginGuardSettings {
+            enabled: true,
+            max_failures_per_user: 3,
+            max_failures_per_ip: 5,
+            failure_window_secs: 60,
+            lockout_secs: 60,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        })
+    }
+
+    fn locked_subjects(ticket: &AttemptTicket) -> Vec<Subject> {
+        ticket.lockouts.iter().map(|lockout| lockout.subject.clone()).collect()
+    }
+
+    #[test]
+    fn user_limit_locks_on_the_nth_attempt() {
+        let guard = guard();
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            let ticket = guard.begin_attempt_at("alice", Some(IP), now).unwrap();
+            assert!(ticket.lockouts.is_empty());
+        }
+        let ticket = guard.begin_attempt_at("alice", Some(IP), now).unwrap();
+        assert_eq!(locked_subjects(&ticket), vec![Subject::User("alice".to_string())]);
+        assert_eq!(ticket.lockouts[0].failures, 3);
+
+        let remaining = guard.begin_attempt_at("alice", Some(IP), now).unwrap_err();
+        assert_eq!(remaining, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn ip_limit_locks_across_usernames() {
+        let guard = guard();
+        let now = Instant::now();
+
+        for name in ["a", "b", "c", "d"] {
+            assert!(guard.begin_attempt_at(name, Some(IP), now).unwrap().lockouts.is_empty());
+        }
+        let ticket = guard.begin_attempt_at("e", Some(IP), now).unwrap();
+        assert_eq!(locked_subjects(&ticket), vec![Subject::Ip(IP)]);
+
+        // 同一IP上没有失败过的用户名同样被拒绝，IP未知时只按用户名判断
+        assert!(guard.begin_attempt_at("f", Some(IP), now).is_err());
+        assert!(guard.begin_attempt_at("f", None, now).is_ok());
+    }
+
+    #[test]
+    fn failures_reset_after_the_window() {
+        let guard = guard();
+        let now = Instant::now();
+        for _ in 0..2 {
+            guard.begin_attempt_at("alice", None, now).unwrap();
+        }
+
+        // 时间窗口结束后重新计数，第3次失败不会触发锁定
+        let later = now + Duration::from_secs(61);
+        let ticket = guard.begin_attempt_at("alice", None, later).unwrap();
+        assert!(ticket.lockouts.is_empty());
+        assert_eq!(ticket.delay, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn lockout_expires() {
+        let guard = guard();
+        let now = Instant::now();
+        for _ in 0..3 {
+            guard.begin_attempt_at("alice", None, now).unwrap();
+        }
+        assert_eq!(guard.begin_attempt_at("alice", None, now + Duration::from_secs(45)).unwrap_err(), Duration::from_secs(15));
+        assert!(guard.begin_attempt_at("alice", None, now + Duration::from_secs(61)).is_ok());
+    }
+
+    #[test]
+    fn cancel_reverts_the_lockout_it_triggered() {
+        let guard = guard();
+        let now = Instant::now();
+        for _ in 0..2 {
+            guard.begin_attempt_at("alice", None, now).unwrap();
+        }
+        let ticket = guard.begin_attempt_at("alice", None, now).unwrap();
+        assert_eq!(ticket.lockouts.len(), 1);
+        guard.cancel(ticket);
+
+        // 计数回到2，下一次尝试重新触发锁定
+        let ticket = guard.begin_attempt_at("alice", None, now).unwrap();
+        assert_eq!(ticket.lockouts.len(), 1);
+        assert_eq!(ticket.lockouts[0].failures, 3);
+    }
+
+    #[test]
+    fn success_clears_user_but_keeps_ip_failures() {
+        let guard = guard();
+        let now = Instant::now();
+        for _ in 0..2 {
+            guard.begin_attempt_at("alice", Some(IP), now).unwrap();
+        }
+        let ticket = guard.begin_attempt_at("alice", Some(IP), now).unwrap();
+        guard.record_success(ticket);
+
+        // 用户名的记录被清除，IP仍然记有2次失败
+        assert!(guard.begin_attempt_at("alice", Some(IP), now).unwrap().lockouts.is_empty());
+        for name in ["b", "c"] {
+            guard.begin_attempt_at(name, Some(IP), now).unwrap();
+        }
+        assert!(guard.begin_attempt_at("d", Some(IP), now).is_err());
+    }
+
+    #[test]
+    fn delay_doubles_and_saturates() {
+        let guard = guard();
+        let delays: Vec<u64> = [0, 1, 2, 3, 4, 5].map(|failures| guard.delay_for(failures).as_millis() as u64).to_vec();
+        assert_eq!(delays, vec![0, 100, 200, 400, 800, 1000]);
+
+        // 移位超出u64时同样停在上限
+        assert_eq!(guard.delay_for(64), Duration::from_millis(1000));
+        assert_eq!(guard.delay_for(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn disabled_guard_never_counts() {
+        let guard = LoginGuard::from_settings(&LoginGuardSettings { enabled: false, ..LoginGuardSettings::default() });
+        for _ in 0..10 {
+            let ticket = guard.begin_attempt("alice", Some(IP)).unwrap();
+            assert_eq!(ticket.delay, Duration::ZERO);
+        }
+    }
+}
@@ -1,144 +1,188 @@
-// 导入自定义模块
-mod config;    // 包含应用程序的配置函数
-mod errors;    // 包含自定义错误类型和实现
-mod handlers;  // 包含所有HTTP请求处理函数
-mod models;    // 包含数据模型和结构体
-mod utils;     // 包含工具函数
-
 // 标准库导入
-use std::sync::Mutex;  // 用于线程安全的共享状态
-use std::time::Duration;  // 用于设置时间相关的配置
+use std::process::ExitCode;  // 进程退出状态
+use std::sync::Arc;  // 在工作线程和TLS回调之间共享指标和证书
 
 // 外部库导入
-use actix_web::middleware::Logger;  // 用于请求日志记录
-use actix_web::{guard, web, HttpServer};   // Web服务器和Web相关工具
-use openssl::ssl::{SslAcceptor, SslFiletype};
-// use rand::seq::index;  // SSL/TLS支持
+use actix_web::middleware::from_fn;  // 用于函数式中间件
+use actix_web::HttpServer;   // Web服务器
 
-// 从自定义模块导入特定组件
-// 导入配置函数
-use crate::config::{config, config_error, config2, json_config};
-// 导入所有HTTP请求处理函数
-use crate::handlers::{
-    echo, first_hello, index_by_my_error, login, manual_hello, my_struct_test, path_test,
-    path_test_by_struct, process_data, process_form, query_test, stream_handler,index_resource,
-    get_user, updata_user
-};
-// 导入应用状态结构体
-use crate::models::{AppState, AppStateWithCounter};
+// 从库模块导入特定组件
+// 导入应用工厂和共享组件
+use web_learning::app::{build_app, AppServices};
+// 导入明文HTTP监听器的路由配置
+use web_learning::config::config_plain_http;
+// 导入错误渲染和请求ID中间件
+use web_learning::errors::render_api_errors;
+use web_learning::request_id::request_id;
+// 导入日志初始化和访问日志中间件
+use web_learning::logging::{self, access_log};
+// 导入TLS证书热更新
+use web_learning::tls::{self, CertReloader};
+// 导入优雅停止协调器
+use web_learning::shutdown::ShutdownCoordinator;
+// 导入服务器配置
+use web_learning::settings::{ListenerKind, Settings};
+// 导入明文HTTP重定向的目标
+use web_learning::vhost::HttpsRedirect;
 
 /// 应用程序入口点
 ///
 /// 使用actix_web宏将异步函数标记为应用程序入口点
 /// 配置并启动HTTPS服务器，设置路由和中间件
 #[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    // 创建一个计数器状态，用于在请求之间共享
-    let counter_data = web::Data::new(AppStateWithCounter {
-        counter: Mutex::new(0), // 初始化为0的线程安全计数器
-    });
+async fn main() -> std::io::Result<ExitCode> {
+    // 按 默认值 < 配置文件 < 环境变量 < 命令行参数 的顺序加载配置
+    // 配置不合法时打印所有错误并退出，不启动服务器
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("配置加载失败: {err}");
+            std::process::exit(2);
+        }
+    };
 
-    // 加载SSL证书，配置HTTPS支持
-    // 使用Mozilla推荐的中间安全级别配置
-    let mut builder = SslAcceptor::mozilla_intermediate(openssl::ssl::SslMethod::tls()).unwrap();
+    // 按配置初始化日志系统，不修改进程环境变量
+    logging::init(&settings.logging);
+    for key in &settings.ignored_env {
+        log::warn!("忽略未知的环境变量: {}", key);
+    }
+    log::info!("使用配置启动服务器: {:?}", settings);
 
-    // 设置私钥文件
-    builder
-        .set_private_key_file("key.pem", SslFiletype::PEM)
-        .expect("Failed to set private key");
+    // 加载TLS证书，文件变化或收到SIGHUP时在后台重新加载
+    let cert_reloader = match CertReloader::from_settings(&settings.tls) {
+        Ok(reloader) => Arc::new(reloader),
+        Err(err) => {
+            eprintln!("TLS证书加载失败: {err}");
+            std::process::exit(2);
+        }
+    };
+    actix_web::rt::spawn(cert_reloader.clone().watch());
 
-    // 设置证书链文件
-    builder
-        .set_certificate_chain_file("cert.pem")
-        .expect("Failed to set certificate chain");
+    // 创建计数器、用户存储、JWT密钥、事件总线、健康检查等共享组件，
+    // 所有监听器的所有工作线程共享同一份
+    let services = match AppServices::from_settings(&settings, Some(cert_reloader.clone())) {
+        Ok(services) => services,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
+        }
+    };
+    // 在后台定期写入计数器快照，清理补满的令牌桶和过期的登录失败记录
+    actix_web::rt::spawn(services.counters.clone().into_inner().run_snapshots());
+    actix_web::rt::spawn(services.rate_limiter.clone().into_inner().run_sweeper());
+    actix_web::rt::spawn(services.login_guard.clone().into_inner().run_sweeper());
 
-    // 配置日志环境变量
-    // 使用unsafe块是因为环境变量修改是全局性的
-    unsafe {
-        std::env::set_var("RUST_LOG", "info");       // 设置日志级别为info
-        std::env::set_var("RUST_BACKTRACE", "1");    // 启用错误回溯
-    }
+    // 保留停止流程需要的引用：标记未就绪、结束SSE流、判断请求是否处理完成、写入最后一次快照
+    let shutdown_health = services.health.clone().into_inner();
+    let shutdown_hub = services.event_hub.clone().into_inner();
+    let shutdown_counters = services.counters.clone().into_inner();
+    // 各个HTTPS监听器的TLS握手回调同样使用这份指标
+    let shared_metrics = services.metrics.clone().into_inner();
 
-    // 初始化日志系统
-    env_logger::init();
+    // 创建应用工厂，提供完整应用的每个监听器的每个工作线程各调用一次
+    // move关键字将共享组件的所有权移入闭包
+    let app = move || build_app(&services);
 
-    // 创建新的HTTP服务器
-    // move关键字将counter_data所有权移入闭包
-    HttpServer::new(move || {
-        // 创建默认日志记录器
-        let logger = Logger::default();
+    // 每个监听器单独运行一个HttpServer，各自的工作线程数和连接数限制互不影响
+    let server_settings = &settings.server;
+    // 明文HTTP重定向的目标端口和允许重定向的主机
+    let https_redirect = HttpsRedirect::from_settings(&settings);
+    let mut servers = Vec::new();
+    for listener in server_settings.all_listeners() {
+        let workers = listener.workers.unwrap_or(server_settings.workers);
+        let max_connections = listener.max_connections.unwrap_or(server_settings.max_connections);
+        let max_connection_rate = listener.max_connection_rate.unwrap_or(server_settings.max_connection_rate);
+        log::info!(
+            "监听{:?} {}，工作线程{}个，每个工作线程最多{}个连接",
+            listener.kind, listener.bind, workers, max_connections
+        );
 
-        // 创建新的应用实例，配置中间件和路由
-        actix_web::App::new()
-            // 添加日志中间件
-            .wrap(logger)
-            // 添加应用状态数据
-            .app_data(web::Data::new(AppState {
-                app_name: "Kayano".to_string(),  // 设置应用名称
-            }))
-            // 添加计数器状态数据
-            .app_data(counter_data.clone())
+        // 提供完整应用的服务器
+        let full_app = || {
+            HttpServer::new(app.clone())
+                // 连接建立时标记TLS握手完成，并保存客户端证书的身份
+                .on_connect(tls::on_connect)
+                // 服务器全局配置取自Settings，连接数限制可以按监听器覆盖
+                .keep_alive(server_settings.keep_alive())             // 设置保持连接的时间
+                .workers(workers)                                     // 设置工作线程数
+                .max_connections(max_connections)                     // 设置最大连接数
+                .max_connection_rate(max_connection_rate)             // 设置最大连接速率
+                .client_disconnect_timeout(server_settings.client_disconnect_timeout()) // 设置关闭连接的超时时间
+                .backlog(server_settings.backlog)                     // 设置请求队列的长度
+                .shutdown_timeout(server_settings.shutdown_timeout_secs) // 设置关闭服务器的超时时间
+                .disable_signals()                                    // 停止信号由下面的任务处理
+        };
 
-            // 配置路由组
-            .configure(config)         // 配置/app路径下的路由
-            .configure(config_error)   // 配置/error路径下的路由
+        let server = match listener.kind {
+            // 明文HTTP只重定向到HTTPS或提供/healthz，不暴露完整的应用
+            ListenerKind::Http => {
+                let mode = listener.mode;
+                let https_redirect = https_redirect.clone();
+                HttpServer::new(move || {
+                    actix_web::App::new()
+                        .wrap(from_fn(render_api_errors))
+                        .wrap(from_fn(access_log))
+                        .wrap(from_fn(request_id))
+                        .configure(config_plain_http(mode, https_redirect.clone()))
+                })
+                .keep_alive(server_settings.keep_alive())
+                .workers(workers)
+                .max_connections(max_connections)
+                .client_disconnect_timeout(server_settings.client_disconnect_timeout())
+                .backlog(server_settings.backlog)
+                .shutdown_timeout(server_settings.shutdown_timeout_secs)
+                .disable_signals()
+                .bind(&listener.bind)?
+                .run()
+            }
+            // 证书在每次握手时设置，同时统计未完成的TLS握手，按配置校验客户端证书
+            ListenerKind::Https => {
+                let builder = match tls::acceptor(cert_reloader.clone(), &settings.tls, shared_metrics.clone()) {
+                    Ok(builder) => builder,
+                    Err(err) => {
+                        eprintln!("TLS配置失败: {err}");
+                        std::process::exit(2);
+                    }
+                };
+                full_app().bind_openssl(&listener.bind, builder)?.run()
+            }
+            // 已有的socket文件会被删除后重新创建
+            #[cfg(unix)]
+            ListenerKind::Unix => full_app().bind_uds(&listener.bind)?.run(),
+            // 配置校验已经拒绝了这种情况，这里仍作为启动错误返回而不是panic
+            #[cfg(not(unix))]
+            ListenerKind::Unix => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("当前平台不支持Unix domain socket: {}", listener.bind),
+                ));
+            }
+        };
+        servers.push(server);
+    }
+
+    // 收到停止信号后由协调器标记未就绪、停止接受连接、结束SSE流并等待请求处理完成
+    let coordinator = ShutdownCoordinator::new(
+        servers.iter().map(|server| server.handle()).collect(),
+        shutdown_health,
+        shutdown_hub,
+        shared_metrics,
+        shutdown_counters,
+        &settings,
+    );
+    let shutdown = actix_web::rt::spawn(coordinator.run());
+    futures::future::try_join_all(servers).await?;        // 等待所有服务器运行完成
+
+    // 删除Unix domain socket文件
+    for listener in &server_settings.listeners {
+        if listener.kind == ListenerKind::Unix
+            && let Err(err) = std::fs::remove_file(&listener.bind)
+        {
+            log::warn!("无法删除socket文件{}: {}", listener.bind, err);
+        }
+    }
 
-            // 注册各个路由处理函数
-            .service(first_hello)          // 处理根路径"/"
-            .configure(config2)            // 配置/app2路径下的路由
-            .service(echo)                 // 处理POST /echo
-            .service(path_test)            // 处理GET /path/{user_id}/{name}
-            .service(path_test_by_struct)  // 处理GET /path2/{user_id}/{name}
-            .service(query_test)           // 处理GET /query
-            .service(login)                // 处理POST /login
-            .service(my_struct_test)       // 处理GET /my_struct
-            .service(stream_handler)       // 处理GET /sse
-            .service(process_data)         // 处理GET /process
-            .service(index_by_my_error)    // 处理GET /first_error
-            .service(process_form)         // 处理GET /form_test
-            // 注册一个简单的资源路由，路径为"/perix"
-            // 当访问 /perix 时，所有HTTP方法的请求都会被转发到index_resource处理函数
-            .service(web::resource("/perix").to(index_resource))
-            
-            // 注册一个带路径参数的复杂资源路由
-            .service(
-                // 定义资源路径为"user/{name}"，其中{name}是动态路径参数
-                // 例如：user/alice、user/bob等都会匹配这个路由
-                web::resource("user/{name}")
-                // 为该路由指定名称"user_detail"，可用于反向URL生成
-                // 例如：req.url_for("user_detail", &["alice"]) 会生成 /user/alice
-                .name("user_detail")
-                // 添加请求守卫(guard)，只有当请求头中的Content-Type为"application/json"时才会匹配该路由
-                // 如果请求头不符合条件，路由匹配会失败，请求会继续尝试匹配其他路由
-                // 这对于确保只处理特定格式的请求非常有用，例如只接受JSON格式的数据
-                .guard(guard::Header("content-type", "application/json"))
-                // 配置GET请求的处理函数
-                // 当收到 GET /user/{name} 请求时，调用get_user函数处理
-                .route(web::get().to(get_user))
-                // 配置PUT请求的处理函数
-                // 当收到 PUT /user/{name} 请求时，调用updata_user函数处理
-                // 注：这里可能是拼写错误，应为update_user而非updata_user
-                .route(web::put().to(updata_user))
-            )
-            // 手动注册路由，不使用宏
-            .route("/hey", web::get().to(manual_hello))
-            
-            // 配置带有JSON配置的路由
-            .service(
-                web::resource("/config")
-                    .app_data(json_config(4096))  // 设置JSON请求体最大长度为4096字节
-                    .route(web::post().to(handlers::json_test)),  // 设置POST处理函数
-            )
-    })
-    // 服务器全局配置
-    .keep_alive(Duration::from_secs(75))    // 设置保持连接的时间为75秒
-    .workers(10)                            // 设置工作线程数为10
-    .max_connections(100)                   // 设置最大连接数为100
-    .max_connection_rate(10)                // 设置最大连接速率为每秒10个
-    .shutdown_timeout(10)                   // 设置关闭连接的超时时间为10秒
-    .backlog(100)                           // 设置请求队列的长度为100
-    .shutdown_timeout(60)                   // 设置关闭服务器的超时时间为60秒
-    .bind_openssl("127.0.0.1:8087", builder)? // 绑定到127.0.0.1:8087，使用SSL
-    .run()                                  // 运行服务器
-    .await                                  // 等待服务器运行完成
+    // 退出状态反映是否在期限内处理完所有请求
+    let report = shutdown.await.map_err(std::io::Error::other)?;
+    Ok(report.exit_code())
 }
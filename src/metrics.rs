@@ -0,0 +1,364 @@
+// 标准库导入
+use std::any::Any;                                     // on_connect收到的连接类型
+use std::collections::BTreeMap;                        // 按标签排序的指标
+use std::fmt::Write;                                   // 拼接文本格式
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};  // 无锁计数
+use std::sync::{Arc, Mutex, OnceLock};                 // 共享状态和扩展数据索引
+use std::time::Instant;                                // 计算请求耗时
+
+// 外部库导入
+use actix_tls::accept::openssl::TlsStream;             // OpenSSL连接类型
+use actix_web::body::MessageBody;                      // 响应体
+use actix_web::dev::{Extensions, ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::middleware::Next;                       // 中间件链中的下一个服务
+use actix_web::rt::net::TcpStream;                     // TCP连接
+use actix_web::{web, HttpRequest};                     // 共享状态和请求
+use futures::{Stream, StreamExt};                      // SSE流
+use openssl::ex_data::Index;                           // SSL扩展数据索引
+use openssl::ssl::{Ssl, SslRef};                       // TLS握手回调
+
+// 内部模块导入
+use crate::counters::CounterStore;                     // 命名计数器
+
+/// 请求耗时直方图的桶上限（秒），与Prometheus客户端库的默认值一致
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// 没有匹配任何路由的请求使用的route标签，避免把原始路径写进标签
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Prometheus文本格式的Content-Type
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 每个SSL对象上握手状态的扩展数据索引
+static HANDSHAKE_INDEX: OnceLock<Option<Index<Ssl, HandshakeGuard>>> = OnceLock::new();
+
+/// 请求指标的标签
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct RouteLabels {
+    route: String,         // 匹配的路由模式，例如/path/{user_id}/{name}
+    name: String,          // 路由名称，例如user_detail，没有名称时为空
+    status: &'static str,  // 状态码类别，例如2xx
+}
+
+/// 请求耗时直方图
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],  // 每个桶内的请求数（非累计）
+    sum: f64,                               // 耗时总和（秒）
+    count: u64,                             // 请求总数
+}
+
+impl Histogram {
+    /// 记录一次耗时
+    fn observe(&mut self, seconds: f64) {
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|le| seconds <= *le) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// 服务器指标
+///
+/// 由`track_requests`中间件记录请求数和耗时，`/metrics`以Prometheus文本格式导出
+/// 请求按匹配的路由模式和名称分组，不使用原始路径，标签数量不会随请求路径增长
+pub struct Metrics {
+    requests: Mutex<BTreeMap<RouteLabels, Histogram>>,  // 每组标签的耗时直方图
+    in_flight: AtomicI64,                               // 正在处理的请求数
+    sse_streams: AtomicI64,                             // 当前打开的SSE流
+    tls_handshake_failures: AtomicU64,                  // 失败的TLS握手数
+}
+
+impl Metrics {
+    /// 创建空的指标
+    pub fn new() -> Self {
+        Metrics {
+            requests: Mutex::new(BTreeMap::new()),
+            in_flight: AtomicI64::new(0),
+            sse_streams: AtomicI64::new(0),
+            tls_handshake_failures: AtomicU64::new(0),
+        }
+    }
+
+    /// 正在处理的请求数
+    pub fn in_flight(&self) -> i64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// 当前打开的SSE流数
+    pub fn sse_streams(&self) -> i64 {
+        self.sse_streams.load(Ordering::Relaxed)
+    }
+
+    /// 记录一个已完成的请求
+    ///
+    /// # 参数
+    /// * `req` - 请求，用于读取匹配的路由
+    /// * `status` - 响应状态码
+    /// * `seconds` - 处理耗时（秒）
+    fn observe(&self, req: &HttpRequest, status: u16, seconds: f64) {
+        let labels = RouteLabels {
+            route: req.match_pattern().unwrap_or_else(|| UNMATCHED_ROUTE.to_string()),
+            name: req.match_name().unwrap_or_default().to_string(),
+            status: status_class(status),
+        };
+        self.requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(labels)
+            .or_default()
+            .observe(seconds);
+    }
+
+    /// 以Prometheus文本格式导出所有指标
+    ///
+    /// # 参数
+    /// * `counters` - 命名计数器，每个计数器导出为一个带name标签的gauge
+    pub fn render(&self, counters: &CounterStore) -> String {
+        let mut out = String::new();
+
+        // 写入String不会失败，下面忽略writeln!的返回值
+        let requests = self.requests.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(out, "# HELP http_requests_total Total number of HTTP requests.");
+        let _ = writeln!(out, "# TYPE http_requests_total counter");
+        for (labels, histogram) in requests.iter() {
+            let _ = writeln!(out, "http_requests_total{{{}}} {}", labels.render(), histogram.count);
+        }
+
+        let _ = writeln!(out, "# HELP http_request_duration_seconds HTTP request latency in seconds.");
+        let _ = writeln!(out, "# TYPE http_request_duration_seconds histogram");
+        for (labels, histogram) in requests.iter() {
+            let labels = labels.render();
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(out, "http_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}");
+            }
+            let _ = writeln!(out, "http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "http_request_duration_seconds_sum{{{labels}}} {}", histogram.sum);
+            let _ = writeln!(out, "http_request_duration_seconds_count{{{labels}}} {}", histogram.count);
+        }
+        drop(requests);
+
+        let _ = writeln!(out, "# HELP http_requests_in_flight Number of HTTP requests currently being handled.");
+        let _ = writeln!(out, "# TYPE http_requests_in_flight gauge");
+        let _ = writeln!(out, "http_requests_in_flight {}", self.in_flight.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP sse_streams_active Number of open server-sent event streams.");
+        let _ = writeln!(out, "# TYPE sse_streams_active gauge");
+        let _ = writeln!(out, "sse_streams_active {}", self.sse_streams.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP tls_handshake_failures_total Number of TLS handshakes that did not complete.");
+        let _ = writeln!(out, "# TYPE tls_handshake_failures_total counter");
+        let _ = writeln!(
+            out,
+            "tls_handshake_failures_total {}",
+            self.tls_handshake_failures.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(out, "# HELP app_counter_value Current value of a named counter.");
+        let _ = writeln!(out, "# TYPE app_counter_value gauge");
+        for (name, value) in counters.all() {
+            let _ = writeln!(out, "app_counter_value{{name=\"{}\"}} {}", escape_label(&name), value);
+        }
+
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouteLabels {
+    /// 渲染为`route="...",name="...",status="..."`
+    fn render(&self) -> String {
+        format!(
+            "route=\"{}\",name=\"{}\",status=\"{}\"",
+            escape_label(&self.route),
+            escape_label(&self.name),
+            self.status
+        )
+    }
+}
+
+/// 状态码类别
+fn status_class(status: u16) -> &'static str {
+    match status {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// 转义标签值中的反斜杠、双引号和换行
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// 计数器加一，离开作用域时减一
+struct GaugeGuard<'a>(&'a AtomicI64);
+
+impl<'a> GaugeGuard<'a> {
+    fn new(gauge: &'a AtomicI64) -> Self {
+        gauge.fetch_add(1, Ordering::Relaxed);
+        GaugeGuard(gauge)
+    }
+}
+
+impl Drop for GaugeGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 请求指标中间件
+///
+/// 记录正在处理的请求数，以及按路由和状态码类别分组的请求数和耗时
+/// 应放在错误渲染中间件之外，才能记录到渲染后的最终状态码；
+/// 流式响应（如SSE）的耗时只计算到响应头返回为止
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `next` - 中间件链中的下一个服务
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    // 没有注册Metrics的应用直接跳过
+    let Some(metrics) = req.app_data::<web::Data<Metrics>>().cloned() else {
+        return next.call(req).await;
+    };
+
+    let started = Instant::now();
+    let result = {
+        let _in_flight = GaugeGuard::new(&metrics.in_flight);
+        next.call(req).await
+    };
+    let seconds = started.elapsed().as_secs_f64();
+
+    match &result {
+        Ok(res) => metrics.observe(res.request(), res.status().as_u16(), seconds),
+        Err(err) => {
+            // 错误没有携带请求，只能按未匹配的路由记录
+            let status = err.as_response_error().status_code().as_u16();
+            metrics
+                .requests
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .entry(RouteLabels {
+                    route: UNMATCHED_ROUTE.to_string(),
+                    name: String::new(),
+                    status: status_class(status),
+                })
+                .or_default()
+                .observe(seconds);
+        }
+    }
+    result
+}
+
+/// 统计SSE流，流被丢弃（客户端断开）时计数减一
+///
+/// # 参数
+/// * `metrics` - 服务器指标
+/// * `stream` - SSE事件流
+pub fn track_sse_stream<S: Stream>(metrics: Arc<Metrics>, stream: S) -> impl Stream<Item = S::Item> {
+    let guard = SseStreamGuard::new(metrics);
+    stream.map(move |item| {
+        let _ = &guard;
+        item
+    })
+}
+
+/// 持有期间计入一个打开的SSE流
+struct SseStreamGuard(Arc<Metrics>);
+
+impl SseStreamGuard {
+    fn new(metrics: Arc<Metrics>) -> Self {
+        metrics.sse_streams.fetch_add(1, Ordering::Relaxed);
+        SseStreamGuard(metrics)
+    }
+}
+
+impl Drop for SseStreamGuard {
+    fn drop(&mut self) {
+        self.0.sse_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 一次TLS握手的状态，保存在SSL对象的扩展数据中
+///
+/// 连接建立后被标记为完成；SSL对象释放时仍未完成，说明握手失败或超时
+struct HandshakeGuard {
+    metrics: Arc<Metrics>,  // 服务器指标
+    completed: AtomicBool,  // 握手是否已完成
+}
+
+impl Drop for HandshakeGuard {
+    fn drop(&mut self) {
+        if !self.completed.load(Ordering::Relaxed) {
+            self.metrics.tls_handshake_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 记录一次开始的TLS握手，由TLS配置的servername回调在收到ClientHello时调用
+///
+/// 握手状态保存在SSL对象上，`on_tls_connect`在连接建立时把它标记为完成
+/// 连ClientHello都无法解析的连接（例如向HTTPS端口发送明文HTTP）不计入；
+/// HelloRetryRequest会让回调在同一个握手中执行两次，已经记录过的不再重复记录
+///
+/// # 参数
+/// * `metrics` - 服务器指标
+/// * `ssl` - 正在握手的连接
+pub fn on_client_hello(metrics: &Arc<Metrics>, ssl: &mut SslRef) {
+    let Some(index) = handshake_index() else {
+        return;
+    };
+    if ssl.ex_data(index).is_none() {
+        ssl.set_ex_data(
+            index,
+            HandshakeGuard {
+                metrics: metrics.clone(),
+                completed: AtomicBool::new(false),
+            },
+        );
+    }
+}
+
+/// 握手状态的扩展数据索引，在整个进程中只分配一次
+///
+/// 分配失败时只记录日志，不再统计握手失败
+fn handshake_index() -> Option<Index<Ssl, HandshakeGuard>> {
+    *HANDSHAKE_INDEX.get_or_init(|| match Ssl::new_ex_index() {
+        Ok(index) => Some(index),
+        Err(err) => {
+            log::error!("无法分配SSL扩展数据索引，不再统计TLS握手失败: {}", err);
+            None
+        }
+    })
+}
+
+/// 连接建立回调，用于`HttpServer::on_connect`
+///
+/// TLS连接到达这里说明握手已完成
+///
+/// # 参数
+/// * `conn` - 底层连接
+/// * `_ext` - 连接级别的扩展数据
+pub fn on_tls_connect(conn: &dyn Any, _ext: &mut Extensions) {
+    let Some(stream) = conn.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    if let Some(index) = handshake_index()
+        && let Some(guard) = stream.ssl().ex_data(index)
+    {
+        guard.completed.store(true, Ordering::Relaxed);
+    }
+}
@@ -1,9 +1,31 @@
 // 标准库导入
-use std::sync::Mutex;  // 用于线程安全的共享状态
+use std::sync::LazyLock;  // 用于延迟初始化的正则表达式
 
 // 外部库导入
 use serde::{Deserialize, Serialize};  // 用于JSON序列化和反序列化
-use actix_web::{body::BoxBody, HttpResponse, Responder};  // 用于HTTP响应处理
+use actix_web::{body::BoxBody, HttpRequest, HttpResponse, Responder};  // 用于HTTP响应处理
+use regex::Regex;  // 用于校验规则中的正则表达式
+use validator::{Validate, ValidationError};  // 用于声明式的字段校验
+use utoipa::{IntoParams, ToSchema};  // 用于生成OpenAPI文档
+
+// 内部模块导入
+use crate::negotiation::Negotiated;  // 按Accept头选择响应格式
+
+/// 用户名规则：字母、数字、`_`、`.`、`-`
+static USERNAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9_.-]+$").expect("用户名正则表达式无效"));
+
+/// 语言标签规则，例如"en"、"zh-CN"
+static LANG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z]{2,3}(-[A-Za-z0-9]{1,8})*$").expect("语言标签正则表达式无效"));
+
+/// 手机号规则：可选的`+`号加7到15位数字
+static PHONE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\+?[0-9]{7,15}$").expect("手机号正则表达式无效"));
+
+/// 频道名和计数器名规则：字母、数字、`_`、`.`、`-`
+static NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9_.-]+$").expect("名称正则表达式无效"));
 
 /// 应用状态结构体
 ///
@@ -13,21 +35,16 @@ pub struct AppState {
     pub app_name: String,  // 应用程序名称
 }
 
-/// 带计数器的应用状态结构体
-///
-/// 用于在请求之间共享和修改计数器值
-/// 使用Mutex确保线程安全
-pub struct AppStateWithCounter {
-    pub counter: Mutex<i32>,  // 线程安全的整数计数器
-}
-
 /// 路径参数结构体
 ///
 /// 用于从URL路径中提取用户ID和名称
 /// 例如：/path2/123/alice 会提取 user_id=123, name="alice"
-#[derive(Deserialize)]  // 启用从路径参数到结构体的自动反序列化
+#[derive(Deserialize, Validate, IntoParams)]  // 启用从路径参数到结构体的自动反序列化和字段校验
+#[into_params(parameter_in = Path)]
 pub struct UserInfo {
-    pub user_id: u32,    // 用户ID，无符号32位整数
+    #[validate(range(min = 1))]
+    pub user_id: u32,    // 用户ID，无符号32位整数，从1开始
+    #[validate(length(min = 1, max = 64))]
     pub name: String,    // 用户名称
 }
 
@@ -35,37 +52,191 @@ pub struct UserInfo {
 ///
 /// 用于从URL查询字符串中提取搜索参数
 /// 例如：/query?q=rust&lang=en 会提取 q="rust", lang=Some("en")
-#[derive(Deserialize)]  // 启用从查询参数到结构体的自动反序列化
+#[derive(Deserialize, Validate, IntoParams)]  // 启用从查询参数到结构体的自动反序列化和字段校验
+#[into_params(parameter_in = Query)]
 pub struct SearchQuery {
+    #[validate(length(min = 1, max = 256))]
     pub q: String,                // 必需的查询字符串
-    pub lang: Option<String>,     // 可选的语言参数
+    #[validate(regex(path = *LANG_RE))]
+    pub lang: Option<String>,     // 可选的语言参数，必须是合法的语言标签
 }
 
 /// JSON输入结构体
 ///
 /// 用于从请求体中提取JSON数据
 /// 例如：{"username": "alice", "email": "alice@example.com"}
-#[derive(Deserialize)]  // 启用从JSON到结构体的自动反序列化
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]  // 启用JSON的自动序列化和反序列化以及字段校验
 pub struct UserIput {
+    #[validate(length(min = 3, max = 32), regex(path = *USERNAME_RE))]
     pub username: String,  // 用户名
+    #[validate(email)]
     pub email: String,     // 电子邮件
 }
 
+/// 用户结构体
+///
+/// 在UserIput的基础上增加了存储层分配的ID
+/// 序列化时UserIput的字段会被展开到同一层
+/// 例如：{"id": 1, "username": "alice", "email": "alice@example.com"}
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct User {
+    pub id: i64,            // 用户ID，由存储层分配
+    #[serde(flatten)]       // 将UserIput的字段展开到User中
+    pub input: UserIput,    // 用户名和电子邮件
+}
+
 /// 表单输入结构体
 ///
 /// 用于从表单提交中提取用户登录信息
 /// 例如：username=alice&password=secret
-#[derive(Deserialize)]  // 启用从表单数据到结构体的自动反序列化
+#[derive(Deserialize, Validate, ToSchema)]  // 启用从表单数据到结构体的自动反序列化和字段校验
 pub struct LoginInfo {
+    #[validate(length(min = 1, max = 64))]
+    pub username: String,  // 用户名
+    #[validate(length(min = 1, max = 128))]
+    pub password: String,  // 密码
+}
+
+/// 注册表单结构体
+///
+/// 用于从表单提交中提取注册信息
+/// 例如：username=alice&email=alice@example.com&password=secret
+#[derive(Deserialize, Validate, ToSchema)]  // 启用从表单数据到结构体的自动反序列化和字段校验
+pub struct RegisterInfo {
+    #[validate(length(min = 3, max = 32), regex(path = *USERNAME_RE))]
+    pub username: String,  // 用户名，规则与UserIput相同
+    #[validate(email)]
+    pub email: String,     // 电子邮件
+    #[validate(length(min = 8, max = 128))]
+    pub password: String,  // 明文密码，只用于计算哈希，不会被保存
+}
+
+/// 令牌请求结构体
+///
+/// 用于从JSON请求体中提取API客户端的登录凭据
+/// 例如：{"username": "alice", "password": "secret"}
+#[derive(Deserialize, Validate, ToSchema)]  // 启用从JSON到结构体的自动反序列化和字段校验
+pub struct TokenRequest {
+    #[validate(length(min = 1, max = 64))]
     pub username: String,  // 用户名
+    #[validate(length(min = 1, max = 128))]
     pub password: String,  // 密码
 }
 
+/// 刷新令牌请求结构体
+///
+/// 例如：{"refresh_token": "eyJ..."}
+#[derive(Deserialize, Validate, ToSchema)]  // 启用从JSON到结构体的自动反序列化和字段校验
+pub struct RefreshRequest {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,  // 之前签发的刷新令牌
+}
+
+/// SSE订阅查询参数结构体
+///
+/// 例如：/sse?channel=users
+#[derive(Deserialize, Validate, IntoParams)]  // 启用从查询参数到结构体的自动反序列化和字段校验
+#[into_params(parameter_in = Query)]
+pub struct SseQuery {
+    #[validate(length(min = 1, max = 64), regex(path = *NAME_RE))]
+    pub channel: Option<String>,  // 频道名，未指定时使用默认频道
+}
+
+/// 计数器路径参数结构体
+///
+/// 例如：/counters/page_views 会提取 name="page_views"
+#[derive(Deserialize, Validate, IntoParams)]  // 启用从路径参数到结构体的自动反序列化和字段校验
+#[into_params(parameter_in = Path)]
+pub struct CounterPath {
+    #[validate(length(min = 1, max = 64), regex(path = *NAME_RE))]
+    pub name: String,  // 计数器名
+}
+
+/// 计数器操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CounterAction {
+    Increment,  // 增加
+    Decrement,  // 减少
+    Reset,      // 重置为0
+}
+
+/// 计数器修改请求结构体
+///
+/// 例如：{"action": "increment", "amount": 5}
+#[derive(Deserialize, Validate, ToSchema)]  // 启用从JSON到结构体的自动反序列化和字段校验
+pub struct CounterUpdate {
+    pub action: CounterAction,  // 操作
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub amount: Option<i64>,    // 增减的数量，默认为1，reset时忽略
+}
+
+/// 计数器响应结构体
+///
+/// 例如：{"name": "page_views", "value": 42}
+#[derive(Serialize, ToSchema)]  // 启用结构体到JSON的自动序列化
+pub struct CounterValue {
+    pub name: String,  // 计数器名
+    pub value: i64,    // 当前值
+}
+
+/// 联系方式查询参数结构体
+///
+/// 用于演示条件必填：选择哪种联系方式，就必须提供对应的字段
+/// 例如：/form_test?contact_method=email&email=alice@example.com
+#[derive(Deserialize, Validate, IntoParams)]  // 启用从查询参数到结构体的自动反序列化和字段校验
+#[into_params(parameter_in = Query)]
+#[validate(schema(function = validate_contact_info, skip_on_field_errors = false))]
+pub struct ContactInfo {
+    #[validate(regex(path = *CONTACT_METHOD_RE))]
+    pub contact_method: String,   // 联系方式，"email"或"phone"
+    #[validate(email)]
+    pub email: Option<String>,    // 电子邮件，contact_method为email时必填
+    #[validate(regex(path = *PHONE_RE))]
+    pub phone: Option<String>,    // 手机号，contact_method为phone时必填
+}
+
+/// 联系方式规则
+static CONTACT_METHOD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(email|phone)$").expect("联系方式正则表达式无效"));
+
+/// ContactInfo的条件必填规则（required-if）
+///
+/// 结构体级别的错误通过`field`参数指明所属字段，
+/// 由`validation`模块归到对应字段下
+fn validate_contact_info(info: &ContactInfo) -> Result<(), ValidationError> {
+    let missing = match info.contact_method.as_str() {
+        "email" if info.email.is_none() => Some("email"),
+        "phone" if info.phone.is_none() => Some("phone"),
+        _ => None,
+    };
+    match missing {
+        Some(field) => {
+            let mut err = ValidationError::new("required_if");
+            err.add_param("field".into(), &field);
+            err.add_param("contact_method".into(), &info.contact_method);
+            Err(err)
+        }
+        None => Ok(()),
+    }
+}
+
+/// 令牌响应结构体
+///
+/// 签发令牌成功时返回给客户端
+#[derive(Serialize, ToSchema)]  // 启用结构体到JSON的自动序列化
+pub struct TokenPair {
+    pub access_token: String,      // 访问令牌，放在Authorization: Bearer请求头中
+    pub refresh_token: String,     // 刷新令牌，只能用于换取新的令牌
+    pub token_type: &'static str,  // 令牌类型，固定为"Bearer"
+    pub expires_in: u64,           // 访问令牌的有效期（秒）
+}
+
 /// 响应结构体
 ///
-/// 用于生成JSON响应
+/// 用于生成按内容协商选择格式的响应
 /// 包含用户名和年龄信息
-#[derive(Serialize)]  // 启用结构体到JSON的自动序列化
+#[derive(Serialize, ToSchema)]  // 启用结构体到JSON的自动序列化
 pub struct MyStruct {
     pub name: String,  // 用户名
     pub age: u32,      // 年龄，无符号32位整数
@@ -74,7 +245,7 @@ pub struct MyStruct {
 /// 为MyStruct实现Responder trait
 ///
 /// 使MyStruct可以直接作为处理函数的返回值
-/// 自动转换为HTTP响应
+/// 按请求的Accept头转换为JSON、MessagePack、CBOR或XML响应
 impl Responder for MyStruct {
     // 指定响应体的类型为 BoxBody，这是 actix-web 推荐的响应体类型
     type Body = BoxBody;
@@ -82,21 +253,13 @@ impl Responder for MyStruct {
     /// 实现 respond_to 方法，将 MyStruct 转换为 HTTP 响应
     ///
     /// # 参数
-    /// * `_req` - HTTP请求引用，本实现中未使用
+    /// * `req` - HTTP请求引用，用于内容协商
     ///
     /// # 返回值
-    /// * 返回包含JSON数据的HTTP 200 OK响应
-    fn respond_to(self, _req: &actix_web::HttpRequest) -> HttpResponse<Self::Body> {
-        // 将结构体序列化为 JSON 字符串
-        // unwrap()在序列化失败时会导致panic，生产环境应处理错误
-        let body = serde_json::to_string(&self).unwrap();
-
-        // 构建 HTTP 响应
-        // 1. 设置状态码为200 OK
-        // 2. 设置 Content-Type 为 application/json
-        // 3. 将序列化后的 JSON 作为响应体
-        HttpResponse::Ok()
-            .content_type("application/json")
-            .body(body)
+    /// * 返回协商出的格式的HTTP 200 OK响应
+    /// * 没有可接受的格式时返回406，序列化失败时返回500
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        // 交给Negotiated处理，序列化失败时返回错误响应而不是panic
+        Negotiated(self).respond_to(req)
     }
 }
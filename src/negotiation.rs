@@ -0,0 +1,237 @@
+// 标准库导入
+use std::future::Future;  // 提取器返回的Future
+use std::ops::Deref;      // 让包装类型可以像内部值一样使用
+use std::pin::Pin;        // 用于装箱的Future
+
+// 外部库导入
+use actix_web::body::BoxBody;                // 响应体类型
+use actix_web::dev::Payload;                 // 请求体载荷
+use actix_web::http::header::{self, Header, HeaderValue};  // Accept和Vary头
+use actix_web::mime::{self, Mime};           // 媒体类型
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError};  // 提取器和响应
+use serde::de::DeserializeOwned;             // 反序列化约束
+use serde::Serialize;                        // 序列化约束
+
+// 内部模块导入
+use crate::errors::NegotiationError;         // 内容协商错误
+
+/// 支持的数据格式
+///
+/// 顺序即通配类型（例如`*/*`）匹配时的优先顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,         // application/json
+    MessagePack,  // application/msgpack
+    Cbor,         // application/cbor
+    Xml,          // application/xml
+}
+
+impl Format {
+    /// 所有支持的格式
+    pub const ALL: [Format; 4] = [Format::Json, Format::MessagePack, Format::Cbor, Format::Xml];
+
+    /// 响应使用的媒体类型
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::MessagePack => "application/msgpack",
+            Format::Cbor => "application/cbor",
+            Format::Xml => "application/xml",
+        }
+    }
+
+    /// 所有支持的媒体类型，用于错误信息
+    fn supported() -> Vec<&'static str> {
+        Format::ALL.iter().map(|format| format.media_type()).collect()
+    }
+
+    /// 按媒体类型查找格式
+    ///
+    /// 除标准类型外，还接受`application/x-msgpack`、`text/xml`以及`+json`、`+cbor`、`+xml`后缀
+    ///
+    /// # 参数
+    /// * `mime` - 媒体类型，不能是通配类型
+    pub fn from_mime(mime: &Mime) -> Option<Self> {
+        let suffix = mime.suffix().map(|suffix| suffix.as_str());
+        match (mime.type_().as_str(), mime.subtype().as_str(), suffix) {
+            ("application", "json", _) | (_, _, Some("json")) => Some(Format::Json),
+            ("application", "msgpack" | "x-msgpack" | "vnd.msgpack", _) => Some(Format::MessagePack),
+            ("application", "cbor", _) | (_, _, Some("cbor")) => Some(Format::Cbor),
+            ("application" | "text", "xml", _) | (_, _, Some("xml")) => Some(Format::Xml),
+            _ => None,
+        }
+    }
+
+    /// 根据Accept头选择响应格式
+    ///
+    /// 按q值从高到低依次尝试，q=0的类型视为不可接受；
+    /// `*/*`和`application/*`选择第一个未被排除的格式，`text/*`对应XML；
+    /// 没有Accept头或无法解析时使用JSON
+    ///
+    /// # 参数
+    /// * `req` - HTTP请求
+    ///
+    /// # 返回值
+    /// * 成功时返回选中的格式
+    /// * 没有可接受的格式时返回NegotiationError::NotAcceptable
+    pub fn negotiate(req: &HttpRequest) -> Result<Self, NegotiationError> {
+        let Ok(accept) = header::Accept::parse(req) else {
+            return Ok(Format::Json);
+        };
+        if accept.is_empty() {
+            return Ok(Format::Json);
+        }
+
+        // 明确以q=0排除的格式，通配类型不会选中它们
+        let rejected: Vec<Format> = accept
+            .iter()
+            .filter(|item| item.quality == header::Quality::ZERO)
+            .filter_map(|item| Format::from_mime(&item.item))
+            .collect();
+        let acceptable = |format: &Format| !rejected.contains(format);
+
+        let mut items: Vec<_> = accept
+            .iter()
+            .filter(|item| item.quality > header::Quality::ZERO)
+            .collect();
+        // 稳定排序，q值相同时保持客户端给出的顺序
+        items.sort_by_key(|item| std::cmp::Reverse(item.quality));
+
+        for item in items {
+            let mime = &item.item;
+            let chosen = match (mime.type_(), mime.subtype()) {
+                (mime::STAR, _) => Format::ALL.into_iter().find(acceptable),
+                (mime::APPLICATION, mime::STAR) => {
+                    Format::ALL.into_iter().filter(|format| *format != Format::Xml).find(acceptable)
+                }
+                (mime::TEXT, mime::STAR) => Some(Format::Xml).filter(acceptable),
+                _ => Format::from_mime(mime).filter(acceptable),
+            };
+            if let Some(format) = chosen {
+                return Ok(format);
+            }
+        }
+        Err(NegotiationError::NotAcceptable { supported: Format::supported() })
+    }
+
+    /// 根据Content-Type确定请求体的格式
+    ///
+    /// # 参数
+    /// * `req` - HTTP请求
+    ///
+    /// # 返回值
+    /// * 成功时返回请求体的格式
+    /// * 缺少Content-Type或不是支持的格式时返回NegotiationError::UnsupportedMediaType
+    pub fn of_request(req: &HttpRequest) -> Result<Self, NegotiationError> {
+        let unsupported = |content_type: String| NegotiationError::UnsupportedMediaType {
+            content_type,
+            supported: Format::supported(),
+        };
+        match req.mime_type() {
+            Ok(Some(mime)) => Format::from_mime(&mime).ok_or_else(|| unsupported(mime.to_string())),
+            Ok(None) => Err(unsupported("未指定".to_string())),
+            Err(_) => Err(unsupported(req.content_type().to_string())),
+        }
+    }
+
+    /// 把数据序列化为该格式
+    ///
+    /// MessagePack按字段名编码结构体，便于其他语言的客户端解析；
+    /// XML以类型名作为根元素，因此顶层不能是列表或标量
+    ///
+    /// # 参数
+    /// * `value` - 要序列化的数据
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, NegotiationError> {
+        let result = match self {
+            Format::Json => serde_json::to_vec(value).map_err(|err| err.to_string()),
+            Format::MessagePack => rmp_serde::to_vec_named(value).map_err(|err| err.to_string()),
+            Format::Cbor => cbor4ii::serde::to_vec(Vec::new(), value).map_err(|err| err.to_string()),
+            Format::Xml => quick_xml::se::to_string(value)
+                .map(String::into_bytes)
+                .map_err(|err| err.to_string()),
+        };
+        result.map_err(|message| NegotiationError::Serialize { format: self.media_type(), message })
+    }
+
+    /// 按该格式解析请求体
+    ///
+    /// # 参数
+    /// * `body` - 请求体
+    pub fn decode<T: DeserializeOwned>(self, body: &[u8]) -> Result<T, NegotiationError> {
+        let result = match self {
+            Format::Json => serde_json::from_slice(body).map_err(|err| err.to_string()),
+            Format::MessagePack => rmp_serde::from_slice(body).map_err(|err| err.to_string()),
+            Format::Cbor => cbor4ii::serde::from_slice(body).map_err(|err| err.to_string()),
+            Format::Xml => std::str::from_utf8(body)
+                .map_err(|err| err.to_string())
+                .and_then(|text| quick_xml::de::from_str(text).map_err(|err| err.to_string())),
+        };
+        result.map_err(|message| NegotiationError::InvalidBody { format: self.media_type(), message })
+    }
+}
+
+/// 按内容协商选择格式的数据
+///
+/// 作为响应时按Accept头选择JSON、MessagePack、CBOR或XML，序列化失败时返回500而不是panic；
+/// 作为提取器时按Content-Type解析请求体，请求体大小受`web::PayloadConfig`限制。
+/// 用法与`web::Json`相同
+#[derive(Debug)]
+pub struct Negotiated<T>(pub T);
+
+impl<T> Negotiated<T> {
+    /// 取出内部值
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Negotiated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// 为Negotiated实现Responder trait
+///
+/// 响应总是带有`Vary: Accept`，以便缓存按Accept区分不同格式的响应
+impl<T: Serialize> Responder for Negotiated<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        let encoded = Format::negotiate(req).and_then(|format| Ok((format, format.encode(&self.0)?)));
+        let mut res = match encoded {
+            Ok((format, body)) => HttpResponse::Ok().content_type(format.media_type()).body(body),
+            Err(err) => {
+                if let NegotiationError::Serialize { .. } = err {
+                    log::error!("{}", err);
+                }
+                err.error_response()
+            }
+        };
+        res.headers_mut().insert(header::VARY, HeaderValue::from_static("accept"));
+        res
+    }
+}
+
+/// 为Negotiated实现FromRequest trait
+///
+/// 先检查Content-Type，不支持的格式不读取请求体
+impl<T> FromRequest for Negotiated<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let format = Format::of_request(req);
+        let body = web::Bytes::from_request(req, payload);
+        Box::pin(async move {
+            let format = format?;
+            let body = body.await?;
+            Ok(Negotiated(format.decode(&body)?))
+        })
+    }
+}
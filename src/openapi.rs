@@ -0,0 +1,191 @@
+// 标准库导入
+use std::sync::LazyLock;                 // 文档只生成一次
+
+// 外部库导入
+use serde::Deserialize;                  // 文档页面的查询参数
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};  // 认证方式
+use utoipa::{Modify, OpenApi};           // 文档的生成和修改
+
+// 内部模块导入
+use crate::errors::ProblemDetails;       // RFC 7807格式的错误响应
+use crate::handlers;                     // 带文档注解的处理函数
+
+/// 本服务的OpenAPI文档
+///
+/// 路径来自处理函数上的`#[utoipa::path]`注解，数据模型由注解引用的类型自动收集；
+/// 新增路由时必须同时加到`paths`中，`tests/openapi.rs`会检查没有遗漏
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "web_learning", description = "Actix-Web学习示例服务"),
+    paths(
+        handlers::first_hello,
+        handlers::echo,
+        handlers::manual_hello,
+        handlers::index,
+        handlers::index2,
+        handlers::index3,
+        handlers::users_site,
+        handlers::www_site,
+        handlers::path_test,
+        handlers::path_test_by_struct,
+        handlers::query_test,
+        handlers::json_test,
+        handlers::register,
+        handlers::login,
+        handlers::issue_token,
+        handlers::refresh_token,
+        handlers::logout,
+        handlers::my_struct_test,
+        handlers::stream_handler,
+        handlers::ws_handler,
+        handlers::process_data,
+        handlers::index_by_my_error,
+        handlers::index_by_my_new_error_internal,
+        handlers::index_by_my_new_error_timeout,
+        handlers::index_by_my_new_error_bad_client_data,
+        handlers::index_by_simple_error,
+        handlers::process_form,
+        handlers::index_by_user_facing_error,
+        handlers::index_resource,
+        handlers::list_users,
+        handlers::create_user,
+        handlers::get_user,
+        handlers::updata_user,
+        handlers::delete_user,
+        handlers::get_counter,
+        handlers::update_counter,
+        handlers::metrics_handler,
+        handlers::healthz,
+        handlers::readyz,
+        handlers::client_identity,
+        handlers::openapi_json,
+        handlers::api_docs,
+    ),
+    components(schemas(ProblemDetails)),
+    modifiers(&SecuritySchemes, &SharedHandlers),
+    tags(
+        (name = "示例", description = "路由、提取器和响应的基本用法"),
+        (name = "认证", description = "注册、会话登录和JWT令牌"),
+        (name = "用户", description = "用户资源"),
+        (name = "计数器", description = "可持久化的命名计数器"),
+        (name = "事件", description = "SSE和WebSocket推送"),
+        (name = "错误演示", description = "各种错误类型的渲染"),
+        (name = "运维", description = "健康检查、指标和mTLS身份"),
+        (name = "文档", description = "本文档"),
+    )
+)]
+pub struct ApiDoc;
+
+/// 注册文档中引用的认证方式
+///
+/// `session`使用默认的Cookie名称，修改了`auth.cookie_name`时以配置为准
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("web_session"))),
+        );
+    }
+}
+
+/// 同一个处理函数注册在多个路径上：(已注解的路径, 其他路径)
+///
+/// `#[utoipa::path]`只能描述一个路径，其他路径复制已注解路径的文档
+const SHARED_PATHS: &[(&str, &str)] = &[("/app/index", "/app2/index")];
+
+/// 为共用处理函数的路径补上文档
+struct SharedHandlers;
+
+impl Modify for SharedHandlers {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for (documented, alias) in SHARED_PATHS {
+            if let Some(mut item) = openapi.paths.paths.get(*documented).cloned() {
+                // operationId必须唯一，加上路径区分
+                let suffix = alias.trim_matches('/').replace('/', "_");
+                let operations = [&mut item.get, &mut item.post, &mut item.put, &mut item.delete, &mut item.patch];
+                for operation in operations.into_iter().flatten() {
+                    if let Some(id) = &mut operation.operation_id {
+                        *id = format!("{id}_{suffix}");
+                    }
+                }
+                openapi.paths.paths.insert(alias.to_string(), item);
+            }
+        }
+    }
+}
+
+/// 生成好的文档，第一次请求时生成
+static SPEC: LazyLock<utoipa::openapi::OpenApi> = LazyLock::new(ApiDoc::openapi);
+
+/// 获取OpenAPI文档
+pub fn spec() -> &'static utoipa::openapi::OpenApi {
+    &SPEC
+}
+
+/// 文档页面使用的界面
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocsUi {
+    #[default]
+    Swagger,  // Swagger UI，可以直接发送请求
+    Redoc,    // Redoc，适合阅读
+}
+
+/// 文档页面的查询参数
+///
+/// 例如：/docs?ui=redoc
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DocsQuery {
+    pub ui: DocsUi,  // 文档界面，默认为Swagger UI
+}
+
+/// Swagger UI页面，脚本和样式从CDN加载
+const SWAGGER_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="utf-8">
+  <title>web_learning API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Redoc页面，脚本从CDN加载
+const REDOC_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="utf-8">
+  <title>web_learning API</title>
+</head>
+<body>
+  <redoc spec-url="/openapi.json"></redoc>
+  <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+</body>
+</html>
+"##;
+
+/// 文档页面的HTML
+///
+/// # 参数
+/// * `ui` - 文档界面
+pub fn docs_page(ui: DocsUi) -> &'static str {
+    match ui {
+        DocsUi::Swagger => SWAGGER_PAGE,
+        DocsUi::Redoc => REDOC_PAGE,
+    }
+}
@@ -0,0 +1,394 @@
+// 标准库导入
+use std::collections::{HashMap, HashSet};  // 令牌桶表和已登记的API Key
+use std::sync::{Arc, Mutex};               // 令牌桶表的互斥锁
+use std::time::{Duration, Instant};        // 令牌补充和过期
+
+// 外部库导入
+use actix_session::SessionExt;             // 读取会话中的登录用户
+use actix_web::body::{BoxBody, MessageBody};  // 响应体
+use actix_web::dev::{ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};  // 限流响应头
+use actix_web::middleware::Next;           // 中间件链中的下一个服务
+use actix_web::{web, HttpMessage};         // 共享状态和请求扩展
+
+// 内部模块导入
+use crate::auth::SESSION_USER_KEY;         // 会话中保存用户ID的键
+use crate::errors::RateLimitError;         // 限流错误
+use crate::jwt::Claims;                    // Bearer令牌的载荷
+use crate::settings::RateLimitSettings;    // 限流配置
+
+/// 配额
+#[derive(Debug, Clone, Copy)]
+struct Quota {
+    per_second: f64,  // 每秒补充的令牌数
+    burst: f64,       // 令牌桶容量
+}
+
+impl Quota {
+    fn new(requests_per_minute: u32, burst: u32) -> Self {
+        Quota {
+            per_second: f64::from(requests_per_minute) / 60.0,
+            burst: f64::from(burst),
+        }
+    }
+
+    /// 补充`tokens`个令牌需要的时间
+    fn time_for(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64((tokens / self.per_second).max(0.0))
+    }
+}
+
+/// 令牌桶
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,       // 上次更新时的令牌数
+    updated: Instant,  // 上次更新的时间
+    full_at: Instant,  // 令牌补满的时间，之后这个桶与新建的桶没有区别，可以清理
+}
+
+/// 一次限流判断的结果
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub allowed: bool,     // 是否放行
+    pub limit: u32,        // 令牌桶容量
+    pub remaining: u32,    // 剩余令牌数
+    pub reset: u64,        // 令牌补满还需要的时间（秒）
+    pub retry_after: u64,  // 被拒绝时，下一个令牌可用还需要的时间（秒）
+    pub window: u64,       // 从空桶补满需要的时间（秒），用于RateLimit-Policy
+}
+
+impl Decision {
+    /// 写入RateLimit-Limit、RateLimit-Remaining、RateLimit-Reset和RateLimit-Policy响应头
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let pairs = [
+            ("ratelimit-limit", self.limit.to_string()),
+            ("ratelimit-remaining", self.remaining.to_string()),
+            ("ratelimit-reset", self.reset.to_string()),
+            ("ratelimit-policy", format!("{};w={}", self.limit, self.window)),
+        ];
+        for (name, value) in pairs {
+            // 这些值都是数字和分号，一定是合法的请求头值
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        }
+    }
+}
+
+/// 按客户端限流
+///
+/// 每个客户端在每条单独配置了配额的路由上各有一个令牌桶，其余路由共用默认配额的令牌桶
+/// 令牌桶只保存在内存中，补满后由`run_sweeper`清理；多实例部署时各实例分别计数
+pub struct RateLimiter {
+    enabled: bool,                     // 是否启用
+    default: Quota,                    // 默认配额
+    routes: HashMap<String, Quota>,    // 路由模式到配额
+    api_key_header: HeaderName,        // 携带API Key的请求头
+    api_keys: HashSet<String>,         // 已登记的API Key
+    sweep_interval: Duration,          // 清理间隔
+    buckets: Mutex<HashMap<(Option<String>, String), Bucket>>,  // (路由, 客户端)到令牌桶，默认配额的路由为None
+}
+
+impl RateLimiter {
+    /// 根据配置创建限流器
+    ///
+    /// 配置已经过`Settings::validate`校验，请求头名称一定合法
+    pub fn from_settings(settings: &RateLimitSettings) -> Self {
+        RateLimiter {
+            enabled: settings.enabled,
+            default: Quota::new(settings.requests_per_minute, settings.burst),
+            routes: settings
+                .routes
+                .iter()
+                .map(|quota| (quota.route.clone(), Quota::new(quota.requests_per_minute, quota.burst)))
+                .collect(),
+            api_key_header: HeaderName::try_from(settings.api_key_header.as_str())
+                .unwrap_or_else(|_| HeaderName::from_static("x-api-key")),
+            api_keys: settings.api_keys.iter().cloned().collect(),
+            sweep_interval: Duration::from_secs(settings.sweep_interval_secs),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从客户端的令牌桶中取一个令牌
+    ///
+    /// # 参数
+    /// * `route` - 匹配的路由模式，没有匹配时为None
+    /// * `client` - 客户端标识
+    pub fn check(&self, route: Option<&str>, client: &str) -> Decision {
+        self.check_at(route, client, Instant::now())
+    }
+
+    /// 在给定时刻从客户端的令牌桶中取一个令牌
+    fn check_at(&self, route: Option<&str>, client: &str, now: Instant) -> Decision {
+        let (scope, quota) = match route.and_then(|route| self.routes.get_key_value(route)) {
+            Some((route, quota)) => (Some(route.clone()), *quota),
+            None => (None, self.default),
+        };
+
+        let mut buckets = self.buckets();
+        let bucket = buckets.entry((scope, client.to_string())).or_insert(Bucket {
+            tokens: quota.burst,
+            updated: now,
+            full_at: now,
+        });
+
+        // 按经过的时间补充令牌
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * quota.per_second).min(quota.burst);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let missing = quota.burst - bucket.tokens;
+        bucket.full_at = now + quota.time_for(missing);
+
+        Decision {
+            allowed,
+            limit: quota.burst as u32,
+            remaining: bucket.tokens.floor() as u32,
+            reset: quota.time_for(missing).as_secs_f64().ceil() as u64,
+            retry_after: quota.time_for(1.0 - bucket.tokens).as_secs_f64().ceil().max(1.0) as u64,
+            window: quota.time_for(quota.burst).as_secs_f64().ceil() as u64,
+        }
+    }
+
+    /// 识别客户端
+    ///
+    /// 已登记的API Key > Bearer令牌或会话中的登录用户 > 对端IP
+    /// 不信任X-Forwarded-For等可以伪造的请求头
+    fn client_of(&self, req: &ServiceRequest) -> String {
+        if let Some(key) = req.headers().get(&self.api_key_header).and_then(|value| value.to_str().ok())
+            && self.api_keys.contains(key)
+        {
+            return format!("key:{key}");
+        }
+        if let Some(claims) = req.extensions().get::<Claims>() {
+            return format!("user:{}", claims.sub);
+        }
+        if let Ok(Some(id)) = req.get_session().get::<i64>(SESSION_USER_KEY) {
+            return format!("user:{id}");
+        }
+        ip_of(req)
+    }
+
+    /// 定期清理已经补满的令牌桶，直到进程退出
+    pub async fn run_sweeper(self: Arc<Self>) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(self.sweep_interval);
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let mut buckets = self.buckets();
+            let before = buckets.len();
+            buckets.retain(|_, bucket| bucket.full_at > now);
+            if before != buckets.len() {
+                log::debug!("清理了{}个令牌桶，剩余{}个", before - buckets.len(), buckets.len());
+            }
+        }
+    }
+
+    /// 获取令牌桶表的锁
+    ///
+    /// 锁内只做简单的表操作，不会panic，锁中毒时直接沿用内部数据
+    fn buckets(&self) -> std::sync::MutexGuard<'_, HashMap<(Option<String>, String), Bucket>> {
+        self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 按对端IP识别客户端
+fn ip_of(req: &ServiceRequest) -> String {
+    match req.peer_addr() {
+        Some(addr) => format!("ip:{}", addr.ip()),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+/// 日志中使用的客户端标识
+///
+/// API Key只显示SHA-256指纹的前8位十六进制字符，其余标识原样输出
+fn log_label(client: &str) -> String {
+    match client.strip_prefix("key:") {
+        Some(key) => {
+            let digest = openssl::sha::sha256(key.as_bytes());
+            let fingerprint: String = digest[..4].iter().map(|byte| format!("{byte:02x}")).collect();
+            format!("key:{fingerprint}")
+        }
+        None => client.to_string(),
+    }
+}
+
+/// 超出配额时的429响应，带Retry-After
+fn throttled(req: ServiceRequest, route: Option<&str>, client: &str, retry_after: u64) -> ServiceResponse<BoxBody> {
+    log::info!("客户端{}在路由{:?}上超出配额", log_label(client), route);
+    req.error_response(RateLimitError { retry_after })
+}
+
+/// 拒绝一个认证失败的请求，并按对端IP计入限流
+///
+/// Bearer认证失败的请求不会到达限流中间件，由认证中间件调用这里，
+/// 猜测令牌同样会消耗对端IP的令牌桶，超出配额后返回429而不是401
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `err` - 认证错误，未超出配额时作为响应
+pub fn reject_unauthenticated(
+    req: ServiceRequest,
+    err: impl Into<actix_web::Error>,
+) -> ServiceResponse<BoxBody> {
+    let limiter = req
+        .app_data::<web::Data<RateLimiter>>()
+        .filter(|limiter| limiter.enabled)
+        .cloned();
+    let Some(limiter) = limiter else {
+        return req.error_response(err);
+    };
+
+    let route = req.match_pattern();
+    let client = ip_of(&req);
+    let decision = limiter.check(route.as_deref(), &client);
+    let mut res = if decision.allowed {
+        req.error_response(err)
+    } else {
+        throttled(req, route.as_deref(), &client, decision.retry_after)
+    };
+    decision.apply(res.headers_mut());
+    res
+}
+
+/// 限流中间件
+///
+/// 按客户端和路由取令牌，超出配额时返回429和Retry-After；
+/// 无论是否放行都会带上RateLimit-*响应头
+/// 必须位于Bearer认证和会话中间件之内，才能按登录用户识别客户端；
+/// Bearer认证失败的请求由认证中间件通过`reject_unauthenticated`按对端IP计数
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `next` - 中间件链中的下一个服务
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let Some(limiter) = req.app_data::<web::Data<RateLimiter>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    if !limiter.enabled {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    // 路由匹配之前match_pattern按路径查找资源表，不需要等待路由
+    let route = req.match_pattern();
+    let client = limiter.client_of(&req);
+    let decision = limiter.check(route.as_deref(), &client);
+
+    let mut res = if decision.allowed {
+        next.call(req).await?.map_into_boxed_body()
+    } else {
+        throttled(req, route.as_deref(), &client, decision.retry_after)
+    };
+    decision.apply(res.headers_mut());
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::RouteQuota;
+
+    /// 默认配额每秒补充1个令牌、容量为3，/login每10秒补充1个令牌、容量为1
+    fn limiter() -> RateLimiter {
+        RateLimiter::from_settings(&RateLimitSettings {
+            requests_per_minute: 60,
+            burst: 3,
+            routes: vec![RouteQuota { route: "/login".to_string(), requests_per_minute: 6, burst: 1 }],
+            ..RateLimitSettings::default()
+        })
+    }
+
+    #[test]
+    fn burst_is_exhausted_then_rejected() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        for remaining in [2, 1, 0] {
+            let decision = limiter.check_at(None, "ip:a", now);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+        let decision = limiter.check_at(None, "ip:a", now);
+        assert!(!decision.allowed);
+        assert_eq!((decision.limit, decision.remaining, decision.reset, decision.retry_after), (3, 0, 3, 1));
+
+        // 其他客户端有自己的令牌桶
+        assert!(limiter.check_at(None, "ip:b", now).allowed);
+    }
+
+    #[test]
+    fn tokens_refill_partially_over_time() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            limiter.check_at(None, "ip:a", now);
+        }
+
+        // 0.5秒只补充半个令牌，仍然不够
+        let decision = limiter.check_at(None, "ip:a", now + Duration::from_millis(500));
+        assert!(!decision.allowed);
+        assert_eq!((decision.reset, decision.retry_after), (3, 1));
+
+        // 再过1秒共补充1.5个令牌，取走1个后剩0.5个
+        let decision = limiter.check_at(None, "ip:a", now + Duration::from_millis(1500));
+        assert!(decision.allowed);
+        assert_eq!((decision.remaining, decision.reset), (0, 3));
+
+        // 补充不会超过容量
+        let decision = limiter.check_at(None, "ip:a", now + Duration::from_secs(60));
+        assert_eq!(decision.remaining, 2);
+    }
+
+    #[test]
+    fn routes_with_quotas_use_their_own_bucket() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        let decision = limiter.check_at(Some("/login"), "ip:a", now);
+        assert!(decision.allowed);
+        assert_eq!((decision.limit, decision.window), (1, 10));
+        let decision = limiter.check_at(Some("/login"), "ip:a", now);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, 10);
+
+        // 默认配额的路由共用一个不受/login影响的令牌桶
+        let decision = limiter.check_at(Some("/users"), "ip:a", now);
+        assert_eq!((decision.limit, decision.remaining), (3, 2));
+        let decision = limiter.check_at(None, "ip:a", now);
+        assert_eq!(decision.remaining, 1);
+    }
+
+    #[test]
+    fn decision_writes_ratelimit_headers() {
+        let decision = Decision { allowed: true, limit: 5, remaining: 4, reset: 12, retry_after: 1, window: 30 };
+        let mut headers = HeaderMap::new();
+        decision.apply(&mut headers);
+
+        let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+        assert_eq!(header("ratelimit-limit"), "5");
+        assert_eq!(header("ratelimit-remaining"), "4");
+        assert_eq!(header("ratelimit-reset"), "12");
+        assert_eq!(header("ratelimit-policy"), "5;w=30");
+    }
+
+    #[test]
+    fn api_keys_are_fingerprinted_in_logs() {
+        let label = log_label("key:super-secret-api-key");
+        assert!(label.starts_with("key:") && label.len() == 12, "{label}");
+        assert!(!label.contains("secret"));
+        assert_eq!(log_label("ip:127.0.0.1"), "ip:127.0.0.1");
+    }
+}
@@ -0,0 +1,411 @@
+// 标准库导入
+use std::collections::BTreeMap;  // 用于按用户名有序存储用户
+use std::path::Path;             // 用于表示SQLite文件路径
+use std::sync::{Arc, Mutex};     // 用于线程安全的共享状态
+
+// 外部库导入
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};  // SQLite访问
+
+// 内部模块导入
+use crate::errors::RepositoryError;                     // 存储错误类型
+use crate::models::{User, UserIput};                    // 用户数据模型
+use crate::settings::{StorageBackend, StorageSettings}; // 存储配置
+
+/// 用户存储接口
+///
+/// 所有方法都是同步的，处理函数应通过`web::block`在线程池中调用，
+/// 避免阻塞异步工作线程
+pub trait UserRepository: Send + Sync {
+    /// 按用户名排序列出所有用户
+    fn list(&self) -> Result<Vec<User>, RepositoryError>;
+
+    /// 按用户名查找用户，不存在时返回NotFound
+    fn get(&self, username: &str) -> Result<User, RepositoryError>;
+
+    /// 按ID查找用户，不存在时返回NotFound
+    ///
+    /// ID在改名后保持不变，删除后也不会分配给新用户，会话和令牌据此识别用户
+    fn get_by_id(&self, id: i64) -> Result<User, RepositoryError>;
+
+    /// 创建用户，用户名已存在时返回Conflict
+    fn create(&self, input: UserIput) -> Result<User, RepositoryError>;
+
+    /// 创建带密码的用户，`password_hash`必须是已经哈希过的密码
+    ///
+    /// 用户名已存在时返回Conflict
+    fn register(&self, input: UserIput, password_hash: String) -> Result<User, RepositoryError>;
+
+    /// 读取用户的密码哈希
+    ///
+    /// 用户不存在时返回NotFound，用户未设置密码时返回None
+    fn password_hash(&self, username: &str) -> Result<Option<String>, RepositoryError>;
+
+    /// 更新用户，允许修改用户名
+    ///
+    /// 用户不存在时返回NotFound，新用户名被占用时返回Conflict
+    fn update(&self, username: &str, input: UserIput) -> Result<User, RepositoryError>;
+
+    /// 删除用户，不存在时返回NotFound
+    fn delete(&self, username: &str) -> Result<(), RepositoryError>;
+
+    /// 检查存储是否可用，用于就绪检查
+    ///
+    /// 默认实现总是成功，适用于不依赖外部资源的存储
+    fn ping(&self) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+}
+
+/// 根据配置创建用户存储
+///
+/// # 参数
+/// * `settings` - 存储配置
+///
+/// # 返回值
+/// * 成功时返回可在工作线程之间共享的存储实例
+/// * SQLite文件无法打开或初始化时返回RepositoryError
+pub fn from_settings(settings: &StorageSettings) -> Result<Arc<dyn UserRepository>, RepositoryError> {
+    match settings.backend {
+        StorageBackend::Memory => Ok(Arc::new(InMemoryUserRepository::default())),
+        StorageBackend::Sqlite => Ok(Arc::new(SqliteUserRepository::open(&settings.sqlite_path)?)),
+    }
+}
+
+/// 内存用户存储
+///
+/// 数据保存在进程内存中，重启后丢失
+/// 适合开发和测试环境
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+    inner: Mutex<InMemoryState>,  // 由互斥锁保护的用户表
+}
+
+/// 内存存储的内部状态
+#[derive(Default)]
+struct InMemoryState {
+    last_id: i64,                          // 最后分配的用户ID
+    users: BTreeMap<String, StoredUser>,   // 以用户名为键的用户表
+}
+
+/// 内存中保存的一条用户记录
+struct StoredUser {
+    user: User,                     // 用户信息
+    password_hash: Option<String>,  // 密码哈希，通过/users创建的用户没有密码
+}
+
+impl InMemoryUserRepository {
+    /// 获取内部状态的锁
+    ///
+    /// 持锁线程panic导致锁中毒时返回存储错误
+    fn state(&self) -> Result<std::sync::MutexGuard<'_, InMemoryState>, RepositoryError> {
+        self.inner
+            .lock()
+            .map_err(|_| RepositoryError::Storage("内存存储的锁已中毒".to_string()))
+    }
+
+    /// 插入一条新的用户记录
+    fn insert(&self, input: UserIput, password_hash: Option<String>) -> Result<User, RepositoryError> {
+        let mut state = self.state()?;
+        if state.users.contains_key(&input.username) {
+            return Err(RepositoryError::Conflict(input.username));
+        }
+
+        state.last_id += 1;
+        let user = User { id: state.last_id, input };
+        state.users.insert(
+            user.input.username.clone(),
+            StoredUser { user: user.clone(), password_hash },
+        );
+        Ok(user)
+    }
+}
+
+impl UserRepository for InMemoryUserRepository {
+    fn list(&self) -> Result<Vec<User>, RepositoryError> {
+        Ok(self.state()?.users.values().map(|stored| stored.user.clone()).collect())
+    }
+
+    fn get(&self, username: &str) -> Result<User, RepositoryError> {
+        self.state()?
+            .users
+            .get(username)
+            .map(|stored| stored.user.clone())
+            .ok_or_else(|| RepositoryError::NotFound(username.to_string()))
+    }
+
+    fn get_by_id(&self, id: i64) -> Result<User, RepositoryError> {
+        self.state()?
+            .users
+            .values()
+            .find(|stored| stored.user.id == id)
+            .map(|stored| stored.user.clone())
+            .ok_or_else(|| RepositoryError::NotFound(id.to_string()))
+    }
+
+    fn create(&self, input: UserIput) -> Result<User, RepositoryError> {
+        self.insert(input, None)
+    }
+
+    fn register(&self, input: UserIput, password_hash: String) -> Result<User, RepositoryError> {
+        self.insert(input, Some(password_hash))
+    }
+
+    fn password_hash(&self, username: &str) -> Result<Option<String>, RepositoryError> {
+        self.state()?
+            .users
+            .get(username)
+            .map(|stored| stored.password_hash.clone())
+            .ok_or_else(|| RepositoryError::NotFound(username.to_string()))
+    }
+
+    fn update(&self, username: &str, input: UserIput) -> Result<User, RepositoryError> {
+        let mut state = self.state()?;
+        if !state.users.contains_key(username) {
+            return Err(RepositoryError::NotFound(username.to_string()));
+        }
+        // 修改用户名时，新用户名不能被其他用户占用
+        if input.username != username && state.users.contains_key(&input.username) {
+            return Err(RepositoryError::Conflict(input.username));
+        }
+
+        // 保留原有的ID和密码哈希
+        let mut stored = state.users.remove(username).expect("用户已确认存在");
+        stored.user.input = input;
+        let user = stored.user.clone();
+        state.users.insert(user.input.username.clone(), stored);
+        Ok(user)
+    }
+
+    fn delete(&self, username: &str) -> Result<(), RepositoryError> {
+        self.state()?
+            .users
+            .remove(username)
+            .map(|_| ())
+            .ok_or_else(|| RepositoryError::NotFound(username.to_string()))
+    }
+}
+
+/// SQLite用户存储
+///
+/// 数据保存在本地SQLite文件中，重启后仍然存在
+/// 所有工作线程共用一个连接，由互斥锁串行化访问
+pub struct SqliteUserRepository {
+    conn: Mutex<Connection>,  // SQLite连接
+}
+
+impl SqliteUserRepository {
+    /// 打开（必要时创建）SQLite数据库文件并初始化表结构
+    ///
+    /// # 参数
+    /// * `path` - 数据库文件路径
+    pub fn open(path: &Path) -> Result<Self, RepositoryError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// 打开内存中的SQLite数据库，主要用于测试
+    pub fn open_in_memory() -> Result<Self, RepositoryError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    /// 初始化表结构
+    fn init(conn: Connection) -> Result<Self, RepositoryError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS users (
+                id            INTEGER PRIMARY KEY AUTOINCREMENT,
+                username      TEXT NOT NULL UNIQUE,
+                email         TEXT NOT NULL,
+                password_hash TEXT
+            )",
+        )?;
+
+        // 旧版本创建的数据库没有password_hash列，需要补上
+        let has_password_hash: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('users') WHERE name = 'password_hash'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_password_hash {
+            conn.execute_batch("ALTER TABLE users ADD COLUMN password_hash TEXT")?;
+        }
+
+        Ok(SqliteUserRepository { conn: Mutex::new(conn) })
+    }
+
+    /// 获取连接的锁
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, RepositoryError> {
+        self.conn
+            .lock()
+            .map_err(|_| RepositoryError::Storage("SQLite连接的锁已中毒".to_string()))
+    }
+
+    /// 插入一条新的用户记录
+    fn insert(&self, input: UserIput, password_hash: Option<String>) -> Result<User, RepositoryError> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO users (username, email, password_hash) VALUES (?1, ?2, ?3)",
+            params![input.username, input.email, password_hash],
+        )
+        .map_err(|err| map_write_error(err, &input.username))?;
+
+        Ok(User { id: conn.last_insert_rowid(), input })
+    }
+}
+
+/// 将一行查询结果转换为User
+fn row_to_user(row: &rusqlite::Row<'_>) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        input: UserIput {
+            username: row.get(1)?,
+            email: row.get(2)?,
+        },
+    })
+}
+
+/// 将唯一约束冲突转换为Conflict，其余错误转换为Storage
+fn map_write_error(err: rusqlite::Error, username: &str) -> RepositoryError {
+    match &err {
+        rusqlite::Error::SqliteFailure(failure, _)
+            if failure.code == ErrorCode::ConstraintViolation =>
+        {
+            RepositoryError::Conflict(username.to_string())
+        }
+        _ => err.into(),
+    }
+}
+
+impl UserRepository for SqliteUserRepository {
+    fn ping(&self) -> Result<(), RepositoryError> {
+        self.conn()?.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<User>, RepositoryError> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT id, username, email FROM users ORDER BY username")?;
+        let users = stmt
+            .query_map([], row_to_user)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(users)
+    }
+
+    fn get(&self, username: &str) -> Result<User, RepositoryError> {
+        self.conn()?
+            .query_row(
+                "SELECT id, username, email FROM users WHERE username = ?1",
+                params![username],
+                row_to_user,
+            )
+            .optional()?
+            .ok_or_else(|| RepositoryError::NotFound(username.to_string()))
+    }
+
+    fn get_by_id(&self, id: i64) -> Result<User, RepositoryError> {
+        self.conn()?
+            .query_row(
+                "SELECT id, username, email FROM users WHERE id = ?1",
+                params![id],
+                row_to_user,
+            )
+            .optional()?
+            .ok_or_else(|| RepositoryError::NotFound(id.to_string()))
+    }
+
+    fn create(&self, input: UserIput) -> Result<User, RepositoryError> {
+        self.insert(input, None)
+    }
+
+    fn register(&self, input: UserIput, password_hash: String) -> Result<User, RepositoryError> {
+        self.insert(input, Some(password_hash))
+    }
+
+    fn password_hash(&self, username: &str) -> Result<Option<String>, RepositoryError> {
+        self.conn()?
+            .query_row(
+                "SELECT password_hash FROM users WHERE username = ?1",
+                params![username],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| RepositoryError::NotFound(username.to_string()))
+    }
+
+    fn update(&self, username: &str, input: UserIput) -> Result<User, RepositoryError> {
+        self.conn()?
+            .query_row(
+                "UPDATE users SET username = ?1, email = ?2 WHERE username = ?3 RETURNING id",
+                params![input.username, input.email, username],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| map_write_error(err, &input.username))?
+            .map(|id| User { id, input })
+            .ok_or_else(|| RepositoryError::NotFound(username.to_string()))
+    }
+
+    fn delete(&self, username: &str) -> Result<(), RepositoryError> {
+        let deleted = self
+            .conn()?
+            .execute("DELETE FROM users WHERE username = ?1", params![username])?;
+        if deleted == 0 {
+            return Err(RepositoryError::NotFound(username.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(username: &str) -> UserIput {
+        UserIput { username: username.to_string(), email: format!("{username}@example.com") }
+    }
+
+    #[test]
+    fn sqlite_create_assigns_ids_and_rejects_duplicates() {
+        let repo = SqliteUserRepository::open_in_memory().unwrap();
+
+        let alice = repo.create(input("alice")).unwrap();
+        let bob = repo.register(input("bob"), "hash".to_string()).unwrap();
+        assert!(bob.id > alice.id);
+        assert_eq!(repo.get("alice").unwrap().id, alice.id);
+        assert_eq!(repo.password_hash("alice").unwrap(), None);
+        assert_eq!(repo.password_hash("bob").unwrap().as_deref(), Some("hash"));
+
+        assert!(matches!(repo.create(input("alice")), Err(RepositoryError::Conflict(name)) if name == "alice"));
+        let names: Vec<_> = repo.list().unwrap().into_iter().map(|user| user.input.username).collect();
+        assert_eq!(names, ["alice", "bob"]);
+    }
+
+    #[test]
+    fn sqlite_update_keeps_id_and_rejects_taken_names() {
+        let repo = SqliteUserRepository::open_in_memory().unwrap();
+        let alice = repo.create(input("alice")).unwrap();
+        repo.create(input("bob")).unwrap();
+
+        // 改成已存在的用户名时返回Conflict，原记录不变
+        assert!(matches!(repo.update("alice", input("bob")), Err(RepositoryError::Conflict(name)) if name == "bob"));
+        assert_eq!(repo.get("alice").unwrap().input.email, "alice@example.com");
+
+        let renamed = repo.update("alice", input("carol")).unwrap();
+        assert_eq!(renamed.id, alice.id);
+        assert_eq!(repo.get_by_id(alice.id).unwrap().input.username, "carol");
+        assert!(matches!(repo.get("alice"), Err(RepositoryError::NotFound(_))));
+        assert!(matches!(repo.update("alice", input("dave")), Err(RepositoryError::NotFound(_))));
+    }
+
+    #[test]
+    fn sqlite_delete_removes_the_user_once() {
+        let repo = SqliteUserRepository::open_in_memory().unwrap();
+        let alice = repo.create(input("alice")).unwrap();
+
+        repo.delete("alice").unwrap();
+        assert!(matches!(repo.get("alice"), Err(RepositoryError::NotFound(_))));
+        assert!(matches!(repo.get_by_id(alice.id), Err(RepositoryError::NotFound(_))));
+        assert!(matches!(repo.delete("alice"), Err(RepositoryError::NotFound(_))));
+
+        // AUTOINCREMENT保证被删除用户的ID不会分配给新用户
+        assert!(repo.create(input("alice")).unwrap().id > alice.id);
+    }
+}
@@ -0,0 +1,89 @@
+// 外部库导入
+use actix_web::body::MessageBody;                       // 响应体trait
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::http::header::{HeaderName, HeaderValue}; // 请求头类型
+use actix_web::middleware::Next;                        // 中间件链中的下一个服务
+use actix_web::{FromRequest, HttpMessage, HttpRequest}; // 提取器相关类型
+use futures::future::{ready, Ready};                    // 用于同步提取器的立即完成的Future
+use rand::Rng;                                          // 用于生成随机ID
+
+/// 请求ID使用的请求头
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 客户端传入的请求ID的最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 请求ID
+///
+/// 由`request_id`中间件放入请求扩展，处理函数可以直接声明该参数获取，
+/// 错误响应也会带上同一个ID，便于把客户端看到的错误和服务端日志对应起来
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// 生成一个新的随机请求ID（32位十六进制字符串）
+    pub fn generate() -> Self {
+        RequestId(format!("{:032x}", rand::thread_rng().r#gen::<u128>()))
+    }
+
+    /// 读取请求扩展中的请求ID
+    pub fn of(req: &HttpRequest) -> Option<RequestId> {
+        req.extensions().get::<RequestId>().cloned()
+    }
+
+    /// 校验客户端传入的请求ID
+    ///
+    /// 只接受长度合理且由字母、数字、`-`、`_`、`.`组成的值，
+    /// 避免把任意内容写进日志
+    fn parse(value: &HeaderValue) -> Option<RequestId> {
+        let value = value.to_str().ok()?.trim();
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        valid.then(|| RequestId(value.to_string()))
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    /// 未经过`request_id`中间件的请求会得到一个新生成的ID
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(RequestId::of(req).unwrap_or_else(RequestId::generate)))
+    }
+}
+
+/// 请求ID中间件
+///
+/// 沿用客户端传入的合法`X-Request-Id`，否则生成一个新的ID；
+/// ID会放入请求扩展，并写回到响应头中
+///
+/// # 参数
+/// * `req` - 服务请求
+/// * `next` - 中间件链中的下一个服务
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+    req.extensions_mut().insert(id.clone());
+
+    let mut res = next.call(req).await?;
+    if let Ok(value) = HeaderValue::from_str(&id.0) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,1296 @@
+// 标准库导入
+use std::collections::HashSet;  // 用于检查重复的监听地址
+use std::net::SocketAddr;        // 用于校验监听地址
+use std::path::{Path, PathBuf};  // 用于表示配置文件和证书路径
+use std::time::Duration;         // 用于表示时间相关的配置
+
+// 外部库导入
+use clap::Parser;                    // 用于解析命令行参数
+use derive_more::{Display, Error};   // 用于自动派生Display和Error trait
+use serde::Deserialize;              // 用于从TOML反序列化配置
+use actix_web::http;                 // 用于校验请求头名称
+
+// 内部模块导入
+use crate::vhost::HostPattern;       // 用于校验虚拟主机的主机模式
+
+/// 默认配置文件路径
+///
+/// 未通过`--config`或`WEB_CONFIG`指定时，若该文件存在则自动加载
+pub const DEFAULT_SETTINGS_FILE: &str = "settings.toml";
+
+/// 环境变量前缀
+///
+/// 所有以该前缀开头的环境变量都会被视为配置覆盖项
+pub const ENV_PREFIX: &str = "WEB_";
+
+/// 事件频道缓冲区的上限
+const MAX_CHANNEL_CAPACITY: usize = 65536;
+
+/// 配置加载错误
+///
+/// 表示读取、解析或校验配置时发生的错误
+#[derive(Debug, Display, Error)]  // 自动派生Debug、Display和Error trait
+pub enum SettingsError {
+    #[display(fmt = "无法读取配置文件 {}: {}", "path.display()", source)]
+    /// 配置文件读取失败
+    Io { path: PathBuf, source: std::io::Error },
+
+    #[display(fmt = "配置文件 {} 格式错误: {}", "path.display()", source)]
+    /// 配置文件解析失败（包括TOML中重复定义的键）
+    Parse { path: PathBuf, source: toml::de::Error },
+
+    #[display(fmt = "环境变量 {key} 无效: {message}")]
+    /// 环境变量无法识别或取值无法解析
+    Env {
+        #[error(not(source))]
+        key: String,
+        #[error(not(source))]
+        message: String,
+    },
+
+    #[display(fmt = "配置校验失败: {}", "_0.join(\"; \")")]
+    /// 配置校验失败，列出所有不合法的配置项
+    Invalid(#[error(not(source))] Vec<String>),
+}
+
+/// 应用配置
+///
+/// 按以下优先级逐层合并（后者覆盖前者）：
+/// 1. 内置默认值
+/// 2. TOML配置文件
+/// 3. `WEB_*`环境变量
+/// 4. 命令行参数
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]  // 缺省字段使用默认值，未知字段直接报错
+pub struct Settings {
+    pub app_name: String,          // 应用名称，注入到AppState中
+    pub server: ServerSettings,    // HTTP服务器配置
+    pub tls: TlsSettings,          // TLS证书配置
+    pub storage: StorageSettings,  // 用户存储配置
+    pub auth: AuthSettings,        // 认证和会话配置
+    pub jwt: JwtSettings,          // JWT令牌配置
+    pub events: EventSettings,     // 事件总线配置
+    pub websocket: WebSocketSettings, // WebSocket配置
+    pub counters: CounterSettings, // 命名计数器配置
+    pub logging: LoggingSettings,  // 日志配置
+    pub health: HealthSettings,    // 健康检查配置
+    pub rate_limit: RateLimitSettings, // 限流配置
+    pub login_guard: LoginGuardSettings, // 登录防暴力破解配置
+    pub virtual_hosts: VirtualHostsSettings, // 虚拟主机配置
+    #[serde(skip)]
+    pub ignored_env: Vec<String>,  // 加载时忽略的未知`WEB_*`环境变量，由日志系统初始化后输出警告
+}
+
+/// HTTP服务器配置
+///
+/// 对应HttpServer上的各项调优参数
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub bind: String,                         // 监听地址，例如"127.0.0.1:8087"
+    pub workers: usize,                       // 工作线程数
+    pub max_connections: usize,               // 每个工作线程的最大连接数
+    pub max_connection_rate: usize,           // 每个工作线程每秒最多处理的TLS握手数
+    pub keep_alive_secs: u64,                 // 保持连接的时间（秒），0表示关闭keep-alive
+    pub backlog: u32,                         // 等待队列的长度
+    pub client_disconnect_timeout_secs: u64,  // 关闭客户端连接时的等待时间（秒）
+    pub shutdown_timeout_secs: u64,           // 优雅停止时等待请求和SSE流结束的期限（秒）
+    pub listeners: Vec<ListenerSettings>,     // bind之外的其他监听器
+}
+
+/// 监听器类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenerKind {
+    Http,   // 明文HTTP，按mode重定向到HTTPS或只提供/healthz
+    Https,  // HTTPS，提供完整的应用
+    Unix,   // Unix domain socket上的明文HTTP，提供完整的应用，供本机的sidecar使用
+}
+
+/// 明文HTTP监听器的行为
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpMode {
+    #[default]
+    Redirect,  // 所有请求308重定向到server.bind上的HTTPS
+    Health,    // 只提供/healthz，其他路径返回404
+}
+
+/// 一个监听器
+///
+/// 每个监听器单独运行一组工作线程，连接数限制互不影响；
+/// 未设置的限制沿用server中的对应值
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerSettings {
+    pub kind: ListenerKind,                   // 监听器类型
+    pub bind: String,                         // 监听地址；unix类型为socket文件路径
+    #[serde(default)]
+    pub mode: HttpMode,                       // 明文HTTP监听器的行为，仅对http类型有效
+    #[serde(default)]
+    pub workers: Option<usize>,               // 工作线程数
+    #[serde(default)]
+    pub max_connections: Option<usize>,       // 每个工作线程的最大连接数
+    #[serde(default)]
+    pub max_connection_rate: Option<usize>,   // 每个工作线程每秒最多处理的TLS握手数，仅对https类型有效
+}
+
+impl ServerSettings {
+    /// 所有监听器：server.bind上的HTTPS监听器在前，之后是listeners中的其他监听器
+    pub fn all_listeners(&self) -> Vec<ListenerSettings> {
+        let primary = ListenerSettings {
+            kind: ListenerKind::Https,
+            bind: self.bind.clone(),
+            mode: HttpMode::default(),
+            workers: None,
+            max_connections: None,
+            max_connection_rate: None,
+        };
+        std::iter::once(primary).chain(self.listeners.iter().cloned()).collect()
+    }
+}
+
+/// TLS证书配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    pub key_file: PathBuf,   // 私钥文件路径（PEM格式）
+    pub cert_file: PathBuf,  // 证书链文件路径（PEM格式）
+    pub reload_interval_secs: u64,  // 检查证书文件是否变化的间隔（秒），0表示只在收到SIGHUP时重新加载
+    pub client_auth: ClientAuth,    // 客户端证书认证（mTLS）模式
+    pub client_ca_file: Option<PathBuf>,  // 用于校验客户端证书的CA证书文件（PEM格式，可包含多张证书）
+}
+
+/// 客户端证书认证（mTLS）模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuth {
+    Off,       // 不请求客户端证书
+    Optional,  // 请求客户端证书，未提供时仍允许连接，提供了则必须通过校验
+    Require,   // 必须提供通过校验的客户端证书，否则握手失败
+}
+
+impl std::str::FromStr for ClientAuth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(ClientAuth::Off),
+            "optional" => Ok(ClientAuth::Optional),
+            "require" => Ok(ClientAuth::Require),
+            _ => Err("可选值为 off、optional 或 require".to_string()),
+        }
+    }
+}
+
+/// 用户存储后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Memory,  // 内存存储，重启后数据丢失
+    Sqlite,  // SQLite文件存储
+}
+
+impl std::str::FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "memory" => Ok(StorageBackend::Memory),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err("可选值为 memory 或 sqlite".to_string()),
+        }
+    }
+}
+
+/// 用户存储配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,  // 存储后端
+    pub sqlite_path: PathBuf,     // SQLite数据库文件路径，仅在backend为sqlite时使用
+}
+
+/// 认证和会话配置
+///
+/// Debug输出中会隐藏密钥，避免启动日志泄露
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub session_secret: Option<String>,  // 会话Cookie签名密钥，至少64字节；未设置时每次启动随机生成
+    pub cookie_name: String,             // 会话Cookie名称
+    pub session_ttl_secs: u64,           // 会话有效期（秒）
+}
+
+/// JWT签名算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum JwtAlgorithm {
+    HS256,  // HMAC-SHA256，使用共享密钥
+    RS256,  // RSA-SHA256，使用PEM格式的私钥签名、公钥校验
+}
+
+impl std::str::FromStr for JwtAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "HS256" => Ok(JwtAlgorithm::HS256),
+            "RS256" => Ok(JwtAlgorithm::RS256),
+            _ => Err("可选值为 HS256 或 RS256".to_string()),
+        }
+    }
+}
+
+/// JWT令牌配置
+///
+/// Debug输出中会隐藏密钥，避免启动日志泄露
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtSettings {
+    pub algorithm: JwtAlgorithm,            // 签名算法
+    pub secret: Option<String>,             // HS256共享密钥，至少32字节；未设置时每次启动随机生成
+    pub private_key_file: PathBuf,          // RS256私钥文件（PEM格式）
+    pub public_key_file: Option<PathBuf>,   // RS256公钥文件（PEM格式），未设置时从私钥推导
+    pub issuer: String,                     // 令牌签发者（iss）
+    pub access_ttl_secs: u64,               // 访问令牌有效期（秒）
+    pub refresh_ttl_secs: u64,              // 刷新令牌有效期（秒）
+}
+
+/// 事件总线配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventSettings {
+    pub channel_capacity: usize,  // 每个频道为每个订阅者缓冲的事件数，超出后最旧的事件被丢弃
+    pub replay_buffer: usize,     // 每个频道保留的最近事件数，用于按Last-Event-ID补发
+    pub retry_ms: u64,            // 建议客户端断线后的重连间隔（毫秒），通过SSE的retry字段下发
+    pub keepalive_secs: u64,      // 空闲时发送注释保活的间隔（秒）
+}
+
+/// WebSocket配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebSocketSettings {
+    pub heartbeat_secs: u64,      // 服务端发送ping的间隔（秒）
+    pub idle_timeout_secs: u64,   // 超过该时间没有收到客户端任何消息（包括pong）则断开（秒）
+    pub max_frame_size: usize,    // 单个消息的最大字节数
+    pub outbound_buffer: usize,   // 每个连接待发送消息的缓冲区大小，满了之后新的推送被丢弃
+}
+
+/// 命名计数器配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CounterSettings {
+    pub snapshot_file: Option<PathBuf>,  // 快照文件路径，未设置时计数器只保存在内存中
+    pub snapshot_interval_secs: u64,     // 有变化时写入快照的间隔（秒）
+    pub max_counters: usize,             // 计数器数量上限，每个计数器都是/metrics中的一个标签值
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,  // 便于阅读的单行文本
+    Json,  // 每行一个JSON对象，便于日志系统采集
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("可选值为 text 或 json".to_string()),
+        }
+    }
+}
+
+/// 日志配置
+///
+/// 访问日志始终是JSON格式，`format`只影响其他日志
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSettings {
+    pub level: String,      // 日志级别，语法与RUST_LOG相同，例如"info,actix_server=warn"
+    pub format: LogFormat,  // 日志格式
+    pub access_log: bool,   // 是否输出访问日志
+}
+
+/// 健康检查配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthSettings {
+    pub tls_expiry_warn_days: u64,  // 证书剩余有效期少于该天数时/readyz报告警告
+    pub unready_delay_secs: u64,    // 收到停止信号后先报告未就绪，等待该时间再停止接受连接（秒）
+}
+
+/// 限流配置
+///
+/// 每个客户端在每条路由上有一个令牌桶：桶容量为`burst`，每分钟补充`requests_per_minute`个令牌
+/// 客户端按以下顺序识别：已登记的API Key > 已登录用户 > 对端IP
+///
+/// Debug输出中只显示API Key的数量，避免启动日志泄露
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSettings {
+    pub enabled: bool,                // 是否启用限流
+    pub requests_per_minute: u32,     // 默认配额：每分钟补充的令牌数
+    pub burst: u32,                   // 默认配额：令牌桶容量，即允许的突发请求数
+    pub api_key_header: String,       // 携带API Key的请求头
+    pub api_keys: Vec<String>,        // 已登记的API Key，未登记的Key按IP限流，避免换Key绕过
+    pub sweep_interval_secs: u64,     // 清理已经补满的令牌桶的间隔（秒）
+    pub routes: Vec<RouteQuota>,      // 单独配置配额的路由
+}
+
+/// 单条路由的配额
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteQuota {
+    pub route: String,                // 路由模式，与注册时相同，例如"/login"或"user/{name}"
+    pub requests_per_minute: u32,     // 每分钟补充的令牌数
+    pub burst: u32,                   // 令牌桶容量
+}
+
+/// 登录防暴力破解配置
+///
+/// 分别按用户名和对端IP统计失败次数：每次失败后的响应按失败次数逐步延迟，
+/// 在`failure_window_secs`内失败达到上限后锁定`lockout_secs`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoginGuardSettings {
+    pub enabled: bool,                // 是否启用
+    pub max_failures_per_user: u32,   // 同一用户名允许的连续失败次数
+    pub max_failures_per_ip: u32,     // 同一IP允许的失败次数，应大于单个用户名的上限，用于发现撞库
+    pub failure_window_secs: u64,     // 统计失败次数的时间窗口（秒），超过后重新计数
+    pub lockout_secs: u64,            // 锁定时间（秒）
+    pub base_delay_ms: u64,           // 第一次失败后的延迟（毫秒），之后每次失败翻倍
+    pub max_delay_ms: u64,            // 延迟的上限（毫秒）
+}
+
+/// 虚拟主机配置
+///
+/// 按请求的主机名（URI中的authority或Host头）查找虚拟主机，按配置顺序第一个匹配的生效；
+/// 都不匹配时使用`default_host`，没有配置默认主机时只有不区分主机的路由能够匹配
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VirtualHostsSettings {
+    pub strict_sni: bool,                 // TLS握手的SNI与Host属于不同虚拟主机时返回421
+    pub default_host: Option<String>,     // 没有主机匹配时使用的虚拟主机名称
+    pub hosts: Vec<VirtualHostSettings>,  // 虚拟主机表
+}
+
+/// 单个虚拟主机
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VirtualHostSettings {
+    pub name: String,                             // 名称，路由通过它选择虚拟主机
+    pub patterns: Vec<String>,                    // 主机模式，例如"example.com"、"*.example.com"或"example.com:8443"
+    #[serde(default)]
+    pub response: Option<StaticResponseSettings>, // 配置后该主机的所有请求直接返回这个响应，不进入路由
+}
+
+/// 虚拟主机的固定响应
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StaticResponseSettings {
+    pub status: u16,               // 状态码
+    pub content_type: String,      // Content-Type
+    pub body: String,              // 响应体
+    pub location: Option<String>,  // Location头，用于重定向
+}
+
+/// 命令行参数
+///
+/// 所有参数都是可选的，只有显式给出的参数才会覆盖配置
+/// 同一个参数重复出现时clap会直接报错
+#[derive(Debug, Default, Parser)]
+#[command(name = "web_learning", about = "Actix-Web学习示例服务器")]
+pub struct Cli {
+    /// 配置文件路径，默认为settings.toml
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 应用名称
+    #[arg(long)]
+    pub app_name: Option<String>,
+
+    /// 监听地址，例如127.0.0.1:8087
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<String>,
+
+    /// 工作线程数
+    #[arg(long)]
+    pub workers: Option<usize>,
+
+    /// 每个工作线程的最大连接数
+    #[arg(long)]
+    pub max_connections: Option<usize>,
+
+    /// 每个工作线程每秒最多处理的TLS握手数
+    #[arg(long)]
+    pub max_connection_rate: Option<usize>,
+
+    /// 保持连接的时间（秒）
+    #[arg(long, value_name = "SECS")]
+    pub keep_alive: Option<u64>,
+
+    /// 等待队列的长度
+    #[arg(long)]
+    pub backlog: Option<u32>,
+
+    /// 关闭客户端连接时的等待时间（秒）
+    #[arg(long, value_name = "SECS")]
+    pub client_disconnect_timeout: Option<u64>,
+
+    /// 优雅关闭服务器的超时时间（秒）
+    #[arg(long, value_name = "SECS")]
+    pub shutdown_timeout: Option<u64>,
+
+    /// 私钥文件路径
+    #[arg(long, value_name = "FILE")]
+    pub tls_key: Option<PathBuf>,
+
+    /// 证书链文件路径
+    #[arg(long, value_name = "FILE")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// 客户端证书认证（mTLS）模式
+    #[arg(long, value_enum)]
+    pub tls_client_auth: Option<ClientAuth>,
+
+    /// 用户存储后端
+    #[arg(long, value_enum)]
+    pub storage_backend: Option<StorageBackend>,
+
+    /// SQLite数据库文件路径
+    #[arg(long, value_name = "FILE")]
+    pub sqlite_path: Option<PathBuf>,
+
+    /// 日志级别，语法与RUST_LOG相同
+    #[arg(long, value_name = "FILTER")]
+    pub log_level: Option<String>,
+
+    /// 日志格式
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            app_name: "Kayano".to_string(),
+            server: ServerSettings::default(),
+            tls: TlsSettings::default(),
+            storage: StorageSettings::default(),
+            auth: AuthSettings::default(),
+            jwt: JwtSettings::default(),
+            events: EventSettings::default(),
+            websocket: WebSocketSettings::default(),
+            counters: CounterSettings::default(),
+            logging: LoggingSettings::default(),
+            health: HealthSettings::default(),
+            rate_limit: RateLimitSettings::default(),
+            login_guard: LoginGuardSettings::default(),
+            virtual_hosts: VirtualHostsSettings::default(),
+            ignored_env: Vec::new(),
+        }
+    }
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            bind: "127.0.0.1:8087".to_string(),
+            workers: 10,
+            max_connections: 100,
+            max_connection_rate: 10,
+            keep_alive_secs: 75,
+            backlog: 100,
+            client_disconnect_timeout_secs: 10,
+            shutdown_timeout_secs: 60,
+            listeners: Vec::new(),
+        }
+    }
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        TlsSettings {
+            key_file: PathBuf::from("key.pem"),
+            cert_file: PathBuf::from("cert.pem"),
+            reload_interval_secs: 10,
+            client_auth: ClientAuth::Off,
+            client_ca_file: None,
+        }
+    }
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        StorageSettings {
+            backend: StorageBackend::Memory,
+            sqlite_path: PathBuf::from("users.db"),
+        }
+    }
+}
+
+impl std::fmt::Debug for AuthSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthSettings")
+            .field("session_secret", &self.session_secret.as_ref().map(|_| "***"))
+            .field("cookie_name", &self.cookie_name)
+            .field("session_ttl_secs", &self.session_ttl_secs)
+            .finish()
+    }
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        AuthSettings {
+            session_secret: None,
+            cookie_name: "web_session".to_string(),
+            session_ttl_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+impl std::fmt::Debug for JwtSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtSettings")
+            .field("algorithm", &self.algorithm)
+            .field("secret", &self.secret.as_ref().map(|_| "***"))
+            .field("private_key_file", &self.private_key_file)
+            .field("public_key_file", &self.public_key_file)
+            .field("issuer", &self.issuer)
+            .field("access_ttl_secs", &self.access_ttl_secs)
+            .field("refresh_ttl_secs", &self.refresh_ttl_secs)
+            .finish()
+    }
+}
+
+impl Default for JwtSettings {
+    fn default() -> Self {
+        JwtSettings {
+            algorithm: JwtAlgorithm::HS256,
+            secret: None,
+            private_key_file: PathBuf::from("key.pem"),
+            public_key_file: None,
+            issuer: "web_learning".to_string(),
+            access_ttl_secs: 15 * 60,
+            refresh_ttl_secs: 14 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        EventSettings {
+            channel_capacity: 256,
+            replay_buffer: 100,
+            retry_ms: 3000,
+            keepalive_secs: 15,
+        }
+    }
+}
+
+impl Default for WebSocketSettings {
+    fn default() -> Self {
+        WebSocketSettings {
+            heartbeat_secs: 10,
+            idle_timeout_secs: 30,
+            max_frame_size: 64 * 1024,
+            outbound_buffer: 64,
+        }
+    }
+}
+
+impl Default for CounterSettings {
+    fn default() -> Self {
+        CounterSettings {
+            snapshot_file: Some(PathBuf::from("counters.json")),
+            snapshot_interval_secs: 30,
+            max_counters: 1000,
+        }
+    }
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        LoggingSettings {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+            access_log: true,
+        }
+    }
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        HealthSettings {
+            tls_expiry_warn_days: 14,
+            unready_delay_secs: 0,
+        }
+    }
+}
+
+impl std::fmt::Debug for RateLimitSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitSettings")
+            .field("enabled", &self.enabled)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("burst", &self.burst)
+            .field("api_key_header", &self.api_key_header)
+            .field("api_keys", &format_args!("<{}个>", self.api_keys.len()))
+            .field("sweep_interval_secs", &self.sweep_interval_secs)
+            .field("routes", &self.routes)
+            .finish()
+    }
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        let strict = |route: &str| RouteQuota {
+            route: route.to_string(),
+            requests_per_minute: 10,
+            burst: 5,
+        };
+        RateLimitSettings {
+            enabled: true,
+            requests_per_minute: 600,
+            burst: 100,
+            api_key_header: "X-Api-Key".to_string(),
+            api_keys: Vec::new(),
+            sweep_interval_secs: 60,
+            routes: vec![strict("/login"), strict("/register"), strict("/token")],
+        }
+    }
+}
+
+impl Default for VirtualHostsSettings {
+    fn default() -> Self {
+        let site = |name: &str, pattern: &str| VirtualHostSettings {
+            name: name.to_string(),
+            patterns: vec![pattern.to_string()],
+            response: None,
+        };
+        VirtualHostsSettings {
+            strict_sni: true,
+            default_host: None,
+            hosts: vec![site("users", "users.rust-lang.org"), site("www", "www.rust-lang.org")],
+        }
+    }
+}
+
+impl Default for StaticResponseSettings {
+    fn default() -> Self {
+        StaticResponseSettings {
+            status: 200,
+            content_type: "text/plain; charset=utf-8".to_string(),
+            body: String::new(),
+            location: None,
+        }
+    }
+}
+
+impl Default for LoginGuardSettings {
+    fn default() -> Self {
+        LoginGuardSettings {
+            enabled: true,
+            max_failures_per_user: 5,
+            max_failures_per_ip: 20,
+            failure_window_secs: 15 * 60,
+            lockout_secs: 15 * 60,
+            base_delay_ms: 250,
+            max_delay_ms: 4000,
+        }
+    }
+}
+
+impl HealthSettings {
+    /// 报告未就绪后等待的时间
+    pub fn unready_delay(&self) -> Duration {
+        Duration::from_secs(self.unready_delay_secs)
+    }
+}
+
+impl WebSocketSettings {
+    /// 发送ping的间隔
+    pub fn heartbeat(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_secs)
+    }
+
+    /// 空闲超时时间
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+}
+
+impl ServerSettings {
+    /// 保持连接的时间
+    pub fn keep_alive(&self) -> Duration {
+        Duration::from_secs(self.keep_alive_secs)
+    }
+
+    /// 关闭客户端连接时的等待时间
+    pub fn client_disconnect_timeout(&self) -> Duration {
+        Duration::from_secs(self.client_disconnect_timeout_secs)
+    }
+}
+
+impl Settings {
+    /// 从命令行参数和进程环境变量加载配置
+    ///
+    /// 命令行参数解析失败时clap会打印帮助信息并退出进程
+    ///
+    /// # 返回值
+    /// * 成功时返回校验通过的配置
+    /// * 失败时返回SettingsError
+    pub fn load() -> Result<Self, SettingsError> {
+        let cli = Cli::parse();
+        Self::load_from(&cli, std::env::vars())
+    }
+
+    /// 从给定的命令行参数和环境变量加载配置
+    ///
+    /// # 参数
+    /// * `cli` - 已解析的命令行参数
+    /// * `env` - 环境变量键值对，只有`WEB_`前缀的变量会被读取
+    ///
+    /// # 返回值
+    /// * 成功时返回校验通过的配置
+    /// * 失败时返回SettingsError
+    pub fn load_from<I>(cli: &Cli, env: I) -> Result<Self, SettingsError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        // 只保留带有前缀的环境变量，并按名称排序，保证报错顺序稳定
+        let mut env: Vec<(String, String)> = env
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        env.sort();
+
+        // 确定配置文件路径：命令行参数 > WEB_CONFIG > 默认路径
+        let env_config = env
+            .iter()
+            .find(|(key, _)| key == "WEB_CONFIG")
+            .map(|(_, value)| PathBuf::from(value));
+        let explicit = cli.config.clone().or(env_config);
+
+        // 显式指定的配置文件必须存在，默认配置文件则可以缺省
+        let mut settings = match &explicit {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_SETTINGS_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_SETTINGS_FILE))?
+            }
+            None => Settings::default(),
+        };
+
+        settings.ignored_env.clear();
+        settings.apply_env(&env)?;
+        settings.apply_cli(cli);
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// 从TOML文件读取配置
+    ///
+    /// TOML本身不允许重复定义同一个键，重复的配置会在这里直接报错
+    ///
+    /// # 参数
+    /// * `path` - 配置文件路径
+    pub fn from_file(path: &Path) -> Result<Self, SettingsError> {
+        let content = std::fs::read_to_string(path).map_err(|source| SettingsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        toml::from_str(&content).map_err(|source| SettingsError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// 使用`WEB_*`环境变量覆盖配置
+    ///
+    /// `WEB_`前缀较通用，其他工具和CI也可能设置，无法识别的变量不会导致加载失败，
+    /// 而是记录到`ignored_env`中，由调用方在日志系统初始化后输出警告
+    ///
+    /// # 参数
+    /// * `env` - 已按前缀过滤的环境变量
+    fn apply_env(&mut self, env: &[(String, String)]) -> Result<(), SettingsError> {
+        for (key, value) in env {
+            match key.as_str() {
+                // 配置文件路径已在加载文件前处理
+                "WEB_CONFIG" => {}
+                "WEB_APP_NAME" => self.app_name = value.clone(),
+                "WEB_BIND" => self.server.bind = value.clone(),
+                "WEB_WORKERS" => self.server.workers = parse_env(key, value)?,
+                "WEB_MAX_CONNECTIONS" => self.server.max_connections = parse_env(key, value)?,
+                "WEB_MAX_CONNECTION_RATE" => {
+                    self.server.max_connection_rate = parse_env(key, value)?
+                }
+                "WEB_KEEP_ALIVE" => self.server.keep_alive_secs = parse_env(key, value)?,
+                "WEB_BACKLOG" => self.server.backlog = parse_env(key, value)?,
+                "WEB_CLIENT_DISCONNECT_TIMEOUT" => {
+                    self.server.client_disconnect_timeout_secs = parse_env(key, value)?
+                }
+                "WEB_SHUTDOWN_TIMEOUT" => {
+                    self.server.shutdown_timeout_secs = parse_env(key, value)?
+                }
+                "WEB_TLS_KEY" => self.tls.key_file = PathBuf::from(value),
+                "WEB_TLS_CERT" => self.tls.cert_file = PathBuf::from(value),
+                "WEB_TLS_RELOAD_INTERVAL" => self.tls.reload_interval_secs = parse_env(key, value)?,
+                "WEB_TLS_CLIENT_AUTH" => self.tls.client_auth = parse_env(key, value)?,
+                "WEB_TLS_CLIENT_CA" => self.tls.client_ca_file = Some(PathBuf::from(value)),
+                "WEB_STORAGE_BACKEND" => self.storage.backend = parse_env(key, value)?,
+                "WEB_SQLITE_PATH" => self.storage.sqlite_path = PathBuf::from(value),
+                "WEB_SESSION_SECRET" => self.auth.session_secret = Some(value.clone()),
+                "WEB_JWT_ALGORITHM" => self.jwt.algorithm = parse_env(key, value)?,
+                "WEB_JWT_SECRET" => self.jwt.secret = Some(value.clone()),
+                "WEB_EVENTS_CAPACITY" => self.events.channel_capacity = parse_env(key, value)?,
+                "WEB_EVENTS_REPLAY_BUFFER" => self.events.replay_buffer = parse_env(key, value)?,
+                "WEB_EVENTS_RETRY_MS" => self.events.retry_ms = parse_env(key, value)?,
+                "WEB_EVENTS_KEEPALIVE" => self.events.keepalive_secs = parse_env(key, value)?,
+                "WEB_WS_HEARTBEAT" => self.websocket.heartbeat_secs = parse_env(key, value)?,
+                "WEB_WS_IDLE_TIMEOUT" => self.websocket.idle_timeout_secs = parse_env(key, value)?,
+                "WEB_LOG_LEVEL" => self.logging.level = value.clone(),
+                "WEB_LOG_FORMAT" => self.logging.format = parse_env(key, value)?,
+                "WEB_COUNTERS_FILE" => self.counters.snapshot_file = Some(PathBuf::from(value)),
+                "WEB_COUNTERS_INTERVAL" => {
+                    self.counters.snapshot_interval_secs = parse_env(key, value)?
+                }
+                "WEB_COUNTERS_MAX" => self.counters.max_counters = parse_env(key, value)?,
+                "WEB_HEALTH_UNREADY_DELAY" => {
+                    self.health.unready_delay_secs = parse_env(key, value)?
+                }
+                "WEB_RATE_LIMIT_ENABLED" => self.rate_limit.enabled = parse_env(key, value)?,
+                "WEB_RATE_LIMIT_RPM" => self.rate_limit.requests_per_minute = parse_env(key, value)?,
+                "WEB_RATE_LIMIT_BURST" => self.rate_limit.burst = parse_env(key, value)?,
+                "WEB_LOGIN_GUARD_ENABLED" => self.login_guard.enabled = parse_env(key, value)?,
+                "WEB_LOGIN_MAX_FAILURES" => {
+                    self.login_guard.max_failures_per_user = parse_env(key, value)?
+                }
+                "WEB_LOGIN_LOCKOUT" => self.login_guard.lockout_secs = parse_env(key, value)?,
+                "WEB_VHOST_STRICT_SNI" => self.virtual_hosts.strict_sni = parse_env(key, value)?,
+                "WEB_VHOST_DEFAULT" => self.virtual_hosts.default_host = Some(value.clone()),
+                _ => self.ignored_env.push(key.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    /// 使用命令行参数覆盖配置
+    ///
+    /// # 参数
+    /// * `cli` - 已解析的命令行参数
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(app_name) = &cli.app_name {
+            self.app_name = app_name.clone();
+        }
+        if let Some(bind) = &cli.bind {
+            self.server.bind = bind.clone();
+        }
+        if let Some(workers) = cli.workers {
+            self.server.workers = workers;
+        }
+        if let Some(max_connections) = cli.max_connections {
+            self.server.max_connections = max_connections;
+        }
+        if let Some(max_connection_rate) = cli.max_connection_rate {
+            self.server.max_connection_rate = max_connection_rate;
+        }
+        if let Some(keep_alive) = cli.keep_alive {
+            self.server.keep_alive_secs = keep_alive;
+        }
+        if let Some(backlog) = cli.backlog {
+            self.server.backlog = backlog;
+        }
+        if let Some(timeout) = cli.client_disconnect_timeout {
+            self.server.client_disconnect_timeout_secs = timeout;
+        }
+        if let Some(timeout) = cli.shutdown_timeout {
+            self.server.shutdown_timeout_secs = timeout;
+        }
+        if let Some(key) = &cli.tls_key {
+            self.tls.key_file = key.clone();
+        }
+        if let Some(cert) = &cli.tls_cert {
+            self.tls.cert_file = cert.clone();
+        }
+        if let Some(mode) = cli.tls_client_auth {
+            self.tls.client_auth = mode;
+        }
+        if let Some(backend) = cli.storage_backend {
+            self.storage.backend = backend;
+        }
+        if let Some(path) = &cli.sqlite_path {
+            self.storage.sqlite_path = path.clone();
+        }
+        if let Some(level) = &cli.log_level {
+            self.logging.level = level.clone();
+        }
+        if let Some(format) = cli.log_format {
+            self.logging.format = format;
+        }
+    }
+
+    /// 校验配置
+    ///
+    /// 一次性收集所有不合法的配置项，而不是遇到第一个就返回
+    ///
+    /// # 返回值
+    /// * 全部合法时返回Ok(())
+    /// * 否则返回SettingsError::Invalid，包含每一项的错误描述
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let mut problems = Vec::new();
+        let server = &self.server;
+
+        if self.app_name.trim().is_empty() {
+            problems.push("app_name 不能为空".to_string());
+        }
+        if server.bind.parse::<SocketAddr>().is_err() {
+            problems.push(format!("server.bind 不是合法的地址: {}", server.bind));
+        }
+        let mut binds = HashSet::new();
+        for (index, listener) in server.listeners.iter().enumerate() {
+            let field = format!("server.listeners[{index}]");
+            match listener.kind {
+                ListenerKind::Http | ListenerKind::Https => {
+                    if listener.bind.parse::<SocketAddr>().is_err() {
+                        problems.push(format!("{field}.bind 不是合法的地址: {}", listener.bind));
+                    }
+                }
+                ListenerKind::Unix => {
+                    if listener.bind.trim().is_empty() {
+                        problems.push(format!("{field}.bind 不能为空"));
+                    }
+                    if cfg!(not(unix)) {
+                        problems.push(format!("{field}: 当前平台不支持Unix domain socket"));
+                    }
+                }
+            }
+            if listener.bind == server.bind || !binds.insert(listener.bind.as_str()) {
+                problems.push(format!("{field}.bind 与其他监听器重复: {}", listener.bind));
+            }
+            for (name, value) in [
+                ("workers", listener.workers),
+                ("max_connections", listener.max_connections),
+                ("max_connection_rate", listener.max_connection_rate),
+            ] {
+                if value == Some(0) {
+                    problems.push(format!("{field}.{name} 必须大于0"));
+                }
+            }
+        }
+        if server.workers == 0 {
+            problems.push("server.workers 必须大于0".to_string());
+        }
+        if server.max_connections == 0 {
+            problems.push("server.max_connections 必须大于0".to_string());
+        }
+        if server.max_connection_rate == 0 {
+            problems.push("server.max_connection_rate 必须大于0".to_string());
+        }
+        if server.backlog == 0 {
+            problems.push("server.backlog 必须大于0".to_string());
+        }
+        // 关闭单个连接的等待时间不能超过整个服务器的关闭期限
+        if server.client_disconnect_timeout_secs > server.shutdown_timeout_secs {
+            problems.push(format!(
+                "server.client_disconnect_timeout_secs ({}) 与 server.shutdown_timeout_secs ({}) 冲突: 前者不能大于后者",
+                server.client_disconnect_timeout_secs, server.shutdown_timeout_secs
+            ));
+        }
+        for (field, path) in [("tls.key_file", &self.tls.key_file), ("tls.cert_file", &self.tls.cert_file)] {
+            if !path.is_file() {
+                problems.push(format!("{field} 文件不存在: {}", path.display()));
+            }
+        }
+        match (&self.tls.client_ca_file, self.tls.client_auth) {
+            (None, ClientAuth::Optional | ClientAuth::Require) => {
+                problems.push("tls.client_auth 启用时必须设置 tls.client_ca_file".to_string());
+            }
+            (Some(path), ClientAuth::Optional | ClientAuth::Require) if !path.is_file() => {
+                problems.push(format!("tls.client_ca_file 文件不存在: {}", path.display()));
+            }
+            _ => {}
+        }
+        if self.storage.backend == StorageBackend::Sqlite
+            && self.storage.sqlite_path.as_os_str().is_empty()
+        {
+            problems.push("storage.sqlite_path 不能为空".to_string());
+        }
+        if let Some(secret) = &self.auth.session_secret
+            && secret.len() < 64
+        {
+            problems.push(format!("auth.session_secret 至少需要64字节，当前为{}字节", secret.len()));
+        }
+        if self.auth.cookie_name.trim().is_empty() {
+            problems.push("auth.cookie_name 不能为空".to_string());
+        }
+        if self.auth.session_ttl_secs == 0 {
+            problems.push("auth.session_ttl_secs 必须大于0".to_string());
+        }
+        let jwt = &self.jwt;
+        match jwt.algorithm {
+            JwtAlgorithm::HS256 => {
+                if let Some(secret) = &jwt.secret
+                    && secret.len() < 32
+                {
+                    problems.push(format!("jwt.secret 至少需要32字节，当前为{}字节", secret.len()));
+                }
+            }
+            JwtAlgorithm::RS256 => {
+                if !jwt.private_key_file.is_file() {
+                    problems.push(format!("jwt.private_key_file 文件不存在: {}", jwt.private_key_file.display()));
+                }
+                if let Some(public) = &jwt.public_key_file
+                    && !public.is_file()
+                {
+                    problems.push(format!("jwt.public_key_file 文件不存在: {}", public.display()));
+                }
+            }
+        }
+        if jwt.access_ttl_secs == 0 {
+            problems.push("jwt.access_ttl_secs 必须大于0".to_string());
+        }
+        if jwt.refresh_ttl_secs <= jwt.access_ttl_secs {
+            problems.push(format!(
+                "jwt.refresh_ttl_secs ({}) 必须大于 jwt.access_ttl_secs ({})",
+                jwt.refresh_ttl_secs, jwt.access_ttl_secs
+            ));
+        }
+        if !(1..=MAX_CHANNEL_CAPACITY).contains(&self.events.channel_capacity) {
+            problems.push(format!(
+                "events.channel_capacity 必须在1到{}之间，当前为{}",
+                MAX_CHANNEL_CAPACITY, self.events.channel_capacity
+            ));
+        }
+        if self.events.replay_buffer > MAX_CHANNEL_CAPACITY {
+            problems.push(format!(
+                "events.replay_buffer 不能大于{}，当前为{}",
+                MAX_CHANNEL_CAPACITY, self.events.replay_buffer
+            ));
+        }
+        if self.events.keepalive_secs == 0 {
+            problems.push("events.keepalive_secs 必须大于0".to_string());
+        }
+        let ws = &self.websocket;
+        if ws.heartbeat_secs == 0 {
+            problems.push("websocket.heartbeat_secs 必须大于0".to_string());
+        }
+        // 空闲超时必须覆盖至少一次心跳，否则正常的连接也会被断开
+        if ws.idle_timeout_secs <= ws.heartbeat_secs {
+            problems.push(format!(
+                "websocket.idle_timeout_secs ({}) 必须大于 websocket.heartbeat_secs ({})",
+                ws.idle_timeout_secs, ws.heartbeat_secs
+            ));
+        }
+        if ws.max_frame_size == 0 {
+            problems.push("websocket.max_frame_size 必须大于0".to_string());
+        }
+        if ws.outbound_buffer == 0 {
+            problems.push("websocket.outbound_buffer 必须大于0".to_string());
+        }
+        if self.counters.snapshot_interval_secs == 0 {
+            problems.push("counters.snapshot_interval_secs 必须大于0".to_string());
+        }
+        if self.counters.max_counters == 0 {
+            problems.push("counters.max_counters 必须大于0".to_string());
+        }
+        if let Some(path) = &self.counters.snapshot_file
+            && path.as_os_str().is_empty()
+        {
+            problems.push("counters.snapshot_file 不能为空".to_string());
+        }
+        if let Err(problem) = check_log_filter(&self.logging.level) {
+            problems.push(format!("logging.level {problem}"));
+        }
+        let rate_limit = &self.rate_limit;
+        if rate_limit.requests_per_minute == 0 || rate_limit.burst == 0 {
+            problems.push("rate_limit.requests_per_minute 和 rate_limit.burst 必须大于0".to_string());
+        }
+        if rate_limit.sweep_interval_secs == 0 {
+            problems.push("rate_limit.sweep_interval_secs 必须大于0".to_string());
+        }
+        if http::header::HeaderName::try_from(rate_limit.api_key_header.as_str()).is_err() {
+            problems.push(format!("rate_limit.api_key_header 不是合法的请求头: {}", rate_limit.api_key_header));
+        }
+        for (index, quota) in rate_limit.routes.iter().enumerate() {
+            if quota.requests_per_minute == 0 || quota.burst == 0 {
+                problems.push(format!("rate_limit.routes[{index}] ({}) 的配额必须大于0", quota.route));
+            }
+            if rate_limit.routes[..index].iter().any(|other| other.route == quota.route) {
+                problems.push(format!("rate_limit.routes 中的路由重复: {}", quota.route));
+            }
+        }
+        let login_guard = &self.login_guard;
+        if login_guard.max_failures_per_user == 0 || login_guard.max_failures_per_ip == 0 {
+            problems.push("login_guard 的失败次数上限必须大于0".to_string());
+        }
+        if login_guard.failure_window_secs == 0 || login_guard.lockout_secs == 0 {
+            problems.push("login_guard.failure_window_secs 和 login_guard.lockout_secs 必须大于0".to_string());
+        }
+        if login_guard.base_delay_ms > login_guard.max_delay_ms {
+            problems.push("login_guard.base_delay_ms 不能大于 login_guard.max_delay_ms".to_string());
+        }
+        let virtual_hosts = &self.virtual_hosts;
+        for (index, host) in virtual_hosts.hosts.iter().enumerate() {
+            if host.name.trim().is_empty() {
+                problems.push(format!("virtual_hosts.hosts[{index}] 的 name 不能为空"));
+            }
+            if virtual_hosts.hosts[..index].iter().any(|other| other.name == host.name) {
+                problems.push(format!("virtual_hosts.hosts 中的名称重复: {}", host.name));
+            }
+            if host.patterns.is_empty() {
+                problems.push(format!("virtual_hosts.hosts[{index}] ({}) 至少需要一个主机模式", host.name));
+            }
+            for pattern in &host.patterns {
+                if let Err(problem) = HostPattern::parse(pattern) {
+                    problems.push(format!("virtual_hosts.hosts[{index}] ({}) 的主机模式 {pattern:?} {problem}", host.name));
+                }
+            }
+            if let Some(response) = &host.response {
+                if http::StatusCode::from_u16(response.status).is_err() {
+                    problems.push(format!("virtual_hosts.hosts[{index}] ({}) 的状态码无效: {}", host.name, response.status));
+                }
+                if http::header::HeaderValue::try_from(response.content_type.as_str()).is_err() {
+                    problems.push(format!("virtual_hosts.hosts[{index}] ({}) 的 content_type 无效", host.name));
+                }
+                if let Some(location) = &response.location
+                    && http::header::HeaderValue::try_from(location.as_str()).is_err()
+                {
+                    problems.push(format!("virtual_hosts.hosts[{index}] ({}) 的 location 无效", host.name));
+                }
+            }
+        }
+        if let Some(default_host) = &virtual_hosts.default_host
+            && !virtual_hosts.hosts.iter().any(|host| &host.name == default_host)
+        {
+            problems.push(format!("virtual_hosts.default_host 不在虚拟主机表中: {default_host}"));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(SettingsError::Invalid(problems))
+        }
+    }
+}
+
+/// 校验日志级别过滤规则
+///
+/// 规则以逗号分隔，每一项为`级别`、`模块`或`模块=级别`
+fn check_log_filter(filter: &str) -> Result<(), String> {
+    const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+    if filter.trim().is_empty() {
+        return Err("不能为空".to_string());
+    }
+    for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let level = match directive.split_once('=') {
+            Some((_, level)) => level,
+            // 单独一项可以是级别也可以是模块名，模块名不需要校验
+            None => continue,
+        };
+        if !LEVELS.contains(&level.to_ascii_lowercase().as_str()) {
+            return Err(format!("中的级别无效: {directive}"));
+        }
+    }
+    Ok(())
+}
+
+/// 解析环境变量的取值
+///
+/// # 参数
+/// * `key` - 环境变量名称，用于生成错误信息
+/// * `value` - 环境变量的取值
+fn parse_env<T>(key: &str, value: &str) -> Result<T, SettingsError>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    value.trim().parse().map_err(|err: T::Err| SettingsError::Env {
+        key: key.to_string(),
+        message: format!("无法解析 {value:?}: {err}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_hides_secrets() {
+        let mut settings = Settings::default();
+        settings.rate_limit.api_keys = vec!["key-one-secret".to_string(), "key-two-secret".to_string()];
+        settings.auth.session_secret = Some("session-secret-value".to_string());
+        settings.jwt.secret = Some("jwt-secret-value".to_string());
+
+        let output = format!("{:?}", settings);
+        for secret in ["key-one-secret", "key-two-secret", "session-secret-value", "jwt-secret-value"] {
+            assert!(!output.contains(secret), "Debug输出泄露了{secret}");
+        }
+        assert!(output.contains("api_keys: <2个>"), "{output}");
+    }
+
+    #[test]
+    fn unknown_env_vars_are_ignored() {
+        let env = vec![
+            ("WEB_BIND".to_string(), "127.0.0.1:9443".to_string()),
+            ("WEB_CONCURRENCY".to_string(), "4".to_string()),
+        ];
+        let mut settings = Settings::default();
+        settings.apply_env(&env).expect("未知的环境变量不应导致加载失败");
+        assert_eq!(settings.server.bind, "127.0.0.1:9443");
+        assert_eq!(settings.ignored_env, vec!["WEB_CONCURRENCY".to_string()]);
+    }
+
+    #[test]
+    fn load_from_layers_file_env_and_cli() {
+        let path = std::env::temp_dir().join(format!("settings-layers-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "app_name = \"from-file\"\n[server]\nbind = \"127.0.0.1:9001\"\nworkers = 2\nbacklog = 64\n",
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from(["web_learning", "--config", path.to_str().unwrap(), "--bind", "127.0.0.1:9003"]);
+        let env = vec![
+            ("WEB_BIND".to_string(), "127.0.0.1:9002".to_string()),
+            ("WEB_WORKERS".to_string(), "3".to_string()),
+            ("WEB_UNRELATED".to_string(), "1".to_string()),
+            // 没有前缀的变量不会被读取
+            ("BACKLOG".to_string(), "1".to_string()),
+        ];
+        let settings = Settings::load_from(&cli, env);
+        std::fs::remove_file(&path).unwrap();
+        let settings = settings.unwrap();
+
+        // 文件 < 环境变量 < 命令行参数，未覆盖的项保留文件中的值
+        assert_eq!(settings.app_name, "from-file");
+        assert_eq!(settings.server.backlog, 64);
+        assert_eq!(settings.server.workers, 3);
+        assert_eq!(settings.server.bind, "127.0.0.1:9003");
+        assert_eq!(settings.ignored_env, vec!["WEB_UNRELATED".to_string()]);
+    }
+
+    #[test]
+    fn client_auth_is_read_from_env_and_cli() {
+        let env = vec![("WEB_TLS_CLIENT_AUTH".to_string(), "Optional".to_string())];
+        let mut settings = Settings::default();
+        settings.apply_env(&env).unwrap();
+        assert_eq!(settings.tls.client_auth, ClientAuth::Optional);
+
+        settings.apply_cli(&Cli::parse_from(["web_learning", "--tls-client-auth", "require"]));
+        assert_eq!(settings.tls.client_auth, ClientAuth::Require);
+        assert!(Cli::try_parse_from(["web_learning", "--tls-client-auth", "always"]).is_err());
+    }
+
+    #[test]
+    fn invalid_known_env_var_is_rejected() {
+        let env = vec![("WEB_WORKERS".to_string(), "many".to_string())];
+        let err = Settings::default().apply_env(&env).unwrap_err();
+        assert!(matches!(err, SettingsError::Env { ref key, .. } if key == "WEB_WORKERS"), "{err}");
+    }
+}
@@ -0,0 +1,249 @@
+// 标准库导入
+use std::process::ExitCode;              // 进程退出状态
+use std::sync::Arc;                      // 共享的组件
+use std::time::Duration;                 // 等待时间
+
+// 外部库导入
+use actix_web::dev::ServerHandle;        // 控制各个监听器的服务器
+use futures::future::join_all;           // 同时控制所有监听器
+use tokio::time::Instant;                // 排空的截止时间
+
+// 内部模块导入
+use crate::counters::CounterStore;       // 停止前写入快照
+use crate::events::EventHub;             // 结束SSE流
+use crate::health::Health;               // 标记未就绪
+use crate::metrics::Metrics;             // 正在处理的请求数和SSE流数
+use crate::settings::Settings;           // 等待时间配置
+
+/// 检查是否已经排空的间隔
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 停止的结果
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownReport {
+    pub drained: bool,         // 截止时间内所有请求和SSE流是否都已结束
+    pub snapshot_saved: bool,  // 计数器快照是否写入成功
+}
+
+impl ShutdownReport {
+    /// 进程退出状态：完全排空且快照写入成功时为0，否则为1
+    pub fn exit_code(&self) -> ExitCode {
+        if self.drained && self.snapshot_saved {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// 优雅停止协调器
+///
+/// 收到SIGTERM或SIGINT后依次：
+/// 1. 把服务标记为未就绪，`/readyz`返回503，等待`health.unready_delay_secs`让负载均衡摘除流量
+/// 2. 所有监听器停止接受新连接，已有连接上的请求继续处理
+/// 3. 向所有SSE订阅者发送`event: shutdown`并结束这些流
+/// 4. 等待正在处理的请求结束，最多等待`server.shutdown_timeout_secs`
+/// 5. 关闭所有连接和工作线程，写入计数器快照
+///
+/// 没有在截止时间内排空或快照写入失败时，退出状态为失败，便于部署系统发现被中断的请求
+pub struct ShutdownCoordinator {
+    handles: Vec<ServerHandle>,      // 各个监听器的服务器
+    health: Arc<Health>,             // 健康检查
+    event_hub: Arc<EventHub>,        // 事件总线
+    metrics: Arc<Metrics>,           // 服务器指标
+    counters: Arc<CounterStore>,     // 命名计数器
+    unready_delay: Duration,         // 标记未就绪后继续接受连接的时间
+    drain_timeout: Duration,         // 等待请求和SSE流结束的期限
+}
+
+impl ShutdownCoordinator {
+    /// 创建协调器
+    ///
+    /// # 参数
+    /// * `handles` - 各个监听器的服务器
+    /// * `health` - 健康检查
+    /// * `event_hub` - 事件总线
+    /// * `metrics` - 服务器指标
+    /// * `counters` - 命名计数器
+    /// * `settings` - 服务器配置
+    pub fn new(
+        handles: Vec<ServerHandle>,
+        health: Arc<Health>,
+        event_hub: Arc<EventHub>,
+        metrics: Arc<Metrics>,
+        counters: Arc<CounterStore>,
+        settings: &Settings,
+    ) -> Self {
+        ShutdownCoordinator {
+            handles,
+            health,
+            event_hub,
+            metrics,
+            counters,
+            unready_delay: settings.health.unready_delay(),
+            drain_timeout: Duration::from_secs(settings.server.shutdown_timeout_secs),
+        }
+    }
+
+    /// 等待停止信号并执行停止流程
+    pub async fn run(self) -> ShutdownReport {
+        wait_for_signal().await;
+        self.shut_down().await
+    }
+
+    /// 执行停止流程
+    async fn shut_down(self) -> ShutdownReport {
+        self.health.begin_shutdown();
+        log::info!("收到停止信号，/readyz开始返回503，{:?}后停止接受连接", self.unready_delay);
+        tokio::time::sleep(self.unready_delay).await;
+
+        join_all(self.handles.iter().map(|handle| handle.pause())).await;
+        log::info!(
+            "已停止接受连接，等待{}个请求和{}个SSE流结束，最多等待{:?}",
+            self.metrics.in_flight(),
+            self.metrics.sse_streams(),
+            self.drain_timeout
+        );
+        self.event_hub.close();
+
+        let drained = self.drain().await;
+        if drained {
+            log::info!("所有请求已处理完成");
+        } else {
+            log::warn!(
+                "排空超时，强制关闭{}个请求和{}个SSE流",
+                self.metrics.in_flight(),
+                self.metrics.sse_streams()
+            );
+        }
+        join_all(self.handles.iter().map(|handle| handle.stop(false))).await;
+
+        let snapshot_saved = match self.counters.snapshot() {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("计数器快照写入失败: {}", err);
+                false
+            }
+        };
+        ShutdownReport { drained, snapshot_saved }
+    }
+
+    /// 等待正在处理的请求和SSE流全部结束
+    ///
+    /// # 返回值
+    /// * 截止时间内全部结束时返回true
+    async fn drain(&self) -> bool {
+        let deadline = Instant::now() + self.drain_timeout;
+        loop {
+            if self.metrics.in_flight() == 0 && self.metrics.sse_streams() == 0 {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// 等待停止信号：Ctrl-C（SIGINT），Unix下还包括SIGTERM
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(err) => log::warn!("无法监听SIGTERM，只处理Ctrl-C: {}", err),
+        }
+    }
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        log::error!("无法监听Ctrl-C: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App};
+    use futures::StreamExt;
+
+    use crate::metrics::{track_requests, track_sse_stream};
+
+    /// 没有监听器、不写快照的协调器
+    fn coordinator(metrics: Arc<Metrics>, event_hub: Arc<EventHub>, drain_timeout: Duration) -> ShutdownCoordinator {
+        let mut settings = Settings::default();
+        settings.counters.snapshot_file = None;
+        let mut coordinator = ShutdownCoordinator::new(
+            Vec::new(),
+            Arc::new(Health::new()),
+            event_hub,
+            metrics,
+            Arc::new(CounterStore::from_settings(&settings.counters).unwrap()),
+            &settings,
+        );
+        coordinator.drain_timeout = drain_timeout;
+        coordinator
+    }
+
+    #[actix_web::test]
+    async fn in_flight_requests_delay_drain_until_the_deadline() {
+        let metrics = Arc::new(Metrics::new());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::from(metrics.clone()))
+                .wrap(from_fn(track_requests))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+                        "done"
+                    }),
+                ),
+        )
+        .await;
+        let hub = Arc::new(EventHub::from_settings(&Default::default()));
+
+        // join!按顺序轮询，请求先进入处理函数，排空开始时已经计入
+        let started = Instant::now();
+        let shutdown = coordinator(metrics.clone(), hub.clone(), Duration::from_secs(5));
+        let (resp, report) =
+            futures::join!(test::call_service(&app, TestRequest::get().uri("/slow").to_request()), shutdown.shut_down());
+        assert!(resp.status().is_success());
+        assert!(report.drained);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(metrics.in_flight(), 0);
+
+        // 截止时间内没有处理完的请求使排空失败
+        let shutdown = coordinator(metrics.clone(), hub, Duration::from_millis(100));
+        let (_, report) =
+            futures::join!(test::call_service(&app, TestRequest::get().uri("/slow").to_request()), shutdown.shut_down());
+        assert!(!report.drained);
+        assert_eq!(report.exit_code(), ExitCode::FAILURE);
+    }
+
+    #[actix_web::test]
+    async fn sse_streams_end_on_shutdown() {
+        let metrics = Arc::new(Metrics::new());
+        let hub = Arc::new(EventHub::from_settings(&Default::default()));
+        let mut stream = Box::pin(track_sse_stream(metrics.clone(), hub.sse_stream(hub.subscribe("news", None))));
+        let preamble = stream.next().await.unwrap().unwrap();
+        assert!(preamble.starts_with(b"retry: "));
+        assert_eq!(metrics.sse_streams(), 1);
+
+        let shutdown = coordinator(metrics.clone(), hub.clone(), Duration::from_secs(5));
+        let (rest, report) = futures::join!(stream.collect::<Vec<_>>(), shutdown.shut_down());
+        let rest: Vec<_> = rest.into_iter().map(Result::unwrap).collect();
+        assert_eq!(rest.len(), 1, "{rest:?}");
+        assert!(rest[0].starts_with(b"event: shutdown\n"), "{rest:?}");
+        assert!(report.drained);
+        assert_eq!(metrics.sse_streams(), 0);
+    }
+}
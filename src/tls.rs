@@ -0,0 +1,788 @@
+// 标准库导入
+use std::any::Any;                       // 连接建立回调中的底层连接
+use std::future::{ready, Ready};         // 同步提取器的Future
+use std::net::IpAddr;                    // IP地址形式的SAN
+use std::path::{Path, PathBuf};          // 证书和私钥文件路径
+use std::sync::{Arc, Mutex, RwLock};     // 当前证书和文件状态
+use std::time::{Duration, SystemTime};   // 检查间隔和文件修改时间
+
+// 外部库导入
+use actix_tls::accept::openssl::TlsStream;  // TLS连接
+use actix_web::dev::{Extensions, Payload};  // 连接级别的扩展数据和请求体
+use actix_web::{FromRequest, HttpRequest};  // 提取器
+use derive_more::{Display, Error};       // 用于自动派生Display和Error trait
+use openssl::asn1::Asn1Time;             // 证书有效期
+use openssl::error::ErrorStack;          // OpenSSL错误
+use openssl::hash::MessageDigest;        // 证书指纹
+use openssl::pkey::{PKey, Private};      // 私钥
+use openssl::ssl::{NameType, SniError, SslAcceptor, SslAcceptorBuilder, SslMethod, SslRef, SslVerifyMode};  // TLS配置
+use openssl::x509::store::X509StoreBuilder;  // 客户端证书的信任库
+use openssl::x509::{X509NameRef, X509VerifyResult, X509};  // 证书
+use serde::Serialize;                    // 客户端身份的序列化
+use utoipa::ToSchema;                    // 用于生成OpenAPI文档
+use tokio::net::TcpStream;               // TLS连接的底层TCP连接
+
+// 内部模块导入
+use crate::errors::AuthError;            // 缺少客户端证书时的错误
+use crate::metrics::{self, Metrics};     // 统计TLS握手
+use crate::settings::{ClientAuth, TlsSettings};  // TLS证书配置
+
+/// TLS证书加载错误
+#[derive(Debug, Display, Error)]
+pub enum TlsError {
+    #[display(fmt = "无法读取{file}: {message}")]
+    /// 文件不存在或无法读取
+    Read { file: String, message: String },
+
+    #[display(fmt = "{file}中的内容无法解析: {message}")]
+    /// 文件不是合法的PEM证书或私钥
+    Parse { file: String, message: String },
+
+    #[display(fmt = "证书与私钥不匹配")]
+    /// 私钥不是证书公钥对应的私钥
+    KeyMismatch,
+
+    #[display(fmt = "证书不在有效期内（{not_before} 至 {not_after}）")]
+    /// 证书已过期或尚未生效，只在重新加载时拒绝
+    NotValid { not_before: String, not_after: String },
+
+    #[display(fmt = "OpenSSL错误: {_0}")]
+    /// 构建TLS配置失败
+    OpenSsl(#[error(not(source))] String),
+}
+
+impl From<ErrorStack> for TlsError {
+    fn from(err: ErrorStack) -> Self {
+        TlsError::OpenSsl(err.to_string())
+    }
+}
+
+/// 一组配套的证书链和私钥
+struct CertifiedKey {
+    cert: X509,         // 服务器证书
+    chain: Vec<X509>,   // 中间证书
+    key: PKey<Private>, // 私钥
+}
+
+impl CertifiedKey {
+    /// 读取并校验证书链文件和私钥文件
+    fn load(cert_file: &Path, key_file: &Path) -> Result<Self, TlsError> {
+        let mut certs = X509::stack_from_pem(&read(cert_file)?).map_err(|err| parse_error(cert_file, err))?;
+        if certs.is_empty() {
+            return Err(parse_error(cert_file, "文件中没有证书"));
+        }
+        let cert = certs.remove(0);
+        let key = PKey::private_key_from_pem(&read(key_file)?).map_err(|err| parse_error(key_file, err))?;
+
+        if !cert.public_key()?.public_eq(&key) {
+            return Err(TlsError::KeyMismatch);
+        }
+        Ok(CertifiedKey { cert, chain: certs, key })
+    }
+
+    /// 证书当前是否在有效期内
+    fn check_validity(&self) -> Result<(), TlsError> {
+        let now = Asn1Time::days_from_now(0)?;
+        if self.cert.not_before() > now || self.cert.not_after() < now {
+            return Err(TlsError::NotValid {
+                not_before: self.cert.not_before().to_string(),
+                not_after: self.cert.not_after().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 证书主题，用于日志
+    fn subject(&self) -> String {
+        name_to_string(self.cert.subject_name())
+    }
+
+    /// 为一个连接设置证书和私钥
+    fn apply(&self, ssl: &mut SslRef) -> Result<(), ErrorStack> {
+        ssl.set_certificate(&self.cert)?;
+        ssl.set_private_key(&self.key)?;
+        for cert in &self.chain {
+            ssl.add_chain_cert(cert.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// 文件的修改时间和大小，用于判断文件是否变化
+type FileStamp = Option<(SystemTime, u64)>;
+
+/// 可热更新的服务器证书
+///
+/// 证书和私钥在每个连接收到ClientHello时设置，替换证书只影响之后的新连接，已有连接不受影响
+/// 定期检查文件是否变化，Unix下收到SIGHUP时也会重新加载；
+/// 新的证书无法读取、与私钥不匹配或不在有效期内时拒绝替换，继续使用原有证书并记录原因
+pub struct CertReloader {
+    cert_file: PathBuf,                   // 证书链文件
+    key_file: PathBuf,                    // 私钥文件
+    reload_interval: Duration,            // 检查文件变化的间隔，0表示不检查
+    current: RwLock<Arc<CertifiedKey>>,   // 当前使用的证书和私钥
+    stamps: Mutex<[FileStamp; 2]>,        // 上次检查时证书和私钥文件的状态
+}
+
+impl CertReloader {
+    /// 根据配置加载证书
+    ///
+    /// 启动时证书不在有效期内只记录警告，不拒绝启动，由就绪检查报告
+    ///
+    /// # 返回值
+    /// * 文件无法读取、格式错误或证书与私钥不匹配时返回TlsError
+    pub fn from_settings(settings: &TlsSettings) -> Result<Self, TlsError> {
+        let stamps = [stamp(&settings.cert_file), stamp(&settings.key_file)];
+        let certified = CertifiedKey::load(&settings.cert_file, &settings.key_file)?;
+        if let Err(err) = certified.check_validity() {
+            log::warn!("TLS证书{}: {}", settings.cert_file.display(), err);
+        }
+
+        Ok(CertReloader {
+            cert_file: settings.cert_file.clone(),
+            key_file: settings.key_file.clone(),
+            reload_interval: Duration::from_secs(settings.reload_interval_secs),
+            current: RwLock::new(Arc::new(certified)),
+            stamps: Mutex::new(stamps),
+        })
+    }
+
+    /// 当前使用的服务器证书
+    pub fn certificate(&self) -> X509 {
+        self.current().cert.clone()
+    }
+
+    /// 重新加载证书，校验不通过时保留原有证书
+    pub fn reload(&self) -> Result<(), TlsError> {
+        let result = CertifiedKey::load(&self.cert_file, &self.key_file).and_then(|certified| {
+            certified.check_validity()?;
+            Ok(certified)
+        });
+
+        match result {
+            Ok(certified) => {
+                log::info!(
+                    "已重新加载TLS证书，主题: {}，有效期至: {}",
+                    certified.subject(),
+                    certified.cert.not_after()
+                );
+                *self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(certified);
+                Ok(())
+            }
+            Err(err) => {
+                log::error!("TLS证书重新加载失败，继续使用原有证书: {}", err);
+                Err(err)
+            }
+        }
+    }
+
+    /// 监视证书文件和SIGHUP，直到进程退出
+    pub async fn watch(self: Arc<Self>) {
+        let mut ticker = (!self.reload_interval.is_zero()).then(|| tokio::time::interval(self.reload_interval));
+        let mut hangup = HangupSignal::new();
+
+        loop {
+            let forced = tokio::select! {
+                _ = hangup.recv() => true,
+                _ = tick(&mut ticker) => false,
+            };
+
+            let changed = self.files_changed();
+            if forced {
+                log::info!("收到SIGHUP，重新加载TLS证书");
+            }
+            if forced || changed {
+                // 失败原因已经记录到日志中
+                let _ = self.reload();
+            }
+        }
+    }
+
+    /// 证书或私钥文件自上次检查以来是否变化
+    ///
+    /// 证书和私钥分开写入时可能先看到不匹配的一对，被拒绝后等另一个文件写完再次变化时会重试
+    fn files_changed(&self) -> bool {
+        let now = [stamp(&self.cert_file), stamp(&self.key_file)];
+        let mut stamps = self.stamps.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let changed = *stamps != now;
+        *stamps = now;
+        changed
+    }
+
+    /// 获取当前的证书和私钥
+    fn current(&self) -> Arc<CertifiedKey> {
+        self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+/// 客户端在TLS握手时通过SNI请求的主机名
+///
+/// 连接建立时保存到连接的扩展数据中，虚拟主机中间件用它检查请求的Host是否属于同一个虚拟主机
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsServerName(pub String);
+
+/// 经过校验的客户端证书（mTLS）
+///
+/// 握手时由OpenSSL按`tls.client_ca_file`校验，连接建立时保存到连接的扩展数据中，
+/// 同一连接上的所有请求共享；处理函数通过提取器获取，按身份做服务间的授权
+/// 客户端没有提供证书时提取失败并返回401，可以用`Option<ClientCertificate>`接受匿名客户端
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClientCertificate {
+    pub subject: String,      // 主题，例如"CN=billing, O=Example"
+    pub issuer: String,       // 签发者
+    pub sans: Vec<String>,    // 主题备用名称，例如"DNS:billing.internal"、"URI:spiffe://example/billing"
+    pub serial: String,       // 序列号（十六进制）
+    pub not_after: String,    // 有效期截止时间
+    pub fingerprint: String,  // 证书的SHA-256指纹，冒号分隔的大写十六进制
+}
+
+impl ClientCertificate {
+    /// 从证书中提取身份信息
+    fn from_x509(cert: &X509) -> Result<Self, ErrorStack> {
+        let sans = cert
+            .subject_alt_names()
+            .map(|names| names.iter().filter_map(general_name_to_string).collect())
+            .unwrap_or_default();
+        let fingerprint = cert
+            .digest(MessageDigest::sha256())?
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        Ok(ClientCertificate {
+            subject: name_to_string(cert.subject_name()),
+            issuer: name_to_string(cert.issuer_name()),
+            sans,
+            serial: cert.serial_number().to_bn()?.to_hex_str()?.to_string(),
+            not_after: cert.not_after().to_string(),
+            fingerprint,
+        })
+    }
+
+    /// 主题中的通用名称（CN）
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject
+            .split(", ")
+            .find_map(|entry| entry.strip_prefix("CN="))
+    }
+
+    /// 证书是否代表给定的身份
+    ///
+    /// # 参数
+    /// * `identity` - 通用名称、带类型前缀的SAN（例如"DNS:billing.internal"）或证书指纹
+    pub fn has_identity(&self, identity: &str) -> bool {
+        self.common_name() == Some(identity)
+            || self.sans.iter().any(|san| san == identity)
+            || self.fingerprint.eq_ignore_ascii_case(identity)
+    }
+}
+
+impl FromRequest for ClientCertificate {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.conn_data::<ClientCertificate>()
+                .cloned()
+                .ok_or(AuthError::ClientCertificateRequired),
+        )
+    }
+}
+
+/// 创建TLS配置
+///
+/// 使用Mozilla推荐的中间安全级别配置；证书不放在配置上，而是在servername回调中
+/// 为每个连接设置当前的证书，这样替换证书时不需要重建配置，也不会丢失actix-web设置的ALPN
+/// 启用mTLS时按`client_auth`请求或要求客户端证书，并用`client_ca_file`中的CA校验
+///
+/// # 参数
+/// * `reloader` - 可热更新的服务器证书
+/// * `settings` - TLS配置
+/// * `metrics` - 服务器指标，用于统计TLS握手
+pub fn acceptor(
+    reloader: Arc<CertReloader>,
+    settings: &TlsSettings,
+    metrics: Arc<Metrics>,
+) -> Result<SslAcceptorBuilder, TlsError> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+
+    // OpenSSL 1.1.1起，无论客户端是否发送SNI，每次握手都会调用该回调
+    builder.set_servername_callback(move |ssl, _alert| {
+        metrics::on_client_hello(&metrics, ssl);
+        reloader.current().apply(ssl).map_err(|err| {
+            log::error!("无法为连接设置TLS证书: {}", err);
+            SniError::ALERT_FATAL
+        })
+    });
+
+    let mode = match settings.client_auth {
+        ClientAuth::Off => return Ok(builder),
+        ClientAuth::Optional => SslVerifyMode::PEER,
+        ClientAuth::Require => SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+    };
+    // 配置已经过`Settings::validate`校验，启用mTLS时一定设置了CA文件
+    let ca_file = settings.client_ca_file.as_deref().unwrap_or(Path::new(""));
+    let cas = X509::stack_from_pem(&read(ca_file)?).map_err(|err| parse_error(ca_file, err))?;
+    if cas.is_empty() {
+        return Err(parse_error(ca_file, "文件中没有证书"));
+    }
+
+    let mut store = X509StoreBuilder::new()?;
+    for ca in &cas {
+        store.add_cert(ca.clone())?;
+        // 在CertificateRequest中告知客户端可接受的CA，便于客户端选择证书
+        builder.add_client_ca(ca)?;
+    }
+    builder.set_verify_cert_store(store.build())?;
+    builder.set_verify(mode);
+    // 校验客户端证书时会话恢复需要会话ID上下文，否则恢复会话的握手会失败
+    builder.set_session_id_context(b"web_learning")?;
+    log::info!("已启用客户端证书认证（{:?}），信任{}张CA证书", settings.client_auth, cas.len());
+    Ok(builder)
+}
+
+/// 连接建立回调，用于`HttpServer::on_connect`
+///
+/// 统计完成的TLS握手，保存客户端通过SNI请求的主机名；客户端提供了通过校验的证书时，
+/// 把身份信息保存到连接的扩展数据中，供`ClientCertificate`提取器使用
+///
+/// # 参数
+/// * `conn` - 底层连接
+/// * `ext` - 连接级别的扩展数据
+pub fn on_connect(conn: &dyn Any, ext: &mut Extensions) {
+    metrics::on_tls_connect(conn, ext);
+
+    let Some(stream) = conn.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    let ssl = stream.ssl();
+    if let Some(server_name) = ssl.servername(NameType::HOST_NAME) {
+        ext.insert(TlsServerName(server_name.to_ascii_lowercase()));
+    }
+    if let Some(identity) = peer_identity(ssl) {
+        ext.insert(identity);
+    }
+}
+
+/// 读取连接中通过校验的客户端证书的身份信息，没有证书或未通过校验时为None
+fn peer_identity(ssl: &SslRef) -> Option<ClientCertificate> {
+    let cert = ssl.peer_certificate()?;
+    if ssl.verify_result() != X509VerifyResult::OK {
+        return None;
+    }
+    ClientCertificate::from_x509(&cert)
+        .map_err(|err| log::warn!("无法读取客户端证书: {}", err))
+        .ok()
+}
+
+/// 读取文件
+fn read(path: &Path) -> Result<Vec<u8>, TlsError> {
+    std::fs::read(path).map_err(|err| TlsError::Read {
+        file: path.display().to_string(),
+        message: err.to_string(),
+    })
+}
+
+/// 文件内容无法解析的错误
+fn parse_error(path: &Path, message: impl ToString) -> TlsError {
+    TlsError::Parse {
+        file: path.display().to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// 把证书的主题或签发者格式化为"CN=..., O=..."
+fn name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let field = entry.object().nid().short_name().unwrap_or("?");
+            let value = entry.data().to_string().unwrap_or_default();
+            format!("{field}={value}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 把一个主题备用名称格式化为带类型前缀的字符串，不支持的类型忽略
+fn general_name_to_string(name: &openssl::x509::GeneralNameRef) -> Option<String> {
+    if let Some(dns) = name.dnsname() {
+        return Some(format!("DNS:{dns}"));
+    }
+    if let Some(uri) = name.uri() {
+        return Some(format!("URI:{uri}"));
+    }
+    if let Some(email) = name.email() {
+        return Some(format!("email:{email}"));
+    }
+    let ip = match name.ipaddress()? {
+        [a, b, c, d] => IpAddr::from([*a, *b, *c, *d]),
+        bytes => IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?),
+    };
+    Some(format!("IP:{ip}"))
+}
+
+/// 读取文件的修改时间和大小，文件不存在时为None
+fn stamp(path: &Path) -> FileStamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// 等待下一次检查，没有设置检查间隔时永远等待
+async fn tick(ticker: &mut Option<tokio::time::Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// SIGHUP信号，非Unix平台或无法监听时永远等待
+struct HangupSignal {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,  // SIGHUP监听器
+}
+
+impl HangupSignal {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let signal = signal(SignalKind::hangup())
+                .map_err(|err| log::warn!("无法监听SIGHUP，只通过检查文件变化重新加载证书: {}", err))
+                .ok();
+            HangupSignal { signal }
+        }
+        #[cfg(not(unix))]
+        {
+            HangupSignal {}
+        }
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            signal.recv().await;
+            return;
+        }
+        std::future::pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::bn::BigNum;
+    use openssl::ssl::SslConnector;
+    use openssl::x509::extension::{BasicConstraints, KeyUsage, SubjectAlternativeName};
+    use openssl::x509::{X509Builder, X509NameBuilder};
+
+    /// 每个测试使用自己的证书目录，测试结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("tls-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn settings(&self) -> TlsSettings {
+            TlsSettings {
+                cert_file: self.0.join("cert.pem"),
+                key_file: self.0.join("key.pem"),
+                ..TlsSettings::default()
+            }
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn new_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    /// 生成自签名证书，有效期为`not_before`到`not_after`
+    fn self_signed(cn: &str, key: &PKey<Private>, not_before: &Asn1Time, not_after: &Asn1Time) -> X509 {
+        let mut builder = unsigned(cn, key, not_before, not_after);
+        builder.sign(key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    /// 主题和签发者都是`cn`、尚未签名的证书
+    fn unsigned(cn: &str, key: &PKey<Private>, not_before: &Asn1Time, not_after: &Asn1Time) -> X509Builder {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder.set_not_before(not_before).unwrap();
+        builder.set_not_after(not_after).unwrap();
+        builder
+    }
+
+    fn valid(cn: &str, key: &PKey<Private>) -> X509 {
+        self_signed(cn, key, &Asn1Time::days_from_now(0).unwrap(), &Asn1Time::days_from_now(30).unwrap())
+    }
+
+    /// 签发客户端证书的CA
+    struct Ca {
+        cert: X509,
+        key: PKey<Private>,
+    }
+
+    impl Ca {
+        fn new(cn: &str) -> Self {
+            let key = new_key();
+            let mut builder =
+                unsigned(cn, &key, &Asn1Time::days_from_now(0).unwrap(), &Asn1Time::days_from_now(30).unwrap());
+            builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+            builder.append_extension(KeyUsage::new().critical().key_cert_sign().build().unwrap()).unwrap();
+            builder.sign(&key, MessageDigest::sha256()).unwrap();
+            Ca { cert: builder.build(), key }
+        }
+
+        /// 签发主题为"CN=billing, O=Example"、带DNS、IP和email三种SAN的客户端证书
+        fn issue(&self, key: &PKey<Private>) -> X509 {
+            let mut name = X509NameBuilder::new().unwrap();
+            name.append_entry_by_text("CN", "billing").unwrap();
+            name.append_entry_by_text("O", "Example").unwrap();
+            let name = name.build();
+
+            let mut builder = X509Builder::new().unwrap();
+            builder.set_version(2).unwrap();
+            builder.set_serial_number(&BigNum::from_u32(0x1234).unwrap().to_asn1_integer().unwrap()).unwrap();
+            builder.set_subject_name(&name).unwrap();
+            builder.set_issuer_name(self.cert.subject_name()).unwrap();
+            builder.set_pubkey(key).unwrap();
+            builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+            builder.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+            let san = SubjectAlternativeName::new()
+                .dns("billing.internal")
+                .ip("10.0.0.7")
+                .email("ops@example.com")
+                .build(&builder.x509v3_context(Some(&self.cert), None))
+                .unwrap();
+            builder.append_extension(san).unwrap();
+            builder.sign(&self.key, MessageDigest::sha256()).unwrap();
+            builder.build()
+        }
+    }
+
+    fn write(settings: &TlsSettings, cert: &X509, key: &PKey<Private>) {
+        std::fs::write(&settings.cert_file, cert.to_pem().unwrap()).unwrap();
+        std::fs::write(&settings.key_file, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+    }
+
+    fn common_name(cert: &X509) -> String {
+        let entry = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next().unwrap();
+        entry.data().to_string().unwrap()
+    }
+
+    /// 用`acceptor`完成一次TLS握手，返回服务器出示的证书
+    fn served_certificate(acceptor: &SslAcceptor) -> X509 {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = acceptor.clone();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let _ = acceptor.accept(stream);
+        });
+
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let tls = connector.build().connect("localhost", stream).unwrap();
+        let cert = tls.ssl().peer_certificate().unwrap();
+        drop(tls);
+        server.join().unwrap();
+        cert
+    }
+
+    #[test]
+    fn reload_replaces_the_certificate() {
+        let dir = TempDir::new("replace");
+        let settings = dir.settings();
+        let key = new_key();
+        write(&settings, &valid("old", &key), &key);
+        let reloader = CertReloader::from_settings(&settings).unwrap();
+        assert!(!reloader.files_changed());
+
+        let key = new_key();
+        write(&settings, &valid("new", &key), &key);
+        reloader.reload().unwrap();
+        assert_eq!(common_name(&reloader.certificate()), "new");
+    }
+
+    #[test]
+    fn mismatched_pair_is_rejected_and_old_acceptor_stays_live() {
+        let dir = TempDir::new("mismatch");
+        let settings = dir.settings();
+        let key = new_key();
+        write(&settings, &valid("old", &key), &key);
+        let reloader = Arc::new(CertReloader::from_settings(&settings).unwrap());
+        let acceptor = acceptor(reloader.clone(), &settings, Arc::new(Metrics::new())).unwrap().build();
+        assert_eq!(common_name(&served_certificate(&acceptor)), "old");
+
+        // 新证书配上另一把私钥
+        write(&settings, &valid("new", &new_key()), &new_key());
+        assert!(matches!(reloader.reload(), Err(TlsError::KeyMismatch)));
+        assert_eq!(common_name(&reloader.certificate()), "old");
+        assert_eq!(common_name(&served_certificate(&acceptor)), "old");
+    }
+
+    #[test]
+    fn unreadable_or_expired_certificates_are_rejected() {
+        let dir = TempDir::new("invalid");
+        let settings = dir.settings();
+        let key = new_key();
+        write(&settings, &valid("old", &key), &key);
+        let reloader = CertReloader::from_settings(&settings).unwrap();
+
+        std::fs::write(&settings.cert_file, "not a certificate").unwrap();
+        assert!(matches!(reloader.reload(), Err(TlsError::Parse { .. })));
+
+        std::fs::remove_file(&settings.cert_file).unwrap();
+        assert!(matches!(reloader.reload(), Err(TlsError::Read { .. })));
+
+        let expired = self_signed(
+            "expired",
+            &key,
+            &Asn1Time::from_unix(0).unwrap(),
+            &Asn1Time::from_unix(86_400).unwrap(),
+        );
+        write(&settings, &expired, &key);
+        assert!(matches!(reloader.reload(), Err(TlsError::NotValid { .. })));
+
+        assert_eq!(common_name(&reloader.certificate()), "old");
+    }
+
+    /// 启用mTLS的服务器配置，信任`ca`签发的客户端证书
+    fn mtls_acceptor(dir: &TempDir, ca: &Ca, client_auth: ClientAuth) -> SslAcceptor {
+        let mut settings = dir.settings();
+        let key = new_key();
+        write(&settings, &valid("localhost", &key), &key);
+        let ca_file = dir.0.join("ca.pem");
+        std::fs::write(&ca_file, ca.cert.to_pem().unwrap()).unwrap();
+        settings.client_auth = client_auth;
+        settings.client_ca_file = Some(ca_file);
+
+        let reloader = Arc::new(CertReloader::from_settings(&settings).unwrap());
+        acceptor(reloader, &settings, Arc::new(Metrics::new())).unwrap().build()
+    }
+
+    /// 用`acceptor`完成一次TLS握手，客户端可以出示证书
+    ///
+    /// # 返回值
+    /// 服务器端握手失败时为Err，否则为服务器从连接中读取到的客户端身份
+    fn client_handshake(
+        acceptor: &SslAcceptor,
+        client: Option<(&X509, &PKey<Private>)>,
+    ) -> Result<Option<ClientCertificate>, ()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = acceptor.clone();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            acceptor.accept(stream).map(|tls| peer_identity(tls.ssl())).map_err(|_| ())
+        });
+
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        if let Some((cert, key)) = client {
+            connector.set_certificate(cert).unwrap();
+            connector.set_private_key(key).unwrap();
+        }
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        // TLS 1.3中客户端在服务器校验证书之前就完成了握手，结果以服务器端为准
+        let tls = connector.build().connect("localhost", stream);
+        let result = server.join().unwrap();
+        drop(tls);
+        result
+    }
+
+    #[test]
+    fn identity_is_read_from_subject_and_sans() {
+        let ca = Ca::new("Example CA");
+        let cert = ca.issue(&new_key());
+        let identity = ClientCertificate::from_x509(&cert).unwrap();
+
+        assert_eq!(identity.subject, "CN=billing, O=Example");
+        assert_eq!(identity.issuer, "CN=Example CA");
+        assert_eq!(identity.common_name(), Some("billing"));
+        assert_eq!(identity.sans, ["DNS:billing.internal", "IP:10.0.0.7", "email:ops@example.com"]);
+        assert_eq!(identity.serial, "1234");
+        assert_eq!(identity.fingerprint.len(), 32 * 3 - 1);
+
+        for name in ["billing", "DNS:billing.internal", "IP:10.0.0.7", "email:ops@example.com"] {
+            assert!(identity.has_identity(name), "{name}");
+        }
+        assert!(identity.has_identity(&identity.fingerprint.to_ascii_lowercase()));
+        // SAN必须带类型前缀，主题中的其他字段不算身份
+        for name in ["billing.internal", "Example", "DNS:other.internal"] {
+            assert!(!identity.has_identity(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn required_client_auth_rejects_missing_or_untrusted_certificates() {
+        let dir = TempDir::new("mtls-require");
+        let ca = Ca::new("Example CA");
+        let acceptor = mtls_acceptor(&dir, &ca, ClientAuth::Require);
+
+        let key = new_key();
+        let cert = ca.issue(&key);
+        let identity = client_handshake(&acceptor, Some((&cert, &key))).unwrap().unwrap();
+        assert_eq!(identity.common_name(), Some("billing"));
+
+        assert!(client_handshake(&acceptor, None).is_err());
+        let untrusted = Ca::new("Other CA").issue(&key);
+        assert!(client_handshake(&acceptor, Some((&untrusted, &key))).is_err());
+    }
+
+    #[test]
+    fn optional_client_auth_allows_anonymous_clients() {
+        let dir = TempDir::new("mtls-optional");
+        let ca = Ca::new("Example CA");
+        let acceptor = mtls_acceptor(&dir, &ca, ClientAuth::Optional);
+
+        assert!(matches!(client_handshake(&acceptor, None), Ok(None)));
+
+        let key = new_key();
+        let cert = ca.issue(&key);
+        let identity = client_handshake(&acceptor, Some((&cert, &key))).unwrap().unwrap();
+        assert_eq!(identity.common_name(), Some("billing"));
+
+        // 提供了证书就必须通过校验
+        let untrusted = Ca::new("Other CA").issue(&key);
+        assert!(client_handshake(&acceptor, Some((&untrusted, &key))).is_err());
+    }
+
+    #[test]
+    fn file_changes_are_detected_once() {
+        let dir = TempDir::new("stamps");
+        let settings = dir.settings();
+        let key = new_key();
+        write(&settings, &valid("old", &key), &key);
+        let reloader = CertReloader::from_settings(&settings).unwrap();
+
+        // 写入不同长度的内容，不依赖文件系统的时间精度
+        std::fs::write(&settings.cert_file, "changed").unwrap();
+        assert!(reloader.files_changed());
+        assert!(!reloader.files_changed());
+    }
+}
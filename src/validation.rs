@@ -0,0 +1,141 @@
+// 标准库导入
+use std::future::Future;  // 提取器返回的Future
+use std::ops::Deref;      // 让包装类型可以像内部值一样使用
+use std::pin::Pin;        // 用于装箱的Future
+
+// 外部库导入
+use actix_web::dev::Payload;                 // 请求体载荷
+use actix_web::{web, FromRequest, HttpRequest};  // 提取器相关类型
+use serde::de::DeserializeOwned;             // 反序列化约束
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};  // 声明式校验
+
+// 内部模块导入
+use crate::errors::{FieldViolation, UserError};  // 验证错误类型
+use crate::negotiation::Negotiated;              // 内容协商请求体提取器
+
+/// 将validator的校验结果转换为UserError::ValidationError
+///
+/// 嵌套结构体和列表中的字段以"."连接成路径（列表下标作为一段），
+/// 结构体级别的规则如果带有`field`参数，则归到该字段下
+impl From<ValidationErrors> for UserError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut violations = Vec::new();
+        collect_violations(&errors, "", &mut violations);
+        // HashMap的遍历顺序不固定，按字段排序以得到稳定的输出
+        violations.sort_by(|a, b| a.field.cmp(&b.field).then_with(|| a.rule.cmp(&b.rule)));
+
+        let mut fields: Vec<&str> = violations.iter().map(|v| v.field.as_str()).collect();
+        fields.dedup();
+        UserError::ValidationError { field: fields.join(", "), violations }
+    }
+}
+
+/// 递归收集所有字段的校验失败记录
+fn collect_violations(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldViolation>) {
+    let path = |name: &str| {
+        if prefix.is_empty() { name.to_string() } else { format!("{prefix}.{name}") }
+    };
+
+    for (name, kind) in errors.errors() {
+        match kind {
+            ValidationErrorsKind::Field(list) => {
+                for error in list {
+                    let mut params: serde_json::Map<_, _> = error
+                        .params
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.clone()))
+                        .collect();
+                    // 不回显客户端提交的值，避免把密码等内容写进响应
+                    params.remove("value");
+                    // 结构体级别的规则用field参数指明所属字段
+                    let field = match params.remove("field") {
+                        Some(serde_json::Value::String(field)) if name == "__all__" => path(&field),
+                        _ => path(name),
+                    };
+                    out.push(FieldViolation { field, rule: error.code.to_string(), params });
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => collect_violations(nested, &path(name), out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_violations(nested, &path(&format!("{name}.{index}")), out);
+                }
+            }
+        }
+    }
+}
+
+/// 生成校验型提取器
+///
+/// 先用对应的提取器解析请求（解析失败时沿用原有的错误和配置，
+/// 例如JsonConfig的错误处理），再执行声明在结构体上的校验规则，
+/// 校验失败时一次性返回所有失败的字段
+macro_rules! validated_extractor {
+    ($(#[$doc:meta])* $name:ident, $($inner:ident)::+) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name<T>(pub T);
+
+        impl<T> $name<T> {
+            /// 取出内部值
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> FromRequest for $name<T>
+        where
+            T: DeserializeOwned + Validate + 'static,
+        {
+            type Error = actix_web::Error;
+            type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+            fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+                let extract = $($inner)::+::<T>::from_request(req, payload);
+                Box::pin(async move {
+                    let value = extract.await?.into_inner();
+                    value.validate().map_err(UserError::from)?;
+                    Ok($name(value))
+                })
+            }
+        }
+    };
+}
+
+validated_extractor!(
+    /// 带校验的JSON请求体提取器，对应`web::Json`
+    ValidatedJson,
+    web::Json
+);
+
+validated_extractor!(
+    /// 带校验的表单提取器，对应`web::Form`
+    ValidatedForm,
+    web::Form
+);
+
+validated_extractor!(
+    /// 带校验的查询参数提取器，对应`web::Query`
+    ValidatedQuery,
+    web::Query
+);
+
+validated_extractor!(
+    /// 带校验的路径参数提取器，对应`web::Path`
+    ValidatedPath,
+    web::Path
+);
+
+validated_extractor!(
+    /// 带校验的内容协商请求体提取器，对应`Negotiated`
+    ValidatedBody,
+    Negotiated
+);
@@ -0,0 +1,357 @@
+// 标准库导入
+use std::net::{IpAddr, SocketAddr};        // HTTPS监听器的地址
+
+// 外部库导入
+use actix_web::body::{BoxBody, MessageBody};  // 响应体
+use actix_web::dev::{ServiceRequest, ServiceResponse};  // 中间件的请求和响应类型
+use actix_web::guard::{self, Guard};       // 按虚拟主机选择路由的守卫
+use actix_web::http::header::{self, HeaderValue};  // Host头和固定响应的响应头
+use actix_web::http::StatusCode;           // 固定响应的状态码
+use actix_web::middleware::Next;           // 中间件链中的下一个服务
+use actix_web::{web, HttpMessage, HttpResponse};  // 共享状态、请求扩展和响应
+
+// 内部模块导入
+use crate::errors::MisdirectedRequest;     // SNI与Host不符的错误
+use crate::settings::{ListenerKind, Settings, StaticResponseSettings, VirtualHostsSettings};  // 监听器和虚拟主机配置
+use crate::tls::TlsServerName;             // 握手时的SNI
+
+/// 主机模式
+///
+/// * `example.com` - 只匹配该主机名
+/// * `*.example.com` - 匹配example.com的任意一级或多级子域名，不匹配example.com本身
+///
+/// 可以带`:端口`只匹配该端口，不带端口时匹配所有端口；主机名不区分大小写
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPattern {
+    host: String,        // 主机名；通配模式保存为".example.com"形式的后缀
+    wildcard: bool,      // 是否为通配模式
+    port: Option<u16>,   // 限定的端口
+}
+
+impl HostPattern {
+    /// 解析主机模式
+    ///
+    /// # 参数
+    /// * `pattern` - 配置中的主机模式
+    ///
+    /// # 返回值
+    /// * 成功时返回主机模式
+    /// * 格式不合法时返回错误描述
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let (host, port) = split_host_port(pattern.trim()).ok_or("格式错误")?;
+        let port = match port {
+            Some(port) => Some(port.parse::<u16>().ok().filter(|port| *port != 0).ok_or("的端口无效")?),
+            None => None,
+        };
+        let (wildcard, name) = match host.strip_prefix("*.") {
+            Some(name) => (true, name),
+            None => (false, host.as_str()),
+        };
+        let valid_label = |label: &str| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        // IPv6地址不按标签检查，通配模式不能用于IP地址
+        let is_ipv6 = !wildcard && name.parse::<std::net::Ipv6Addr>().is_ok();
+        if !is_ipv6 && !name.split('.').all(valid_label) {
+            return Err("的主机名无效，通配符只能作为第一个标签使用，例如*.example.com".to_string());
+        }
+        Ok(HostPattern {
+            host: if wildcard { format!(".{name}") } else { name.to_string() },
+            wildcard,
+            port,
+        })
+    }
+
+    /// 判断主机和端口是否匹配
+    ///
+    /// # 参数
+    /// * `host` - 已转换为小写的主机名
+    /// * `port` - 端口，请求中没有写明时为协议的默认端口
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        if self.port.is_some_and(|expected| expected != port) {
+            return false;
+        }
+        if self.wildcard {
+            host.len() > self.host.len() && host.ends_with(&self.host)
+        } else {
+            host == self.host
+        }
+    }
+}
+
+/// 虚拟主机的固定响应
+#[derive(Debug, Clone)]
+struct StaticResponse {
+    status: StatusCode,              // 状态码
+    content_type: HeaderValue,       // Content-Type
+    body: String,                    // 响应体
+    location: Option<HeaderValue>,   // Location头
+}
+
+impl StaticResponse {
+    /// 按配置创建，配置已经过校验，无法转换的取值使用默认值
+    fn from_settings(settings: &StaticResponseSettings) -> Self {
+        StaticResponse {
+            status: StatusCode::from_u16(settings.status).unwrap_or(StatusCode::OK),
+            content_type: HeaderValue::try_from(settings.content_type.as_str())
+                .unwrap_or(HeaderValue::from_static("text/plain; charset=utf-8")),
+            body: settings.body.clone(),
+            location: settings.location.as_deref().and_then(|location| HeaderValue::try_from(location).ok()),
+        }
+    }
+
+    /// 生成响应
+    fn respond(&self) -> HttpResponse {
+        let mut builder = HttpResponse::build(self.status);
+        builder.insert_header((header::CONTENT_TYPE, self.content_type.clone()));
+        if let Some(location) = &self.location {
+            builder.insert_header((header::LOCATION, location.clone()));
+        }
+        builder.body(self.body.clone())
+    }
+}
+
+/// 虚拟主机
+#[derive(Debug, Clone)]
+pub struct VirtualHost {
+    pub name: String,                   // 名称
+    patterns: Vec<HostPattern>,         // 主机模式
+    response: Option<StaticResponse>,   // 固定响应
+}
+
+/// 请求所属的虚拟主机名称
+///
+/// 由`virtual_hosts`中间件放入请求扩展，`virtual_host`守卫按它选择路由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualHostName(pub String);
+
+/// 虚拟主机表
+#[derive(Debug, Clone)]
+pub struct VirtualHosts {
+    strict_sni: bool,             // SNI与Host属于不同虚拟主机时是否拒绝
+    default_host: Option<usize>,  // 默认虚拟主机在表中的位置
+    hosts: Vec<VirtualHost>,      // 按配置顺序排列的虚拟主机
+}
+
+impl VirtualHosts {
+    /// 按配置创建虚拟主机表
+    ///
+    /// 配置已经过校验，无法解析的主机模式会被忽略
+    ///
+    /// # 参数
+    /// * `settings` - 虚拟主机配置
+    pub fn from_settings(settings: &VirtualHostsSettings) -> Self {
+        let hosts: Vec<VirtualHost> = settings
+            .hosts
+            .iter()
+            .map(|host| VirtualHost {
+                name: host.name.clone(),
+                patterns: host.patterns.iter().filter_map(|pattern| HostPattern::parse(pattern).ok()).collect(),
+                response: host.response.as_ref().map(StaticResponse::from_settings),
+            })
+            .collect();
+        let default_host = settings
+            .default_host
+            .as_ref()
+            .and_then(|name| hosts.iter().position(|host| &host.name == name));
+        VirtualHosts {
+            strict_sni: settings.strict_sni,
+            default_host,
+            hosts,
+        }
+    }
+
+    /// 查找主机模式匹配的虚拟主机，不使用默认主机
+    fn position(&self, host: &str, port: u16) -> Option<usize> {
+        let host = normalize(host);
+        self.hosts
+            .iter()
+            .position(|vhost| vhost.patterns.iter().any(|pattern| pattern.matches(&host, port)))
+    }
+
+    /// 查找请求所属的虚拟主机
+    ///
+    /// # 参数
+    /// * `host` - 请求的主机名
+    /// * `port` - 请求的端口
+    ///
+    /// # 返回值
+    /// * 按配置顺序第一个匹配的虚拟主机，都不匹配时返回默认主机
+    pub fn resolve(&self, host: &str, port: u16) -> Option<&VirtualHost> {
+        self.position(host, port).or(self.default_host).map(|index| &self.hosts[index])
+    }
+
+    /// 判断请求是否被发送到了错误的连接上
+    ///
+    /// SNI与Host相同，或者两者匹配同一个虚拟主机时（例如HTTP/2复用同一证书覆盖的连接）视为一致；
+    /// 这里不使用默认主机，否则任意两个未配置的主机都会被视为一致
+    ///
+    /// # 参数
+    /// * `server_name` - TLS握手时的SNI
+    /// * `host` - 请求的主机名
+    /// * `port` - 请求的端口
+    pub fn is_misdirected(&self, server_name: &str, host: &str, port: u16) -> bool {
+        if normalize(server_name) == normalize(host) {
+            return false;
+        }
+        match (self.position(server_name, port), self.position(host, port)) {
+            (Some(sni), Some(host)) => sni != host,
+            _ => true,
+        }
+    }
+}
+
+/// 明文HTTP重定向到HTTPS的目标
+///
+/// 只重定向到本服务器提供的主机：匹配虚拟主机表中的某个主机模式，或者是HTTPS监听器绑定的IP地址，
+/// 避免伪造的Host把客户端引到其他站点
+#[derive(Debug, Clone)]
+pub struct HttpsRedirect {
+    port: u16,                // 重定向的目标端口，即server.bind的端口
+    hosts: VirtualHosts,      // 虚拟主机表
+    addresses: Vec<IpAddr>,   // HTTPS监听器绑定的IP地址，不含0.0.0.0等未指定地址
+}
+
+impl HttpsRedirect {
+    /// 按配置创建
+    ///
+    /// # 参数
+    /// * `settings` - 应用配置，已经过校验，server.bind一定是合法的地址
+    pub fn from_settings(settings: &Settings) -> Self {
+        let port = settings.server.bind.parse::<SocketAddr>().map_or(443, |addr| addr.port());
+        let addresses = settings
+            .server
+            .all_listeners()
+            .iter()
+            .filter(|listener| listener.kind == ListenerKind::Https)
+            .filter_map(|listener| listener.bind.parse::<SocketAddr>().ok())
+            .map(|addr| addr.ip())
+            .filter(|ip| !ip.is_unspecified())
+            .collect();
+        HttpsRedirect {
+            port,
+            hosts: VirtualHosts::from_settings(&settings.virtual_hosts),
+            addresses,
+        }
+    }
+
+    /// 重定向的目标地址
+    ///
+    /// 保留主机名、路径和查询字符串，把端口换成HTTPS端口，443时省略
+    ///
+    /// # 参数
+    /// * `host` - 请求的主机，可以带端口
+    /// * `path` - 请求的路径和查询字符串
+    ///
+    /// # 返回值
+    /// * 主机属于本服务器时返回HTTPS地址，否则返回None
+    pub fn location(&self, host: &str, path: &str) -> Option<String> {
+        let (hostname, _) = split_host_port(host)?;
+        let ip = hostname.parse::<IpAddr>().ok();
+        let known = self.hosts.position(&hostname, self.port).is_some()
+            || ip.is_some_and(|ip| self.addresses.contains(&ip));
+        if !known {
+            return None;
+        }
+        let hostname = match ip {
+            Some(IpAddr::V6(_)) => format!("[{hostname}]"),
+            _ => hostname,
+        };
+        Some(match self.port {
+            443 => format!("https://{hostname}{path}"),
+            port => format!("https://{hostname}:{port}{path}"),
+        })
+    }
+}
+
+/// 主机名转换为小写并去掉末尾的点
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// 拆分主机和端口，支持`[::1]:8443`形式的IPv6地址
+///
+/// # 返回值
+/// * 返回(小写的主机名, 端口)，格式错误时返回None
+fn split_host_port(authority: &str) -> Option<(String, Option<&str>)> {
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        match rest {
+            "" => (host, None),
+            rest => (host, Some(rest.strip_prefix(':')?)),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    let host = normalize(host);
+    (!host.is_empty()).then_some((host, port))
+}
+
+/// 取出请求的主机名和端口
+///
+/// HTTP/2请求的`:authority`保存在URI中，优先使用；否则使用Host头。
+/// 没有写明端口时使用协议的默认端口
+fn request_host(req: &ServiceRequest) -> Option<(String, u16)> {
+    let authority = match req.uri().authority() {
+        Some(authority) => authority.as_str().to_string(),
+        None => req.headers().get(header::HOST)?.to_str().ok()?.to_string(),
+    };
+    let (host, port) = split_host_port(&authority)?;
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None if req.app_config().secure() => 443,
+        None => 80,
+    };
+    Some((host, port))
+}
+
+/// 虚拟主机中间件
+///
+/// 1. 开启`strict_sni`时，SNI与Host属于不同的虚拟主机则返回421 Misdirected Request
+/// 2. 按Host查找虚拟主机，配置了固定响应的直接返回，不进入路由
+/// 3. 否则把虚拟主机名称放入请求扩展，供`virtual_host`守卫使用
+pub async fn virtual_hosts(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let Some(table) = req.app_data::<web::Data<VirtualHosts>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let Some((host, port)) = request_host(&req) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    if table.strict_sni
+        && let Some(TlsServerName(server_name)) = req.conn_data::<TlsServerName>()
+        && table.is_misdirected(server_name, &host, port)
+    {
+        log::info!("SNI {} 与主机 {}:{} 不属于同一个虚拟主机", server_name, host, port);
+        return Ok(req.error_response(MisdirectedRequest { host }));
+    }
+
+    let Some(vhost) = table.resolve(&host, port) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    if let Some(response) = &vhost.response {
+        return Ok(req.into_response(response.respond()));
+    }
+    req.extensions_mut().insert(VirtualHostName(vhost.name.clone()));
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// 只匹配指定虚拟主机的守卫
+///
+/// 依赖`virtual_hosts`中间件解析出的虚拟主机名称，替代写死主机名的`guard::Host`
+///
+/// # 参数
+/// * `name` - 虚拟主机名称，与配置中的`name`相同
+pub fn virtual_host(name: &'static str) -> impl Guard {
+    guard::fn_guard(move |ctx| {
+        ctx.req_data()
+            .get::<VirtualHostName>()
+            .is_some_and(|VirtualHostName(resolved)| resolved == name)
+    })
+}
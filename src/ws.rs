@@ -0,0 +1,323 @@
+// 标准库导入
+use std::collections::{HashMap, HashSet};       // 连接表和房间成员表
+use std::sync::atomic::{AtomicU64, Ordering};   // 连接ID分配
+use std::sync::{Arc, Mutex};                    // 用于线程安全的共享状态
+use std::time::Instant;                         // 记录最后一次收到客户端消息的时间
+
+// 外部库导入
+use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};  // Web框架核心组件
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, CloseCode, CloseReason, Session};  // WebSocket
+use serde::{Deserialize, Serialize};            // 消息协议的序列化
+use serde_json::Value;                          // 消息内容
+use tokio::sync::mpsc;                          // 向连接推送消息的有界通道
+
+// 内部模块导入
+use crate::settings::WebSocketSettings;         // WebSocket配置
+
+/// 房间名的最大长度
+const MAX_ROOM_NAME_LEN: usize = 64;
+
+/// 客户端发给服务端的消息
+///
+/// 以`type`字段区分，`id`由客户端自行分配，服务端在ack或error中原样带回，例如：
+/// * `{"type": "join", "id": 1, "room": "lobby"}`
+/// * `{"type": "leave", "id": 2, "room": "lobby"}`
+/// * `{"type": "publish", "id": 3, "room": "lobby", "data": {"text": "hi"}}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join { id: Option<u64>, room: String },                  // 加入房间
+    Leave { id: Option<u64>, room: String },                 // 离开房间
+    Publish { id: Option<u64>, room: String, data: Value },  // 向房间内的其他成员发送消息
+}
+
+/// 服务端发给客户端的消息
+///
+/// 例如：
+/// * `{"type": "ack", "id": 1}`
+/// * `{"type": "error", "id": 3, "code": "not_joined", "message": "..."}`
+/// * `{"type": "message", "room": "lobby", "from": "alice", "data": {"text": "hi"}}`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Ack { id: Option<u64> },  // 请求处理成功
+    Error {
+        id: Option<u64>,       // 对应请求的id，消息无法解析时为null
+        code: &'static str,    // 稳定的错误码
+        message: String,       // 错误描述
+    },
+    Message {
+        room: String,          // 房间名
+        from: Option<String>,  // 发送者用户名，服务端推送或匿名连接时为null
+        data: Value,           // 消息内容
+    },
+}
+
+/// 连接ID
+type ConnId = u64;
+
+/// 房间表的内部状态
+#[derive(Default)]
+struct RoomsState {
+    connections: HashMap<ConnId, mpsc::Sender<ServerMessage>>,  // 连接ID到推送通道
+    rooms: HashMap<String, HashSet<ConnId>>,                    // 房间名到成员
+}
+
+/// WebSocket房间
+///
+/// 记录每个连接加入了哪些房间，并提供服务端向房间推送消息的接口，
+/// 其他处理函数可以通过`web::Data<Rooms>`调用`broadcast`
+///
+/// 每个连接有一个容量为`outbound_buffer`的待发送缓冲区，
+/// 客户端跟不上时新的推送被丢弃，推送方永远不会阻塞
+pub struct Rooms {
+    settings: WebSocketSettings,  // WebSocket配置
+    next_id: AtomicU64,           // 下一个连接ID
+    state: Mutex<RoomsState>,     // 由互斥锁保护的连接和房间表
+}
+
+impl Rooms {
+    /// 根据配置创建房间表
+    pub fn from_settings(settings: &WebSocketSettings) -> Self {
+        Rooms {
+            settings: settings.clone(),
+            next_id: AtomicU64::new(1),
+            state: Mutex::new(RoomsState::default()),
+        }
+    }
+
+    /// 向房间内的所有连接推送消息
+    ///
+    /// # 参数
+    /// * `room` - 房间名
+    /// * `data` - 消息内容
+    ///
+    /// # 返回值
+    /// * 返回成功放入缓冲区的连接数
+    pub fn broadcast(&self, room: &str, data: Value) -> usize {
+        self.deliver(room, None, None, data)
+    }
+
+    /// 房间当前的成员数
+    pub fn member_count(&self, room: &str) -> usize {
+        self.state().rooms.get(room).map_or(0, HashSet::len)
+    }
+
+    /// 向房间成员投递消息
+    ///
+    /// # 参数
+    /// * `room` - 房间名
+    /// * `sender` - 发送消息的连接，不会收到自己的消息
+    /// * `from` - 发送者用户名
+    /// * `data` - 消息内容
+    fn deliver(&self, room: &str, sender: Option<ConnId>, from: Option<&str>, data: Value) -> usize {
+        let state = self.state();
+        let Some(members) = state.rooms.get(room) else {
+            return 0;
+        };
+
+        let message = ServerMessage::Message {
+            room: room.to_string(),
+            from: from.map(str::to_string),
+            data,
+        };
+        members
+            .iter()
+            .filter(|conn| Some(**conn) != sender)
+            .filter_map(|conn| state.connections.get(conn))
+            .filter(|tx| match tx.try_send(message.clone()) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::warn!("WebSocket连接的发送缓冲区已满或已关闭，丢弃房间{}的消息: {}", room, err);
+                    false
+                }
+            })
+            .count()
+    }
+
+    /// 登记一个新连接
+    fn connect(&self) -> (ConnId, mpsc::Receiver<ServerMessage>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel(self.settings.outbound_buffer);
+        self.state().connections.insert(id, tx);
+        (id, rx)
+    }
+
+    /// 注销连接，并将其从所有房间中移除
+    fn disconnect(&self, conn: ConnId) {
+        let mut state = self.state();
+        state.connections.remove(&conn);
+        state.rooms.retain(|_, members| {
+            members.remove(&conn);
+            !members.is_empty()
+        });
+    }
+
+    /// 处理一条客户端消息，返回需要回复给该客户端的消息
+    fn handle(&self, conn: ConnId, user: Option<&str>, message: ClientMessage) -> ServerMessage {
+        match message {
+            ClientMessage::Join { id, room } => {
+                if let Err(reply) = check_room(id, &room) {
+                    return reply;
+                }
+                self.state().rooms.entry(room).or_default().insert(conn);
+                ServerMessage::Ack { id }
+            }
+            ClientMessage::Leave { id, room } => {
+                let mut state = self.state();
+                let removed = state.rooms.get_mut(&room).is_some_and(|members| members.remove(&conn));
+                if !removed {
+                    return not_joined(id, &room);
+                }
+                if state.rooms.get(&room).is_some_and(HashSet::is_empty) {
+                    state.rooms.remove(&room);
+                }
+                ServerMessage::Ack { id }
+            }
+            ClientMessage::Publish { id, room, data } => {
+                let joined = self.state().rooms.get(&room).is_some_and(|members| members.contains(&conn));
+                if !joined {
+                    return not_joined(id, &room);
+                }
+                self.deliver(&room, Some(conn), user, data);
+                ServerMessage::Ack { id }
+            }
+        }
+    }
+
+    /// 获取状态的锁
+    ///
+    /// 锁内只做简单的表操作，不会panic，锁中毒时直接沿用内部数据
+    fn state(&self) -> std::sync::MutexGuard<'_, RoomsState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 校验房间名：1到64个字母、数字、`_`、`.`、`-`
+fn check_room(id: Option<u64>, room: &str) -> Result<(), ServerMessage> {
+    let valid = !room.is_empty()
+        && room.len() <= MAX_ROOM_NAME_LEN
+        && room.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ServerMessage::Error {
+            id,
+            code: "invalid_room",
+            message: format!("房间名无效: {room}"),
+        })
+    }
+}
+
+/// 未加入房间时的错误
+fn not_joined(id: Option<u64>, room: &str) -> ServerMessage {
+    ServerMessage::Error {
+        id,
+        code: "not_joined",
+        message: format!("尚未加入房间: {room}"),
+    }
+}
+
+/// 完成WebSocket握手并在后台处理连接
+///
+/// # 参数
+/// * `rooms` - 房间表
+/// * `req` - HTTP请求
+/// * `body` - 请求体载荷，升级后作为WebSocket消息流
+/// * `user` - 已登录用户的用户名，匿名连接为None
+///
+/// # 返回值
+/// * 成功时返回101 Switching Protocols响应
+/// * 不是合法的WebSocket握手请求时返回错误
+pub fn start(
+    rooms: Arc<Rooms>,
+    req: &HttpRequest,
+    body: web::Payload,
+    user: Option<String>,
+) -> ActixResult<HttpResponse> {
+    let (response, session, stream) = actix_ws::handle(req, body)?;
+    let max_size = rooms.settings.max_frame_size;
+    let stream = stream
+        .max_frame_size(max_size)
+        .aggregate_continuations()
+        .max_continuation_size(max_size);
+
+    // 连接在后台任务中处理，握手响应立即返回
+    actix_web::rt::spawn(run_connection(rooms, session, stream, user));
+    Ok(response)
+}
+
+/// 处理一个WebSocket连接，直到连接关闭或空闲超时
+///
+/// 每隔`heartbeat_secs`发送一次ping，超过`idle_timeout_secs`没有收到客户端的任何消息（包括pong）时断开
+async fn run_connection(
+    rooms: Arc<Rooms>,
+    mut session: Session,
+    mut stream: AggregatedMessageStream,
+    user: Option<String>,
+) {
+    let (conn, mut outbound) = rooms.connect();
+    let mut heartbeat = tokio::time::interval(rooms.settings.heartbeat());
+    let mut last_seen = Instant::now();
+    log::info!("WebSocket连接{}已建立，用户: {:?}", conn, user);
+
+    let close_reason: Option<CloseReason> = loop {
+        let reply = tokio::select! {
+            received = stream.recv() => {
+                last_seen = Instant::now();
+                match received {
+                    Some(Ok(AggregatedMessage::Text(text))) => {
+                        match serde_json::from_str::<ClientMessage>(&text) {
+                            Ok(message) => rooms.handle(conn, user.as_deref(), message),
+                            Err(err) => ServerMessage::Error {
+                                id: None,
+                                code: "invalid_message",
+                                message: err.to_string(),
+                            },
+                        }
+                    }
+                    Some(Ok(AggregatedMessage::Binary(_))) => ServerMessage::Error {
+                        id: None,
+                        code: "invalid_message",
+                        message: "只支持JSON文本消息".to_string(),
+                    },
+                    Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break None;
+                        }
+                        continue;
+                    }
+                    Some(Ok(AggregatedMessage::Pong(_))) => continue,
+                    Some(Ok(AggregatedMessage::Close(reason))) => break reason,
+                    Some(Err(err)) => {
+                        log::warn!("WebSocket连接{}协议错误: {}", conn, err);
+                        break Some(CloseReason::from((CloseCode::Protocol, err.to_string())));
+                    }
+                    None => break None,
+                }
+            }
+            Some(message) = outbound.recv() => message,
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > rooms.settings.idle_timeout() {
+                    log::info!("WebSocket连接{}空闲超时", conn);
+                    break Some(CloseReason::from((CloseCode::Away, "idle timeout")));
+                }
+                if session.ping(b"").await.is_err() {
+                    break None;
+                }
+                continue;
+            }
+        };
+
+        // ServerMessage只包含字符串和JSON值，序列化不会失败
+        let text = serde_json::to_string(&reply).unwrap_or_default();
+        if session.text(text).await.is_err() {
+            break None;
+        }
+    };
+
+    rooms.disconnect(conn);
+    // 客户端已经断开时关闭会失败，忽略即可
+    let _ = session.close(close_reason).await;
+    log::info!("WebSocket连接{}已关闭", conn);
+}
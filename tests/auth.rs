@@ -0,0 +1,337 @@
+//! 注册、会话登录、JWT令牌和用户资源的集成测试
+
+mod common;
+
+// 外部库导入
+use actix_web::http::{header, StatusCode};  // 请求头和状态码
+use actix_web::test::{self, TestRequest};  // 测试请求
+use serde_json::json;  // 构造JSON请求体
+
+// 内部模块导入
+use common::{assert_error, call_json, call_text, init_app, init_app_with, register, test_settings, Auth, PASSWORD};
+
+#[actix_web::test]
+async fn register_does_not_reveal_existing_usernames() {
+    let (app, services) = init_app().await;
+
+    let attempt = |email: &str| {
+        TestRequest::post()
+            .uri("/register")
+            .set_form([("username", "alice"), ("email", email), ("password", PASSWORD)])
+            .to_request()
+    };
+    let first = call_text(&app, attempt("alice@example.com")).await;
+    assert_eq!(first.0, StatusCode::ACCEPTED, "{}", first.1);
+    assert!(!first.1.contains(PASSWORD), "响应中不能出现密码: {}", first.1);
+
+    // 用户名已存在时响应完全相同，只有第一次注册生效
+    let second = call_text(&app, attempt("other@example.com")).await;
+    assert_eq!(first, second);
+    assert_eq!(services.users.get("alice").unwrap().input.email, "alice@example.com");
+
+    // 密码太短
+    let req = TestRequest::post()
+        .uri("/register")
+        .set_form([("username", "bob"), ("email", "bob@example.com"), ("password", "short")])
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "password");
+}
+
+#[actix_web::test]
+async fn login_rejects_wrong_password_and_unknown_user() {
+    let (app, _) = init_app().await;
+    register(&app, "alice").await;
+
+    for (username, password) in [("alice", "wrong password"), ("nobody", PASSWORD)] {
+        let req = TestRequest::post()
+            .uri("/login")
+            .set_form([("username", username), ("password", password)])
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_error(status, &body, StatusCode::UNAUTHORIZED, "invalid_credentials");
+    }
+}
+
+#[actix_web::test]
+async fn repeated_failures_lock_the_account() {
+    let mut settings = test_settings();
+    settings.login_guard.max_failures_per_user = 2;
+    let (app, _) = init_app_with(settings).await;
+    register(&app, "alice").await;
+
+    let attempt = |password: &'static str| {
+        TestRequest::post()
+            .uri("/login")
+            .set_form([("username", "alice"), ("password", password)])
+            .to_request()
+    };
+    for _ in 0..2 {
+        let (status, _) = call_text(&app, attempt("wrong password")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    // 锁定期间正确的密码同样被拒绝
+    let resp = test::call_service(&app, attempt(PASSWORD)).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[actix_web::test]
+async fn concurrent_failures_still_lock_the_account() {
+    let mut settings = test_settings();
+    settings.login_guard.max_failures_per_user = 3;
+    let (app, _) = init_app_with(settings).await;
+    register(&app, "alice").await;
+
+    // 所有请求同时发出，密码校验完成之前就已计数，超出上限的请求直接被拒绝
+    let attempts = (0..8).map(|_| {
+        let req = TestRequest::post()
+            .uri("/login")
+            .set_form([("username", "alice"), ("password", "wrong password")])
+            .to_request();
+        test::call_service(&app, req)
+    });
+    let statuses: Vec<StatusCode> = futures::future::join_all(attempts)
+        .await
+        .iter()
+        .map(|resp| resp.status())
+        .collect();
+    let rejected = statuses.iter().filter(|status| **status == StatusCode::UNAUTHORIZED).count();
+    let locked = statuses.iter().filter(|status| **status == StatusCode::TOO_MANY_REQUESTS).count();
+    assert_eq!((rejected, locked), (3, 5), "{statuses:?}");
+
+    let req = TestRequest::post()
+        .uri("/login")
+        .set_form([("username", "alice"), ("password", PASSWORD)])
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn session_and_bearer_authenticate_user_updates() {
+    let (app, _) = init_app().await;
+
+    for (username, auth) in [
+        ("alice", Auth::session(&app, "alice").await),
+        ("bob", Auth::bearer(&app, "bob").await),
+    ] {
+        let update = json!({ "username": username, "email": format!("{username}@new.example.com") });
+        let req = auth.apply(TestRequest::put().uri(&format!("/user/{username}")).set_json(&update)).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{username}: {body}");
+        assert_eq!(body["email"], format!("{username}@new.example.com"));
+    }
+}
+
+#[actix_web::test]
+async fn user_updates_require_the_owner() {
+    let (app, _) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+    register(&app, "bob").await;
+
+    let update = json!({ "username": "bob", "email": "bob@evil.example.com" });
+    let req = TestRequest::put().uri("/user/bob").set_json(&update).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+
+    let req = alice.apply(TestRequest::put().uri("/user/bob").set_json(&update)).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::FORBIDDEN, "forbidden");
+
+    let req = alice
+        .apply(TestRequest::delete().uri("/user/bob"))
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::FORBIDDEN, "forbidden");
+}
+
+#[actix_web::test]
+async fn session_does_not_survive_username_reuse() {
+    let (app, _) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+
+    // 删除时清空会话Cookie
+    let req = alice
+        .apply(TestRequest::delete().uri("/user/alice"))
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let cookie = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == test_settings().auth.cookie_name)
+        .expect("删除用户时应当删除会话Cookie");
+    assert_eq!(cookie.value(), "");
+
+    // 其他人重新注册同名用户后，客户端保留的旧Cookie不能冒充新用户
+    register(&app, "alice").await;
+    let update = json!({ "username": "alice", "email": "alice@evil.example.com" });
+    let req = alice.apply(TestRequest::put().uri("/user/alice").set_json(&update)).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[actix_web::test]
+async fn session_follows_a_renamed_user() {
+    let (app, _) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+
+    let rename = json!({ "username": "alicia", "email": "alice@example.com" });
+    let req = alice.apply(TestRequest::put().uri("/user/alice").set_json(&rename)).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 会话保存的是用户ID，改名后仍然有效并指向新用户名
+    let update = json!({ "username": "alicia", "email": "alicia@example.com" });
+    let req = alice.apply(TestRequest::put().uri("/user/alicia").set_json(&update)).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 旧用户名被他人注册后，会话不会指向那个用户
+    register(&app, "alice").await;
+    let req = alice.apply(TestRequest::put().uri("/user/alice").set_json(&rename)).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::FORBIDDEN, "forbidden");
+}
+
+#[actix_web::test]
+async fn bearer_scheme_is_case_insensitive() {
+    let (app, _) = init_app().await;
+    register(&app, "alice").await;
+    let token = common::token(&app, "alice", PASSWORD).await["access_token"].as_str().unwrap().to_string();
+
+    let update = json!({ "username": "alice", "email": "alice@new.example.com" });
+    for scheme in ["bearer", "BEARER"] {
+        let req = TestRequest::put()
+            .uri("/user/alice")
+            .insert_header((header::AUTHORIZATION, format!("{scheme} {token}")))
+            .set_json(&update)
+            .to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{scheme}: {body}");
+    }
+}
+
+#[actix_web::test]
+async fn tokens_do_not_survive_username_reuse() {
+    let (app, _) = init_app().await;
+    register(&app, "alice").await;
+    let pair = common::token(&app, "alice", PASSWORD).await;
+    let alice = Auth::Bearer(pair["access_token"].as_str().unwrap().to_string());
+
+    let req = alice
+        .apply(TestRequest::delete().uri("/user/alice"))
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .to_request();
+    let (status, _) = call_text(&app, req).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 令牌中是旧用户的ID，同名的新用户不受影响
+    register(&app, "alice").await;
+    let update = json!({ "username": "alice", "email": "alice@evil.example.com" });
+    let req = alice.apply(TestRequest::put().uri("/user/alice").set_json(&update)).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+
+    let req = TestRequest::post()
+        .uri("/token/refresh")
+        .set_json(json!({ "refresh_token": pair["refresh_token"] }))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "invalid_token");
+}
+
+#[actix_web::test]
+async fn invalid_bearer_token_is_rejected() {
+    let (app, _) = init_app().await;
+    let req = Auth::Bearer("not-a-jwt".to_string()).apply(TestRequest::get().uri("/")).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "invalid_token");
+}
+
+#[actix_web::test]
+async fn token_refresh_issues_a_new_pair() {
+    let (app, _) = init_app().await;
+    register(&app, "alice").await;
+    let pair = common::token(&app, "alice", PASSWORD).await;
+    assert_eq!(pair["token_type"], "Bearer");
+
+    let req = TestRequest::post()
+        .uri("/token/refresh")
+        .set_json(json!({ "refresh_token": pair["refresh_token"] }))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["access_token"].is_string());
+
+    // 访问令牌不能用来刷新
+    let req = TestRequest::post()
+        .uri("/token/refresh")
+        .set_json(json!({ "refresh_token": pair["access_token"] }))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "invalid_token");
+}
+
+#[actix_web::test]
+async fn logout_clears_the_session_cookie() {
+    let (app, _) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+
+    let resp = test::call_service(&app, alice.apply(TestRequest::post().uri("/logout")).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let cookie = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == test_settings().auth.cookie_name)
+        .expect("退出登录时应当删除会话Cookie");
+    assert_eq!(cookie.value(), "");
+}
+
+#[actix_web::test]
+async fn user_collection_crud() {
+    let (app, services) = init_app().await;
+    let erin = Auth::session(&app, "erin").await;
+
+    // 列出和创建用户都需要登录
+    let carol = json!({ "username": "carol", "email": "carol@example.com" });
+    let req = TestRequest::post().uri("/users").set_json(&carol).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+    let (status, body) = call_json(&app, TestRequest::get().uri("/users").to_request()).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+
+    let req = erin.apply(TestRequest::post().uri("/users").set_json(&carol)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert!(resp.headers().get(header::LOCATION).unwrap().to_str().unwrap().ends_with("/user/carol"));
+
+    // POST /users只接受JSON，守卫不匹配时资源返回405
+    let req = erin.apply(TestRequest::post().uri("/users").set_form([("username", "dave")])).to_request();
+    let (status, _) = call_text(&app, req).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+
+    let (status, body) = call_json(&app, erin.apply(TestRequest::get().uri("/users")).to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+    assert_eq!(services.users.list().unwrap().len(), 2);
+
+    // 删除需要本人登录
+    let req = erin
+        .apply(TestRequest::delete().uri("/user/erin"))
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .to_request();
+    let (status, _) = call_text(&app, req).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // 查询用户同样需要登录，已删除的用户返回404
+    let get_erin = || TestRequest::get().uri("/user/erin").insert_header((header::CONTENT_TYPE, "application/json"));
+    let (status, body) = call_json(&app, get_erin().to_request()).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+    let frank = Auth::bearer(&app, "frank").await;
+    let (status, body) = call_json(&app, frank.apply(get_erin()).to_request()).await;
+    assert_error(status, &body, StatusCode::NOT_FOUND, "user_not_found");
+}
@@ -0,0 +1,268 @@
+//! 集成测试的公共工具
+//!
+//! * `test_settings` / `init_app` - 用内存存储构建完整应用
+//! * `register` / `login` / `token` / `Auth` - 登录并发送带身份的请求
+//! * `SseReader` - 逐条读取SSE响应中的事件
+
+// 不是每个测试文件都用到所有工具
+#![allow(dead_code)]
+
+// 标准库导入
+use std::pin::Pin;  // 固定响应体以便逐块读取
+use std::time::Duration;  // 读取事件的超时
+
+// 外部库导入
+use actix_http::Request;  // 测试请求
+use actix_web::body::MessageBody;  // 响应体
+use actix_web::cookie::Cookie;  // 会话Cookie
+use actix_web::dev::{Service, ServiceResponse};  // 初始化后的应用
+use actix_web::http::{header, StatusCode};  // 请求头和状态码
+use actix_web::test::{self, TestRequest};  // 测试请求构建和调用
+use serde_json::Value;  // JSON响应
+
+// 内部模块导入
+use web_learning::app::{build_app, AppServices};  // 应用工厂
+use web_learning::settings::Settings;  // 服务器配置
+
+/// 测试用户的默认密码，满足注册的长度要求
+pub const PASSWORD: &str = "correct horse battery";
+
+/// 等待一条SSE事件的最长时间
+const SSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 测试用的配置
+///
+/// 内存存储、不写计数器快照、固定的JWT密钥，登录失败的延迟缩短到1毫秒
+pub fn test_settings() -> Settings {
+    let mut settings = Settings::default();
+    settings.counters.snapshot_file = None;
+    settings.jwt.secret = Some("integration-test-secret-at-least-32-bytes".to_string());
+    settings.login_guard.base_delay_ms = 1;
+    settings.login_guard.max_delay_ms = 1;
+    settings
+}
+
+/// 用默认的测试配置初始化应用
+///
+/// # 返回值
+/// * 初始化后的应用和它使用的共享组件，测试可以直接检查或修改组件的状态
+pub async fn init_app() -> (
+    impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+    AppServices,
+) {
+    init_app_with(test_settings()).await
+}
+
+/// 用指定的配置初始化应用
+///
+/// # 参数
+/// * `settings` - 服务器配置
+pub async fn init_app_with(
+    settings: Settings,
+) -> (
+    impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+    AppServices,
+) {
+    let services = AppServices::from_settings(&settings, None).expect("无法创建应用组件");
+    let app = test::init_service(build_app(&services)).await;
+    (app, services)
+}
+
+/// 发送请求并把响应体读成字符串
+pub async fn call_text<S, B>(app: &S, req: Request) -> (StatusCode, String)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let resp = test::call_service(app, req).await;
+    let status = resp.status();
+    let body = test::read_body(resp).await;
+    (status, String::from_utf8(body.to_vec()).expect("响应体不是UTF-8"))
+}
+
+/// 发送请求并把响应体解析为JSON
+pub async fn call_json<S, B>(app: &S, req: Request) -> (StatusCode, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (status, body) = call_text(app, req).await;
+    let value = serde_json::from_str(&body).unwrap_or_else(|err| panic!("响应体不是JSON（{err}）: {body}"));
+    (status, value)
+}
+
+/// 断言响应是统一的错误信封
+///
+/// # 参数
+/// * `status` - 实际的状态码
+/// * `body` - 响应体
+/// * `expected_status` - 期望的状态码
+/// * `code` - 期望的错误码
+pub fn assert_error(status: StatusCode, body: &Value, expected_status: StatusCode, code: &str) {
+    assert_eq!(status, expected_status, "响应: {body}");
+    assert_eq!(body["code"], code, "响应: {body}");
+    assert!(body["message"].is_string(), "错误信封缺少message: {body}");
+    assert!(body["request_id"].is_string(), "错误信封缺少request_id: {body}");
+}
+
+/// 注册一个用户，失败时直接panic
+pub async fn register<S, B>(app: &S, username: &str)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let req = TestRequest::post()
+        .uri("/register")
+        .set_form([("username", username), ("email", &format!("{username}@example.com")), ("password", PASSWORD)])
+        .to_request();
+    let (status, body) = call_text(app, req).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "注册{username}失败: {body}");
+}
+
+/// 用表单登录，返回会话Cookie
+pub async fn login<S, B>(app: &S, username: &str, password: &str) -> Cookie<'static>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let req = TestRequest::post()
+        .uri("/login")
+        .set_form([("username", username), ("password", password)])
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{username}登录失败");
+    resp.response()
+        .cookies()
+        .find(|cookie| cookie.name() == test_settings().auth.cookie_name)
+        .map(Cookie::into_owned)
+        .expect("登录响应中没有会话Cookie")
+}
+
+/// 用JSON换取令牌，返回`/token`的响应
+pub async fn token<S, B>(app: &S, username: &str, password: &str) -> Value
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let req = TestRequest::post()
+        .uri("/token")
+        .set_json(serde_json::json!({ "username": username, "password": password }))
+        .to_request();
+    let (status, body) = call_json(app, req).await;
+    assert_eq!(status, StatusCode::OK, "{username}换取令牌失败: {body}");
+    body
+}
+
+/// 请求携带的身份
+pub enum Auth {
+    Session(Cookie<'static>),  // 登录得到的会话Cookie
+    Bearer(String),            // JWT访问令牌
+}
+
+impl Auth {
+    /// 注册用户后用会话登录
+    pub async fn session<S, B>(app: &S, username: &str) -> Self
+    where
+        S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        B: MessageBody,
+    {
+        register(app, username).await;
+        Auth::Session(login(app, username, PASSWORD).await)
+    }
+
+    /// 注册用户后换取访问令牌
+    pub async fn bearer<S, B>(app: &S, username: &str) -> Self
+    where
+        S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        B: MessageBody,
+    {
+        register(app, username).await;
+        let pair = token(app, username, PASSWORD).await;
+        Auth::Bearer(pair["access_token"].as_str().expect("缺少access_token").to_string())
+    }
+
+    /// 给请求加上身份
+    pub fn apply(&self, req: TestRequest) -> TestRequest {
+        match self {
+            Auth::Session(cookie) => req.cookie(cookie.clone()),
+            Auth::Bearer(token) => req.insert_header((header::AUTHORIZATION, format!("Bearer {token}"))),
+        }
+    }
+}
+
+/// 一条SSE事件
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,     // id字段
+    pub event: Option<String>,  // event字段
+    pub data: String,           // 所有data行，以换行符连接
+}
+
+/// 逐条读取SSE响应中的事件
+///
+/// 注释（保活）被跳过，`retry:`字段记录在`retry`中
+pub struct SseReader<B> {
+    body: Pin<Box<B>>,       // 响应体
+    buffer: String,          // 尚未组成完整消息的数据
+    pub retry: Option<u64>,  // 服务端下发的重连间隔（毫秒）
+}
+
+impl<B: MessageBody> SseReader<B> {
+    /// 检查响应是事件流并开始读取
+    pub fn new(resp: ServiceResponse<B>) -> Self {
+        assert_eq!(resp.status(), StatusCode::OK);
+        let content_type = resp.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        assert_eq!(content_type, Some("text/event-stream"));
+        SseReader { body: Box::pin(resp.into_body()), buffer: String::new(), retry: None }
+    }
+
+    /// 读取下一条事件
+    ///
+    /// # 返回值
+    /// * 流结束时返回None；超过5秒没有事件时panic
+    pub async fn next_event(&mut self) -> Option<SseEvent> {
+        loop {
+            while let Some(end) = self.buffer.find("\n\n") {
+                let message: String = self.buffer.drain(..end + 2).collect();
+                if let Some(event) = self.parse(&message) {
+                    return Some(event);
+                }
+            }
+
+            let chunk = tokio::time::timeout(SSE_TIMEOUT, std::future::poll_fn(|cx| self.body.as_mut().poll_next(cx)))
+                .await
+                .expect("等待SSE事件超时")?;
+            let chunk = chunk.map_err(Into::into).expect("读取SSE响应体失败");
+            self.buffer.push_str(std::str::from_utf8(&chunk).expect("SSE数据不是UTF-8"));
+        }
+    }
+
+    /// 读取接下来的`count`条事件
+    pub async fn take(&mut self, count: usize) -> Vec<SseEvent> {
+        let mut events = Vec::with_capacity(count);
+        for _ in 0..count {
+            events.push(self.next_event().await.expect("SSE流提前结束"));
+        }
+        events
+    }
+
+    /// 解析一条消息，只有注释或retry时返回None
+    fn parse(&mut self, message: &str) -> Option<SseEvent> {
+        let mut event = SseEvent::default();
+        let mut data = Vec::new();
+        for line in message.lines() {
+            match line.split_once(": ") {
+                Some(("id", value)) => event.id = Some(value.to_string()),
+                Some(("event", value)) => event.event = Some(value.to_string()),
+                Some(("data", value)) => data.push(value),
+                Some(("retry", value)) => self.retry = value.parse().ok(),
+                _ => {}
+            }
+        }
+        if event.id.is_none() && event.event.is_none() && data.is_empty() {
+            return None;
+        }
+        event.data = data.join("\n");
+        Some(event)
+    }
+}
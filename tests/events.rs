@@ -0,0 +1,142 @@
+//! SSE推送、命名计数器和指标的集成测试
+
+mod common;
+
+// 外部库导入
+use actix_web::http::StatusCode;  // 状态码
+use actix_web::test::{self, TestRequest};  // 测试请求
+use serde_json::json;  // 构造JSON请求体
+
+// 内部模块导入
+use common::{assert_error, call_json, call_text, init_app, init_app_with, test_settings, Auth, SseReader};
+
+#[actix_web::test]
+async fn sse_receives_user_events() {
+    let (app, _) = init_app().await;
+    let bob = Auth::bearer(&app, "bob").await;
+
+    let resp = test::call_service(&app, TestRequest::get().uri("/sse?channel=users").to_request()).await;
+    let mut events = SseReader::new(resp);
+
+    let req = bob
+        .apply(TestRequest::post().uri("/users").set_json(json!({ "username": "alice", "email": "alice@example.com" })))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let event = events.next_event().await.expect("SSE流提前结束");
+    assert_eq!(event.id.as_deref(), Some("1"));
+    assert_eq!(event.event.as_deref(), Some("user_created"));
+    let data: serde_json::Value = serde_json::from_str(&event.data).unwrap();
+    assert_eq!(data["username"], "alice");
+    assert_eq!(events.retry, Some(3000));
+}
+
+#[actix_web::test]
+async fn sse_replays_after_last_event_id() {
+    let (app, services) = init_app().await;
+    for n in 1..=3 {
+        services.event_hub.publish("news", "headline", format!("story {n}"));
+    }
+
+    let req = TestRequest::get()
+        .uri("/sse?channel=news")
+        .insert_header(("last-event-id", "1"))
+        .to_request();
+    let mut events = SseReader::new(test::call_service(&app, req).await);
+    let replayed = events.take(2).await;
+    let ids: Vec<_> = replayed.iter().map(|event| event.id.as_deref()).collect();
+    assert_eq!(ids, [Some("2"), Some("3")]);
+    assert_eq!(replayed[1].data, "story 3");
+}
+
+#[actix_web::test]
+async fn sse_ends_with_shutdown_event() {
+    let (app, services) = init_app().await;
+    let resp = test::call_service(&app, TestRequest::get().uri("/sse").to_request()).await;
+    let mut events = SseReader::new(resp);
+
+    services.event_hub.close();
+    let event = events.next_event().await.expect("SSE流提前结束");
+    assert_eq!(event.event.as_deref(), Some("shutdown"));
+    assert_eq!(event.id, None);
+    assert!(events.next_event().await.is_none(), "shutdown之后流应当结束");
+}
+
+#[actix_web::test]
+async fn sse_rejects_invalid_channel() {
+    let (app, _) = init_app().await;
+    let (status, body) = call_json(&app, TestRequest::get().uri("/sse?channel=bad%20name").to_request()).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+}
+
+#[actix_web::test]
+async fn counters_can_be_changed_and_read() {
+    let (app, services) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/counters/visits").to_request()).await;
+    assert_eq!((status, body), (StatusCode::OK, json!({ "name": "visits", "value": 0 })));
+
+    // 读取不需要登录，修改需要
+    let req = TestRequest::post().uri("/counters/visits").set_json(json!({ "action": "increment" })).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "unauthorized");
+    assert_eq!(services.counters.get("visits"), 0);
+
+    let steps = [
+        (json!({ "action": "increment", "amount": 5 }), 5),
+        (json!({ "action": "decrement" }), 4),
+        (json!({ "action": "reset" }), 0),
+        (json!({ "action": "increment" }), 1),
+    ];
+    for (update, expected) in steps {
+        let req = alice.apply(TestRequest::post().uri("/counters/visits").set_json(&update)).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_eq!(status, StatusCode::OK, "{update}: {body}");
+        assert_eq!(body["value"], expected, "{update}");
+    }
+    assert_eq!(services.counters.get("visits"), 1);
+
+    let req = alice
+        .apply(TestRequest::post().uri("/counters/visits").set_json(json!({ "action": "increment", "amount": 0 })))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "amount");
+}
+
+#[actix_web::test]
+async fn new_counters_are_capped() {
+    let mut settings = test_settings();
+    settings.counters.max_counters = 1;
+    let (app, services) = init_app_with(settings).await;
+    let alice = Auth::bearer(&app, "alice").await;
+
+    let increment = |name: &str| {
+        alice.apply(TestRequest::post().uri(&format!("/counters/{name}")).set_json(json!({ "action": "increment" })))
+    };
+    let (status, body) = call_json(&app, increment("visits").to_request()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = call_json(&app, increment("likes").to_request()).await;
+    assert_error(status, &body, StatusCode::CONFLICT, "counter_limit_reached");
+    assert_eq!(body["details"]["max"], 1);
+    assert_eq!(services.counters.all().len(), 1);
+
+    // 已有的计数器仍然可以修改
+    let (status, body) = call_json(&app, increment("visits").to_request()).await;
+    assert_eq!((status, &body["value"]), (StatusCode::OK, &json!(2)), "{body}");
+}
+
+#[actix_web::test]
+async fn metrics_report_requests_and_counters() {
+    let (app, services) = init_app().await;
+    services.counters.add("downloads", 3).unwrap();
+    let (status, _) = call_text(&app, TestRequest::get().uri("/hey").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = call_text(&app, TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("/hey"), "{body}");
+    assert!(body.contains("downloads"), "{body}");
+}
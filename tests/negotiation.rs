@@ -0,0 +1,173 @@
+//! JSON、MessagePack、CBOR和XML内容协商的集成测试
+
+mod common;
+
+// 标准库导入
+use std::collections::HashMap;  // 无法序列化为JSON的数据
+
+// 外部库导入
+use actix_web::http::{header, StatusCode};  // 请求头和状态码
+use actix_web::test::{self, TestRequest};  // 测试请求
+use actix_web::Responder;  // 直接调用respond_to
+use serde::{Deserialize, Serialize};  // 测试中的请求和响应结构
+use serde_json::json;  // 构造JSON请求体
+
+// 内部模块导入
+use common::{assert_error, call_json, init_app, Auth};
+use web_learning::negotiation::{Format, Negotiated};  // 内容协商
+
+/// 计数器响应
+#[derive(Debug, PartialEq, Deserialize)]
+struct CounterValue {
+    name: String,
+    value: i64,
+}
+
+/// 计数器修改请求
+#[derive(Serialize)]
+#[serde(rename = "CounterUpdate")]
+struct CounterUpdate {
+    action: &'static str,
+    amount: Option<i64>,
+}
+
+#[actix_web::test]
+async fn responses_follow_the_accept_header() {
+    let (app, _) = init_app().await;
+    let cases = [
+        (None, Format::Json),
+        (Some("*/*"), Format::Json),
+        (Some("application/msgpack"), Format::MessagePack),
+        (Some("application/cbor, application/json;q=0.5"), Format::Cbor),
+        (Some("text/html, application/xml;q=0.9"), Format::Xml),
+        (Some("application/json;q=0, */*"), Format::MessagePack),
+        (Some("text/*"), Format::Xml),
+    ];
+    for (accept, expected) in cases {
+        let mut req = TestRequest::get().uri("/counters/visits");
+        if let Some(accept) = accept {
+            req = req.insert_header((header::ACCEPT, accept));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{accept:?}");
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), expected.media_type(), "{accept:?}");
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept");
+
+        let body = test::read_body(resp).await;
+        let counter: CounterValue = expected.decode(&body).unwrap();
+        assert_eq!(counter, CounterValue { name: "visits".to_string(), value: 0 }, "{accept:?}");
+    }
+}
+
+#[actix_web::test]
+async fn my_struct_is_negotiated() {
+    let (app, _) = init_app().await;
+
+    let req = TestRequest::get().uri("/my_struct").insert_header((header::ACCEPT, "application/xml")).to_request();
+    let body = test::read_body(test::call_service(&app, req).await).await;
+    assert_eq!(body, "<MyStruct><name>Kayano</name><age>18</age></MyStruct>");
+
+    let req = TestRequest::get().uri("/my_struct").insert_header((header::ACCEPT, "application/msgpack")).to_request();
+    let body = test::read_body(test::call_service(&app, req).await).await;
+    let value: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+    assert_eq!(value, json!({ "name": "Kayano", "age": 18 }));
+}
+
+#[actix_web::test]
+async fn unsupported_accept_returns_406() {
+    let (app, _) = init_app().await;
+    for accept in ["text/html", "image/*", "application/json;q=0"] {
+        let req = TestRequest::get().uri("/my_struct").insert_header((header::ACCEPT, accept)).to_request();
+        let (status, body) = call_json(&app, req).await;
+        assert_error(status, &body, StatusCode::NOT_ACCEPTABLE, "not_acceptable");
+        assert!(body["details"]["supported"].as_str().unwrap().contains("application/cbor"), "{body}");
+    }
+}
+
+#[actix_web::test]
+async fn request_bodies_are_decoded_by_content_type() {
+    let (app, services) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+    let update = CounterUpdate { action: "increment", amount: Some(2) };
+    let bodies = [
+        (Format::Json, serde_json::to_vec(&update).unwrap()),
+        (Format::MessagePack, rmp_serde::to_vec_named(&update).unwrap()),
+        (Format::Cbor, cbor4ii::serde::to_vec(Vec::new(), &update).unwrap()),
+        (Format::Xml, quick_xml::se::to_string(&update).unwrap().into_bytes()),
+    ];
+    for (round, (format, body)) in bodies.into_iter().enumerate() {
+        let req = alice
+            .apply(TestRequest::post().uri("/counters/visits"))
+            .insert_header((header::CONTENT_TYPE, format.media_type()))
+            .insert_header((header::ACCEPT, format.media_type()))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{format:?}");
+        let counter: CounterValue = format.decode(&test::read_body(resp).await).unwrap();
+        assert_eq!(counter.value, 2 * (round as i64 + 1), "{format:?}");
+    }
+    assert_eq!(services.counters.get("visits"), 8);
+}
+
+#[actix_web::test]
+async fn bad_request_bodies_are_rejected() {
+    let (app, _) = init_app().await;
+    let alice = Auth::session(&app, "alice").await;
+    let post = || alice.apply(TestRequest::post().uri("/counters/visits"));
+
+    // 不支持的格式和缺少Content-Type都返回415
+    let req = post()
+        .insert_header((header::CONTENT_TYPE, "text/plain"))
+        .set_payload("increment")
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type");
+
+    let req = post().set_payload("{}").to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::UNSUPPORTED_MEDIA_TYPE, "unsupported_media_type");
+
+    // 与声明的格式不符
+    let req = post()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(vec![0xff, 0x00, 0x13])
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "invalid_body");
+    assert_eq!(body["details"]["format"], "application/cbor");
+
+    // 解析成功后同样执行校验规则
+    let update = CounterUpdate { action: "increment", amount: Some(0) };
+    let req = post()
+        .insert_header((header::CONTENT_TYPE, "application/msgpack"))
+        .set_payload(rmp_serde::to_vec_named(&update).unwrap())
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "amount");
+}
+
+#[actix_web::test]
+async fn serialization_failures_return_500() {
+    // JSON对象的键只能是字符串，XML的根元素不能是列表
+    let tuple_keys: HashMap<(u8, u8), u8> = HashMap::from([((1, 2), 3)]);
+    assert_eq!(respond(tuple_keys, "application/json").await, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(respond(vec![1, 2, 3], "application/xml").await, StatusCode::INTERNAL_SERVER_ERROR);
+    // 同样的数据换成可以表示的格式时正常返回
+    assert_eq!(respond(vec![1, 2, 3], "application/cbor").await, StatusCode::OK);
+}
+
+/// 用指定的Accept头渲染响应，返回状态码
+async fn respond<T: Serialize>(value: T, accept: &str) -> StatusCode {
+    let req = TestRequest::default().insert_header((header::ACCEPT, accept)).to_http_request();
+    let resp = Negotiated(value).respond_to(&req);
+    let status = resp.status();
+    let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+    // 序列化失败时不泄露内部细节
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "internal_error");
+    }
+    status
+}
@@ -0,0 +1,207 @@
+//! OpenAPI文档的覆盖检查
+//!
+//! 用测试应用的路由表（`HttpRequest::resource_map`）与`ApiDoc`生成的文档比较：
+//! * 路由表中每个资源的路径都出现在文档中
+//! * 文档中的每个路径在路由表中解析到同一个资源，按文档中的方法发送请求能到达处理函数
+//!
+//! 新增路由却没有加到文档中，或者文档中有已经删除的路由时测试失败
+
+mod common;
+
+// 标准库导入
+use std::cell::RefCell;  // 保存默认服务复制的路由表
+use std::collections::BTreeSet;  // 路由集合
+use std::rc::Rc;  // 在默认服务和测试之间共享路由表
+
+// 外部库导入
+use actix_http::Request;  // 测试应用处理的请求
+use actix_web::body::MessageBody;  // 响应体
+use actix_web::dev::{ResourceMap, Service, ServiceResponse};  // 路由表和测试应用
+use actix_web::http::{header, Method, StatusCode};  // 请求头、方法和状态码
+use actix_web::test::{self, TestRequest};  // 测试请求
+use actix_web::{web, HttpRequest, HttpResponse};  // 默认服务
+use utoipa::OpenApi;  // 生成文档
+
+// 内部模块导入
+use common::{call_json, call_text, init_app, test_settings};
+use web_learning::app::{build_app, AppServices};  // 应用工厂
+use web_learning::openapi::ApiDoc;  // 被检查的文档
+
+/// 没有路由匹配时测试应用返回的状态码，与处理函数可能返回的状态码区分开
+const UNROUTED: StatusCode = StatusCode::IM_A_TEAPOT;
+
+/// 文档中的所有操作：(方法, 路径)
+fn documented_routes() -> Vec<(Method, String)> {
+    let spec = ApiDoc::openapi();
+    let mut routes = Vec::new();
+    for (path, item) in &spec.paths.paths {
+        let operations = [
+            (Method::GET, &item.get),
+            (Method::POST, &item.post),
+            (Method::PUT, &item.put),
+            (Method::DELETE, &item.delete),
+            (Method::PATCH, &item.patch),
+            (Method::HEAD, &item.head),
+            (Method::OPTIONS, &item.options),
+            (Method::TRACE, &item.trace),
+        ];
+        for (method, operation) in operations {
+            if operation.is_some() {
+                routes.push((method, path.clone()));
+            }
+        }
+    }
+    routes
+}
+
+/// 路由表中所有资源的完整路径
+///
+/// `ResourceMap`没有提供遍历资源的接口，这里读取它的`{:#?}`输出：树中每个节点的`patterns`
+/// 是相对于所在作用域的路径，`is_prefix`为true的是作用域；`named`按名称重复列出了同一批资源，
+/// 而且不带作用域前缀，需要跳过
+fn registered_paths(rmap: &ResourceMap) -> BTreeSet<String> {
+    let dump = format!("{rmap:#?}");
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+    let mut lines = dump.lines();
+    // (缩进, 完整路径)
+    let mut scopes: Vec<(usize, String)> = Vec::new();
+    let mut paths = BTreeSet::new();
+    while let Some(line) = lines.next() {
+        let indent = indent_of(line);
+        match line.trim_start() {
+            "named: {" => {
+                lines.by_ref().find(|end| indent_of(end) == indent && end.trim_start().starts_with('}'));
+            }
+            "patterns: Single(" => {
+                let pattern = lines.next().expect("缺少路径").trim().trim_end_matches(',').trim_matches('"');
+                let is_prefix = lines
+                    .by_ref()
+                    .find_map(|line| line.trim().strip_prefix("is_prefix: "))
+                    .expect("缺少is_prefix")
+                    == "true,";
+                scopes.retain(|(scope_indent, _)| *scope_indent < indent);
+                let prefix = scopes.last().map_or("", |(_, path)| path.as_str());
+                let path = format!("{prefix}{pattern}");
+                if is_prefix {
+                    scopes.push((indent, path));
+                } else {
+                    paths.insert(if path.starts_with('/') { path } else { format!("/{path}") });
+                }
+            }
+            other if other.starts_with("patterns: ") => panic!("不支持的路径形式: {other}"),
+            _ => {}
+        }
+    }
+    paths
+}
+
+/// 用示例值替换路径参数，例如"/users/{id}"变为"/users/1"
+fn sample_path(path: &str) -> String {
+    let mut sample = String::new();
+    let mut rest = path;
+    while let Some((before, after)) = rest.split_once('{') {
+        sample.push_str(before);
+        rest = after.split_once('}').map_or("", |(_, after)| after);
+        sample.push('1');
+    }
+    sample.push_str(rest);
+    sample
+}
+
+/// 用完整应用初始化测试应用，没有路由匹配时返回`UNROUTED`
+///
+/// # 返回值
+/// * 测试应用和它的路由表
+async fn init_routing_app() -> (
+    impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+    ResourceMap,
+) {
+    let mut settings = test_settings();
+    settings.rate_limit.enabled = false;
+    let services = AppServices::from_settings(&settings, None).expect("无法创建应用组件");
+    // 默认服务收到的请求已经完成路由，从中复制路由表
+    let rmap = Rc::new(RefCell::new(None));
+    let slot = rmap.clone();
+    let app = test::init_service(build_app(&services).default_service(web::to(move |req: HttpRequest| {
+        slot.replace(Some(req.resource_map().clone()));
+        async { HttpResponse::new(UNROUTED) }
+    })))
+    .await;
+
+    let status = test::call_service(&app, TestRequest::get().uri("/no-such-route").to_request()).await.status();
+    assert_eq!(status, UNROUTED);
+    let rmap = rmap.take().expect("默认服务没有收到请求");
+    (app, rmap)
+}
+
+#[actix_web::test]
+async fn every_route_is_documented() {
+    let (_, rmap) = init_routing_app().await;
+    let registered = registered_paths(&rmap);
+    assert!(registered.len() > 30, "路由表中的资源太少，读取方式可能已经失效: {registered:?}");
+
+    let documented: BTreeSet<String> = documented_routes().into_iter().map(|(_, path)| path).collect();
+    let missing: Vec<_> = registered.difference(&documented).collect();
+    assert!(missing.is_empty(), "以下路由没有出现在OpenAPI文档中，请在openapi::ApiDoc中添加: {missing:?}");
+}
+
+#[actix_web::test]
+async fn every_documented_route_exists() {
+    let (app, rmap) = init_routing_app().await;
+    // 按虚拟主机选择的路由需要对应的Host
+    let hosts: Vec<Option<String>> = std::iter::once(None)
+        .chain(test_settings().virtual_hosts.hosts.iter().map(|host| Some(host.patterns[0].clone())))
+        .collect();
+
+    let mut stale = Vec::new();
+    for (method, path) in documented_routes() {
+        let sample = sample_path(&path);
+        let mut routed = false;
+        for host in &hosts {
+            let mut req = TestRequest::default()
+                .method(method.clone())
+                .uri(&sample)
+                .insert_header((header::CONTENT_TYPE, "application/json"));
+            if let Some(host) = host {
+                req = req.insert_header((header::HOST, host.as_str()));
+            }
+            let status = test::call_service(&app, req.to_request()).await.status();
+            // 405表示路径对应的资源存在，但没有这个方法的路由
+            if status != UNROUTED && status != StatusCode::METHOD_NOT_ALLOWED {
+                routed = true;
+                break;
+            }
+        }
+
+        let pattern = rmap.match_pattern(&sample);
+        if !routed || pattern.as_deref() != Some(path.as_str()) {
+            stale.push((method, path, pattern));
+        }
+    }
+    assert!(stale.is_empty(), "OpenAPI文档中的以下路由没有注册或路径不一致: {stale:?}");
+}
+
+#[actix_web::test]
+async fn served_spec_matches_api_doc() {
+    let (app, _) = init_app().await;
+    let (status, body) = call_json(&app, TestRequest::get().uri("/openapi.json").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    let expected: serde_json::Value = serde_json::from_str(&ApiDoc::openapi().to_json().unwrap()).unwrap();
+    assert_eq!(body, expected);
+
+    let (status, body) = call_text(&app, TestRequest::get().uri("/docs").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("/openapi.json"));
+}
+
+#[test]
+fn spec_is_valid_json_with_schemas() {
+    let json = ApiDoc::openapi().to_json().expect("文档无法序列化");
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert!(value["openapi"].as_str().is_some_and(|version| version.starts_with("3.")));
+
+    let schemas = &value["components"]["schemas"];
+    for name in ["User", "UserIput", "LoginInfo", "MyStruct", "ErrorEnvelope", "ProblemDetails"] {
+        assert!(schemas.get(name).is_some(), "components.schemas中缺少{name}");
+    }
+}
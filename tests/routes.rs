@@ -0,0 +1,341 @@
+//! 路由、守卫、参数提取和错误映射的集成测试
+
+mod common;
+
+// 外部库导入
+use actix_web::http::{header, StatusCode};  // 请求头和状态码
+use actix_web::test::{self, TestRequest};  // 测试请求
+use serde_json::json;  // 构造JSON请求体
+
+// 内部模块导入
+use common::{assert_error, call_json, call_text, init_app, init_app_with, test_settings};
+
+#[actix_web::test]
+async fn basic_routes() {
+    let (app, _) = init_app().await;
+
+    let cases = [
+        ("/", "hello actix-web"),
+        ("/hey", "hello there"),
+        ("/app/index", "hello from index"),
+        ("/app/index2", "Hello from Kayano!"),
+        ("/app2/index", "hello from index"),
+        ("/path/7/alice", "Hello from Path_test! User ID: 7, Name: alice"),
+        ("/path2/7/alice", "Hello from Path_test! User ID: 7, Name: alice"),
+    ];
+    for (uri, expected) in cases {
+        let (status, body) = call_text(&app, TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        assert_eq!(body, expected, "{uri}");
+    }
+
+    let req = TestRequest::post().uri("/echo").set_payload("ping").to_request();
+    assert_eq!(call_text(&app, req).await, (StatusCode::OK, "test ping".to_string()));
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/my_struct").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "name": "Kayano", "age": 18 }));
+}
+
+#[actix_web::test]
+async fn resource_accepts_any_method() {
+    let (app, _) = init_app().await;
+    for req in [TestRequest::get(), TestRequest::post(), TestRequest::delete()] {
+        let (status, body) = call_text(&app, req.uri("/perix").to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "index resource");
+    }
+}
+
+#[actix_web::test]
+async fn counter_page_is_shared_with_named_counters() {
+    let (app, services) = init_app().await;
+    for expected in 1..=2 {
+        let (_, body) = call_text(&app, TestRequest::get().uri("/app2/index3").to_request()).await;
+        assert_eq!(body, format!("Hello from index3! Counter: {expected}"));
+    }
+    assert_eq!(services.counters.get("index3"), 2);
+}
+
+#[actix_web::test]
+async fn host_guards() {
+    let (app, _) = init_app().await;
+
+    let req = TestRequest::get().uri("/app").insert_header((header::HOST, "users.rust-lang.org")).to_request();
+    assert_eq!(call_text(&app, req).await, (StatusCode::OK, "users site".to_string()));
+
+    let req = TestRequest::get().uri("/app2").insert_header((header::HOST, "www.rust-lang.org")).to_request();
+    assert_eq!(call_text(&app, req).await, (StatusCode::OK, "www site".to_string()));
+
+    // Host不匹配时落到默认的404
+    let req = TestRequest::get().uri("/app").insert_header((header::HOST, "www.rust-lang.org")).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::NOT_FOUND, "not_found");
+}
+
+#[actix_web::test]
+async fn content_type_guard_on_user_resource() {
+    let (app, _) = init_app().await;
+    let alice = common::Auth::session(&app, "alice").await;
+
+    let req = alice
+        .apply(TestRequest::get().uri("/user/alice"))
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "alice");
+
+    // 没有Content-Type: application/json时资源不匹配
+    let (status, body) = call_json(&app, TestRequest::get().uri("/user/alice").to_request()).await;
+    assert_error(status, &body, StatusCode::NOT_FOUND, "not_found");
+}
+
+#[actix_web::test]
+async fn path_and_query_extraction() {
+    let (app, _) = init_app().await;
+
+    // 元组路径参数无法解析
+    let (status, _) = call_text(&app, TestRequest::get().uri("/path/abc/alice").to_request()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 结构体路径参数校验失败
+    let (status, body) = call_json(&app, TestRequest::get().uri("/path2/0/alice").to_request()).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "user_id");
+
+    let req = TestRequest::get().uri("/query?q=rust&lang=zh-CN").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(header::CONTENT_LANGUAGE).unwrap(), "zh");
+
+    // lang参数在/query上覆盖Accept-Language
+    let req = TestRequest::get()
+        .uri("/query?q=rust&lang=en")
+        .insert_header((header::ACCEPT_LANGUAGE, "ja"))
+        .to_request();
+    let (status, body) = call_text(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Hello from query_test! Query: rust");
+
+    // 其他路由的错误信封只按Accept-Language协商
+    let req = TestRequest::get()
+        .uri("/no-such-route?lang=en")
+        .insert_header((header::ACCEPT_LANGUAGE, "ja"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers().get(header::CONTENT_LANGUAGE).unwrap(), "ja");
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/query?q=").to_request()).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "q");
+}
+
+#[actix_web::test]
+async fn json_config_limit_and_errors() {
+    let (app, _) = init_app().await;
+
+    let req = TestRequest::post()
+        .uri("/config")
+        .set_json(json!({ "username": "alice", "email": "alice@example.com" }))
+        .to_request();
+    let (status, body) = call_text(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Hello from json_test! Username: alice, Email: alice@example.com");
+
+    // 无法解析的JSON由json_config映射为409
+    let req = TestRequest::post()
+        .uri("/config")
+        .insert_header((header::CONTENT_TYPE, "application/json"))
+        .set_payload("{not json")
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::CONFLICT, "invalid_json");
+    assert!(body["details"].is_string());
+
+    // 超过4096字节同样映射为409
+    let req = TestRequest::post()
+        .uri("/config")
+        .set_json(json!({ "username": "alice", "email": "a".repeat(5000) }))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::CONFLICT, "invalid_json");
+
+    // 能解析但校验失败
+    let req = TestRequest::post()
+        .uri("/config")
+        .set_json(json!({ "username": "alice", "email": "not-an-email" }))
+        .to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "email");
+}
+
+#[actix_web::test]
+async fn error_mapping() {
+    let (app, _) = init_app().await;
+
+    let cases = [
+        ("/first_error", StatusCode::INTERNAL_SERVER_ERROR, "my_error"),
+        ("/error/internal_error", StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        ("/error/timeout", StatusCode::REQUEST_TIMEOUT, "timeout"),
+        ("/error/bad_client_data", StatusCode::BAD_REQUEST, "bad_client_data"),
+        ("/does/not/exist", StatusCode::NOT_FOUND, "not_found"),
+    ];
+    for (uri, status_code, code) in cases {
+        let (status, body) = call_json(&app, TestRequest::get().uri(uri).to_request()).await;
+        assert_error(status, &body, status_code, code);
+    }
+
+    for (uri, status_code) in [
+        ("/error/simple_error", StatusCode::BAD_REQUEST),
+        ("/error/user_facing_error", StatusCode::INTERNAL_SERVER_ERROR),
+    ] {
+        let (status, body) = call_json(&app, TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(status, status_code, "{uri}: {body}");
+        assert!(body["code"].is_string(), "{uri}: {body}");
+    }
+
+    // 随机成功或失败，两种结果都是合法的响应
+    let (status, _) = call_text(&app, TestRequest::get().uri("/process").to_request()).await;
+    assert!(status == StatusCode::OK || status == StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[actix_web::test]
+async fn error_format_negotiation() {
+    let (app, _) = init_app().await;
+
+    let req = TestRequest::get()
+        .uri("/error/timeout")
+        .insert_header((header::ACCEPT, "application/problem+json"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+    assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], 408);
+    assert_eq!(body["code"], "timeout");
+
+    let req = TestRequest::get()
+        .uri("/error/timeout")
+        .insert_header((header::ACCEPT, "text/plain"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.headers().get(header::CONTENT_TYPE).unwrap().to_str().unwrap().starts_with("text/plain"));
+}
+
+#[actix_web::test]
+async fn conditional_validation() {
+    let (app, _) = init_app().await;
+
+    let req = TestRequest::get().uri("/form_test?contact_method=email&email=alice@example.com").to_request();
+    assert_eq!(call_text(&app, req).await, (StatusCode::OK, "处理成功: alice@example.com".to_string()));
+
+    // 选择email但没有提供email
+    let req = TestRequest::get().uri("/form_test?contact_method=email").to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::BAD_REQUEST, "validation_error");
+    assert_eq!(body["field"], "email");
+}
+
+#[actix_web::test]
+async fn request_id_is_propagated() {
+    let (app, _) = init_app().await;
+
+    let req = TestRequest::get().uri("/").insert_header(("x-request-id", "test-request-1")).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("x-request-id").unwrap(), "test-request-1");
+
+    let resp = test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+    assert!(resp.headers().contains_key("x-request-id"));
+}
+
+#[actix_web::test]
+async fn operational_endpoints() {
+    let (app, _) = init_app().await;
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/healthz").to_request()).await;
+    assert_eq!((status, body), (StatusCode::OK, json!({ "status": "ok" })));
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "ready");
+
+    let (status, body) = call_text(&app, TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("# TYPE"), "{body}");
+
+    // 测试请求没有TLS连接，也就没有客户端证书
+    let (status, body) = call_json(&app, TestRequest::get().uri("/tls/client").to_request()).await;
+    assert_error(status, &body, StatusCode::UNAUTHORIZED, "client_certificate_required");
+
+    // 不是WebSocket握手
+    let (status, _) = call_text(&app, TestRequest::get().uri("/ws").to_request()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/openapi.json").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["paths"]["/users"].is_object());
+
+    let (status, body) = call_text(&app, TestRequest::get().uri("/docs?ui=redoc").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("/openapi.json"));
+}
+
+#[actix_web::test]
+async fn readiness_fails_while_shutting_down() {
+    let (app, services) = init_app().await;
+    services.health.begin_shutdown();
+
+    let (status, body) = call_json(&app, TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "shutting_down");
+}
+
+#[actix_web::test]
+async fn rate_limit_per_route() {
+    let mut settings = test_settings();
+    settings.rate_limit.routes.push(web_learning::settings::RouteQuota {
+        route: "/hey".to_string(),
+        requests_per_minute: 1,
+        burst: 2,
+    });
+    let (app, _) = init_app_with(settings).await;
+
+    for _ in 0..2 {
+        let (status, _) = call_text(&app, TestRequest::get().uri("/hey").to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let resp = test::call_service(&app, TestRequest::get().uri("/hey").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+
+    // 其他路由不受影响
+    let (status, _) = call_text(&app, TestRequest::get().uri("/").to_request()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn rejected_bearer_tokens_are_rate_limited() {
+    let mut settings = test_settings();
+    settings.rate_limit.requests_per_minute = 1;
+    settings.rate_limit.burst = 2;
+    let (app, _) = init_app_with(settings).await;
+
+    // 伪造的令牌在认证中间件中被拒绝，同样消耗对端IP的令牌桶
+    let forged = || {
+        TestRequest::get()
+            .uri("/")
+            .insert_header((header::AUTHORIZATION, "Bearer forged.token.value"))
+            .to_request()
+    };
+    for remaining in ["1", "0"] {
+        let resp = test::call_service(&app, forged()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get("ratelimit-remaining").unwrap(), remaining);
+    }
+    let resp = test::call_service(&app, forged()).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+}
@@ -0,0 +1,155 @@
+//! 可配置虚拟主机表的集成测试
+
+mod common;
+
+// 外部库导入
+use actix_web::http::{header, StatusCode};  // 请求头和状态码
+use actix_web::test::{self, TestRequest};  // 测试请求
+
+// 内部模块导入
+use common::{assert_error, call_json, call_text, init_app_with, test_settings};
+use web_learning::config::config_plain_http;  // 明文HTTP监听器的路由
+use web_learning::settings::{
+    HttpMode, ListenerKind, ListenerSettings, Settings, StaticResponseSettings, VirtualHostSettings,
+};  // 监听器和虚拟主机配置
+use web_learning::vhost::{HostPattern, HttpsRedirect, VirtualHosts};  // 虚拟主机表和重定向目标
+
+/// 构造一个虚拟主机配置
+fn host(name: &str, patterns: &[&str]) -> VirtualHostSettings {
+    VirtualHostSettings {
+        name: name.to_string(),
+        patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+        response: None,
+    }
+}
+
+/// 使用指定虚拟主机表的测试配置
+fn settings_with(hosts: Vec<VirtualHostSettings>) -> Settings {
+    let mut settings = test_settings();
+    settings.virtual_hosts.hosts = hosts;
+    settings
+}
+
+#[actix_web::test]
+async fn wildcard_and_port_patterns_select_the_site() {
+    let settings = settings_with(vec![host("users", &["*.example.com:8443"]), host("www", &["example.com"])]);
+    let (app, _) = init_app_with(settings).await;
+
+    let get = |path: &str, host: &str| TestRequest::get().uri(path).insert_header((header::HOST, host)).to_request();
+
+    assert_eq!(call_text(&app, get("/app", "api.example.com:8443")).await.0, StatusCode::OK);
+    assert_eq!(call_text(&app, get("/app", "a.b.EXAMPLE.com:8443")).await.0, StatusCode::OK);
+    // 通配模式不匹配父域名，也不匹配其他端口（测试请求是明文HTTP，默认端口为80）
+    assert_eq!(call_text(&app, get("/app", "example.com:8443")).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(call_text(&app, get("/app", "api.example.com")).await.0, StatusCode::NOT_FOUND);
+    // 不带端口的模式匹配任意端口
+    assert_eq!(call_text(&app, get("/app2", "example.com:9000")).await.0, StatusCode::OK);
+    // 原来写死的主机名不再生效
+    assert_eq!(call_text(&app, get("/app", "users.rust-lang.org")).await.0, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn unmatched_hosts_fall_back_to_the_default_host() {
+    let mut settings = test_settings();
+    settings.virtual_hosts.default_host = Some("www".to_string());
+    let (app, _) = init_app_with(settings).await;
+
+    let req = TestRequest::get().uri("/app2").insert_header((header::HOST, "127.0.0.1:8087")).to_request();
+    assert_eq!(call_text(&app, req).await, (StatusCode::OK, "www site".to_string()));
+
+    // 匹配到其他虚拟主机时不使用默认主机
+    let req = TestRequest::get().uri("/app2").insert_header((header::HOST, "users.rust-lang.org")).to_request();
+    let (status, body) = call_json(&app, req).await;
+    assert_error(status, &body, StatusCode::NOT_FOUND, "not_found");
+}
+
+#[actix_web::test]
+async fn static_response_hosts_bypass_routing() {
+    let mut legacy = host("legacy", &["*.old.example.com"]);
+    legacy.response = Some(StaticResponseSettings {
+        status: 301,
+        body: "已迁移".to_string(),
+        location: Some("https://www.rust-lang.org/".to_string()),
+        ..StaticResponseSettings::default()
+    });
+    let (app, _) = init_app_with(settings_with(vec![legacy])).await;
+
+    for path in ["/", "/hey", "/no/such/route"] {
+        let req = TestRequest::get().uri(path).insert_header((header::HOST, "shop.old.example.com")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY, "{path}");
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "https://www.rust-lang.org/");
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/plain; charset=utf-8");
+        assert_eq!(test::read_body(resp).await, "已迁移");
+    }
+
+    // 其他主机照常路由
+    let req = TestRequest::get().uri("/hey").insert_header((header::HOST, "old.example.com")).to_request();
+    assert_eq!(call_text(&app, req).await.0, StatusCode::OK);
+}
+
+#[test]
+fn sni_must_belong_to_the_same_virtual_host() {
+    let mut settings = settings_with(vec![host("users", &["*.example.com"]), host("www", &["www.rust-lang.org"])]);
+    settings.virtual_hosts.default_host = Some("www".to_string());
+    let table = VirtualHosts::from_settings(&settings.virtual_hosts);
+
+    assert!(!table.is_misdirected("localhost", "LOCALHOST", 443));
+    // 同一个虚拟主机内的不同主机名可以复用连接
+    assert!(!table.is_misdirected("a.example.com", "b.example.com", 443));
+    assert!(table.is_misdirected("a.example.com", "www.rust-lang.org", 443));
+    // 未配置的主机不使用默认主机比较
+    assert!(table.is_misdirected("localhost", "other.local", 443));
+    assert!(table.is_misdirected("www.rust-lang.org", "other.local", 443));
+}
+
+#[test]
+fn invalid_tables_are_rejected() {
+    for pattern in ["", "example.*", "*", "a..b", "example.com:0", "example.com:http", "[::1"] {
+        assert!(HostPattern::parse(pattern).is_err(), "{pattern:?}");
+    }
+    for pattern in ["Example.com", "*.example.com", "example.com:8443", "[::1]:8443", "127.0.0.1"] {
+        assert!(HostPattern::parse(pattern).is_ok(), "{pattern:?}");
+    }
+
+    let mut settings = settings_with(vec![host("users", &["*.bad*"]), host("users", &["ok.example.com"])]);
+    settings.virtual_hosts.default_host = Some("missing".to_string());
+    let message = settings.validate().unwrap_err().to_string();
+    for expected in ["*.bad*", "名称重复", "default_host"] {
+        assert!(message.contains(expected), "{expected}: {message}");
+    }
+}
+
+#[actix_web::test]
+async fn plain_http_redirects_only_known_hosts_with_308() {
+    let mut settings = settings_with(vec![host("www", &["*.example.com"])]);
+    settings.server.listeners.push(ListenerSettings {
+        kind: ListenerKind::Https,
+        bind: "[::1]:9443".to_string(),
+        mode: HttpMode::default(),
+        workers: None,
+        max_connections: None,
+        max_connection_rate: None,
+    });
+    let redirect = HttpsRedirect::from_settings(&settings);
+    let app = test::init_service(actix_web::App::new().configure(config_plain_http(HttpMode::Redirect, redirect))).await;
+
+    let post = |uri: &str, host: &str| TestRequest::post().uri(uri).insert_header((header::HOST, host)).to_request();
+    // 端口换成server.bind的端口，保留路径和查询字符串；308要求客户端保留方法和请求体
+    for (host, location) in [
+        ("api.Example.com:8080", "https://api.example.com:8087/login?next=%2F"),
+        ("127.0.0.1", "https://127.0.0.1:8087/login?next=%2F"),
+        ("[::1]:8080", "https://[::1]:8087/login?next=%2F"),
+    ] {
+        let resp = test::call_service(&app, post("/login?next=%2F", host)).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT, "{host}");
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), location, "{host}");
+    }
+
+    // 不属于本服务器的主机不会被重定向
+    for host in ["evil.com", "example.com.evil.com", "10.0.0.1", "example.com"] {
+        let resp = test::call_service(&app, post("/login", host)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{host}");
+        assert!(resp.headers().get(header::LOCATION).is_none(), "{host}");
+    }
+}
@@ -0,0 +1,152 @@
+//! WebSocket房间的集成测试
+//!
+//! WebSocket需要真实的连接，这里用`actix_test`启动服务器，用`awc`作为客户端
+
+mod common;
+
+// 标准库导入
+use std::time::Duration;  // 读取消息的超时
+
+// 外部库导入
+use awc::error::WsProtocolError;  // WebSocket协议错误
+use awc::ws::{Frame, Message};  // WebSocket帧和消息
+use futures::{Sink, SinkExt, Stream, StreamExt};  // 收发消息
+use serde_json::{json, Value};  // 构造和检查JSON消息
+
+// 内部模块导入
+use common::{test_settings, PASSWORD};  // 测试配置和默认密码
+use web_learning::app::{build_app, AppServices};  // 应用工厂
+
+/// 等待一条消息的最长时间
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 启动一个真实的测试服务器
+fn start_server() -> (actix_test::TestServer, AppServices) {
+    let services = AppServices::from_settings(&test_settings(), None).expect("无法创建应用组件");
+    let factory = services.clone();
+    (actix_test::start(move || build_app(&factory)), services)
+}
+
+/// 发送一条JSON文本消息
+async fn send<S>(conn: &mut S, message: Value)
+where
+    S: Sink<Message, Error = WsProtocolError> + Unpin,
+{
+    conn.send(Message::Text(message.to_string().into())).await.expect("发送失败");
+}
+
+/// 在`wait`内读取下一条文本消息，跳过心跳；超时返回None
+async fn try_recv<S>(conn: &mut S, wait: Duration) -> Option<Value>
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    loop {
+        let frame = tokio::time::timeout(wait, conn.next()).await.ok()?;
+        match frame.expect("连接已关闭").expect("协议错误") {
+            Frame::Text(text) => return Some(serde_json::from_slice(&text).expect("消息不是JSON")),
+            Frame::Ping(_) | Frame::Pong(_) => continue,
+            other => panic!("意外的帧: {other:?}"),
+        }
+    }
+}
+
+/// 读取下一条文本消息，超时则panic
+async fn recv<S>(conn: &mut S) -> Value
+where
+    S: Stream<Item = Result<Frame, WsProtocolError>> + Unpin,
+{
+    try_recv(conn, RECV_TIMEOUT).await.expect("等待消息超时")
+}
+
+#[actix_web::test]
+async fn join_publish_broadcast_and_leave() {
+    let (mut srv, services) = start_server();
+    let mut alice = srv.ws_at("/ws").await.expect("握手失败");
+    let mut bob = srv.ws_at("/ws").await.expect("握手失败");
+
+    for (id, conn) in [(1, &mut alice), (2, &mut bob)] {
+        send(conn, json!({ "type": "join", "id": id, "room": "lobby" })).await;
+        assert_eq!(recv(conn).await, json!({ "type": "ack", "id": id }));
+    }
+    assert_eq!(services.rooms.member_count("lobby"), 2);
+
+    // 发送者只收到ack，房间内的其他成员收到消息
+    send(&mut alice, json!({ "type": "publish", "id": 3, "room": "lobby", "data": { "text": "hi" } })).await;
+    assert_eq!(recv(&mut alice).await, json!({ "type": "ack", "id": 3 }));
+    assert_eq!(
+        recv(&mut bob).await,
+        json!({ "type": "message", "room": "lobby", "from": null, "data": { "text": "hi" } })
+    );
+
+    // 服务端推送发给所有成员
+    assert_eq!(services.rooms.broadcast("lobby", json!("news")), 2);
+    for conn in [&mut alice, &mut bob] {
+        assert_eq!(recv(conn).await["data"], "news");
+    }
+
+    // 离开后不再收到房间内的消息，也不能再发送
+    send(&mut bob, json!({ "type": "leave", "id": 4, "room": "lobby" })).await;
+    assert_eq!(recv(&mut bob).await, json!({ "type": "ack", "id": 4 }));
+    assert_eq!(services.rooms.member_count("lobby"), 1);
+    send(&mut alice, json!({ "type": "publish", "id": 5, "room": "lobby", "data": "bye" })).await;
+    assert_eq!(recv(&mut alice).await, json!({ "type": "ack", "id": 5 }));
+    assert_eq!(try_recv(&mut bob, Duration::from_millis(200)).await, None);
+
+    send(&mut bob, json!({ "type": "publish", "id": 6, "room": "lobby", "data": "late" })).await;
+    let reply = recv(&mut bob).await;
+    assert_eq!((&reply["type"], &reply["id"], &reply["code"]), (&json!("error"), &json!(6), &json!("not_joined")));
+}
+
+#[actix_web::test]
+async fn malformed_messages_are_rejected_without_closing() {
+    let (mut srv, _) = start_server();
+    let mut conn = srv.ws_at("/ws").await.expect("握手失败");
+
+    for text in ["not json", r#"{"type": "shout", "room": "lobby"}"#, r#"{"type": "join"}"#] {
+        conn.send(Message::Text(text.into())).await.unwrap();
+        let reply = recv(&mut conn).await;
+        assert_eq!(reply["type"], "error", "{text}: {reply}");
+        assert_eq!(reply["code"], "invalid_message", "{text}: {reply}");
+        assert_eq!(reply["id"], Value::Null, "{text}: {reply}");
+    }
+
+    conn.send(Message::Binary("{}".into())).await.unwrap();
+    assert_eq!(recv(&mut conn).await["code"], "invalid_message");
+
+    send(&mut conn, json!({ "type": "join", "id": 1, "room": "bad room!" })).await;
+    assert_eq!(recv(&mut conn).await["code"], "invalid_room");
+
+    // 出错之后连接仍然可用
+    send(&mut conn, json!({ "type": "join", "id": 2, "room": "lobby" })).await;
+    assert_eq!(recv(&mut conn).await, json!({ "type": "ack", "id": 2 }));
+}
+
+#[actix_web::test]
+async fn messages_carry_the_authenticated_sender() {
+    let (mut srv, _) = start_server();
+    let mut resp = srv
+        .post("/register")
+        .send_form(&[("username", "alice"), ("email", "alice@example.com"), ("password", PASSWORD)])
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "注册失败: {:?}", resp.body().await);
+    let pair: Value = srv
+        .post("/token")
+        .send_json(&json!({ "username": "alice", "password": PASSWORD }))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = pair["access_token"].as_str().expect("缺少access_token");
+
+    let (_, mut alice) = awc::Client::new().ws(srv.url("/ws")).bearer_auth(token).connect().await.expect("握手失败");
+    let mut guest = srv.ws_at("/ws").await.expect("握手失败");
+    send(&mut alice, json!({ "type": "join", "room": "lobby" })).await;
+    assert_eq!(recv(&mut alice).await["type"], "ack");
+    send(&mut guest, json!({ "type": "join", "room": "lobby" })).await;
+    assert_eq!(recv(&mut guest).await["type"], "ack");
+
+    send(&mut alice, json!({ "type": "publish", "room": "lobby", "data": "hello" })).await;
+    assert_eq!(recv(&mut guest).await["from"], "alice");
+}